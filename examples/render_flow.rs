@@ -1,35 +1,59 @@
-use cargo_graph::{CStyleFlowchartRenderer, FlowGraph, NodeType, GraphRenderer};
+use cargo_graph::{
+    analyze_functions, CStyleFlowchartRenderer, EdgeKind, FlowGraph, FunctionCollectorPass, GraphConfig, GraphRenderer,
+    NodeType, ParserPass,
+};
 use anyhow::Result;
 
+// 用来演示 analyze_functions 的源码，只为其中的 render 函数建图
+const SOURCE: &str = r#"
+fn render(node: &Node) -> Style {
+    let style = node.style();
+    if style.is_basic() {
+        style.rounded()
+    } else {
+        style.filled()
+    }
+}
+
+fn unused() {}
+"#;
+
 fn main() -> Result<()> {
     let renderer = CStyleFlowchartRenderer::default();
+
+    // 手工构建控制流图
     let mut graph = FlowGraph::new();
-    
-    // 创建节点
-    let start = graph.add_node(NodeType::Start("render".to_string()));
+    let start = graph.add_node(NodeType::Start("render".to_string(), false));
     let style = graph.add_node(NodeType::BasicBlock("获取样式".to_string()));
     let cond = graph.add_node(NodeType::Condition("是否为基本块?".to_string()));
     let basic = graph.add_node(NodeType::BasicBlock("应用圆角样式".to_string()));
     let other = graph.add_node(NodeType::BasicBlock("应用填充样式".to_string()));
-    let end = graph.add_node(NodeType::End("render".to_string()));
-    
-    // 添加边
-    graph.add_edge(start, style, "开始".to_string());
-    graph.add_edge(style, cond, "获取完成".to_string());
-    graph.add_edge(cond, basic, "是".to_string());
-    graph.add_edge(cond, other, "否".to_string());
-    graph.add_edge(basic, end, "完成".to_string());
-    graph.add_edge(other, end, "完成".to_string());
-    
-    // 渲染并保存为SVG
-    let dot = graph.render(&renderer)?;
-    std::fs::write("render_flow.dot", dot)?;
-    
+    let end = graph.add_node(NodeType::End("render".to_string(), false));
+
+    graph.add_edge(start, style, EdgeKind::Next);
+    graph.add_edge(style, cond, EdgeKind::EnterCondition);
+    graph.add_edge(cond, basic, EdgeKind::True);
+    graph.add_edge(cond, other, EdgeKind::False);
+    graph.add_edge(basic, end, EdgeKind::Return(None));
+    graph.add_edge(other, end, EdgeKind::Return(None));
+    std::fs::write("render_flow.dot", renderer.render(&graph)?)?;
+
+    // 从源码收集函数，筛选后再建图
+    let ast = ParserPass::parse(SOURCE)?;
+    let functions = FunctionCollectorPass::collect(&ast)
+        .into_iter()
+        .filter(|function| function.path.ends_with("::render"))
+        .collect();
+    let analyzed = analyze_functions(functions, Some(SOURCE), &GraphConfig::default());
+    std::fs::write("render_flow_analyzed.dot", renderer.render(&analyzed)?)?;
+
     // 使用graphviz转换为SVG
-    std::process::Command::new("dot")
-        .args(["-Tsvg", "render_flow.dot", "-o", "render_flow.svg"])
-        .status()?;
-        
-    println!("流程图已生成: render_flow.svg");
+    for name in ["render_flow", "render_flow_analyzed"] {
+        std::process::Command::new("dot")
+            .args(["-Tsvg", &format!("{name}.dot"), "-o", &format!("{name}.svg")])
+            .status()?;
+    }
+
+    println!("流程图已生成: render_flow.svg, render_flow_analyzed.svg");
     Ok(())
 }
@@ -0,0 +1,69 @@
+use crate::graph::FunctionOverride;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+// 提交到仓库中的默认设置：crate 根目录下的 cargo-graph.toml，或 Cargo.toml 中的
+// [package.metadata.cargo-graph] / [workspace.metadata.cargo-graph]。命令行参数优先于这里的值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub style: Option<String>,
+    pub format: Option<String>,
+    pub renderer: Option<String>,
+    // 内置主题名称或主题文件，文件路径相对于 crate 根目录
+    pub theme: Option<String>,
+    // 内置语言名称或标签文件，文件路径相对于 crate 根目录
+    pub lang: Option<String>,
+    pub link_template: Option<String>,
+    pub include_tests: Option<bool>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub block_granularity: Option<String>,
+    pub max_merged: Option<usize>,
+    pub html_labels: Option<bool>,
+    pub group_declarations: Option<bool>,
+    pub expand_boolean_ops: Option<bool>,
+    pub flat_else_if: Option<bool>,
+    pub show_recursion: Option<bool>,
+    pub highlight_loops: Option<bool>,
+    pub overrides: Vec<FunctionOverride>,
+}
+
+impl ProjectConfig {
+    pub const FILE_NAME: &'static str = "cargo-graph.toml";
+
+    // 两处都没有设置时返回 None；cargo-graph.toml 存在时不再读取 Cargo.toml
+    pub fn load(crate_root: &Path) -> Result<Option<Self>> {
+        let path = crate_root.join(Self::FILE_NAME);
+        let config = if path.is_file() {
+            let source = std::fs::read_to_string(&path)?;
+            toml::from_str(&source).with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            let Ok(manifest) = std::fs::read_to_string(crate_root.join("Cargo.toml")) else {
+                return Ok(None);
+            };
+            let manifest: toml::Table = manifest.parse().context("Failed to parse Cargo.toml")?;
+            let metadata = ["package", "workspace"].iter()
+                .filter_map(|section| manifest.get(*section)?.get("metadata")?.get("cargo-graph"))
+                .next();
+            match metadata {
+                Some(metadata) => metadata.clone().try_into()
+                    .context("Failed to parse [package.metadata.cargo-graph] in Cargo.toml")?,
+                None => return Ok(None),
+            }
+        };
+        Ok(Some(Self::resolve_paths(config, crate_root)))
+    }
+
+    // 主题和标签文件相对于 crate 根目录，与运行时的当前目录无关
+    fn resolve_paths(mut config: Self, crate_root: &Path) -> Self {
+        for spec in [&mut config.theme, &mut config.lang].into_iter().flatten() {
+            let path = crate_root.join(&*spec);
+            if path.is_file() {
+                *spec = path.to_string_lossy().into_owned();
+            }
+        }
+        config
+    }
+}
@@ -0,0 +1,62 @@
+use petgraph::graph::NodeIndex;
+use crate::graph::{EdgeKind, NodeType, SourceSpan};
+
+// 单个函数构建期间使用的 Vec 缓冲区，函数分析完成后一次性写入 FlowGraph。
+// 节点索引是缓冲区内的局部索引，缓冲区在函数之间复用以避免重复分配。
+#[derive(Debug, Default)]
+pub struct GraphArena {
+    pub(crate) nodes: Vec<NodeType>,
+    pub(crate) edges: Vec<(NodeIndex, NodeIndex, EdgeKind)>,
+    // 与 nodes 一一对应的源码范围
+    pub(crate) spans: Vec<Option<SourceSpan>>,
+    // 正在分析的语句或表达式的范围，add_node 创建的节点使用这个范围
+    span: Option<SourceSpan>,
+}
+
+impl GraphArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, node_type: NodeType) -> NodeIndex {
+        self.add_node_at(node_type, self.span)
+    }
+
+    // 合并点、循环结束等辅助节点没有对应的源码
+    pub fn add_node_at(&mut self, node_type: NodeType, span: Option<SourceSpan>) -> NodeIndex {
+        self.nodes.push(node_type);
+        self.spans.push(span);
+        NodeIndex::new(self.nodes.len() - 1)
+    }
+
+    // 设置之后创建的节点的源码范围，返回之前的范围以便恢复
+    pub fn set_span(&mut self, span: Option<SourceSpan>) -> Option<SourceSpan> {
+        std::mem::replace(&mut self.span, span)
+    }
+
+    pub fn add_edge(&mut self, from: NodeIndex, to: NodeIndex, kind: EdgeKind) {
+        self.edges.push((from, to, kind));
+    }
+
+    // 节点合并了后续的语句（如连续的声明），范围扩展到当前的范围
+    pub fn extend_span(&mut self, id: NodeIndex) {
+        if let (Some(span), Some(slot)) = (self.span, self.spans.get_mut(id.index())) {
+            *slot = Some(slot.map_or(span, |existing| existing.join(span)));
+        }
+    }
+
+    pub fn node(&self, id: NodeIndex) -> Option<&NodeType> {
+        self.nodes.get(id.index())
+    }
+
+    pub fn node_mut(&mut self, id: NodeIndex) -> Option<&mut NodeType> {
+        self.nodes.get_mut(id.index())
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.edges.clear();
+        self.spans.clear();
+        self.span = None;
+    }
+}
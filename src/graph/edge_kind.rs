@@ -0,0 +1,164 @@
+use crate::graph::Labels;
+use serde::{Deserialize, Serialize};
+
+// 边的种类，FlowGraph 的边权。显示文本在样式阶段按当前语言的 Labels 生成，
+// 样式和渲染按种类匹配，不依赖标签文本
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    // 顺序执行
+    Next,
+    // 条件成立 / 不成立
+    True,
+    False,
+    // `if let`、`while let` 的模式匹配 / 不匹配
+    Matched,
+    Unmatched,
+    // 进入判断节点
+    EnterCondition,
+    // 分支执行完后到合并点
+    BranchDone,
+    EnterLoop,
+    // 循环体执行完回到循环入口
+    LoopBack,
+    // for 循环迭代结束
+    LoopExit,
+    // 带标签或带值的 break/continue 在边上显示 `'label value`
+    Break(Option<String>),
+    Continue(Option<String>),
+    // 函数末尾表达式的返回值
+    Return(Option<String>),
+    // `?` 的 Ok / Err 分支
+    Ok,
+    Err,
+    // await 之后恢复执行
+    Resume,
+    // 类型为 `!` 的语句
+    Diverge,
+    // match 的分支；`if .. else if` 链的分支为条件文本
+    Case(Option<String>),
+    // match 中兜住剩余情况的通配分支
+    Default,
+    Else,
+    Recursion,
+    // 模块图：父模块到子模块、use 依赖、循环依赖
+    Mod,
+    Use,
+    Cycle,
+    // 类型关系图
+    Implements,
+    Bound,
+    // Crate 依赖图：依赖种类（dev/build，普通依赖为空）以及是否为可选依赖
+    Dependency { kinds: Vec<String>, optional: bool },
+    // 调用图
+    Call,
+    ExternalCall,
+    // 其他显示文本固定的边
+    Custom(String),
+}
+
+impl EdgeKind {
+    // 边上显示的文本，代码和 next、return、break 等关键字不翻译
+    pub fn label(&self, labels: &Labels) -> String {
+        let keyword = |keyword: &str, detail: &Option<String>| match detail {
+            Some(detail) => format!("{} {}", keyword, detail),
+            None => keyword.to_string(),
+        };
+        match self {
+            EdgeKind::Next => "next".to_string(),
+            EdgeKind::True => labels.yes.clone(),
+            EdgeKind::False => labels.no.clone(),
+            EdgeKind::Matched => labels.matched.clone(),
+            EdgeKind::Unmatched => labels.unmatched.clone(),
+            EdgeKind::EnterCondition => labels.enter_condition.clone(),
+            EdgeKind::BranchDone => labels.branch_done.clone(),
+            EdgeKind::EnterLoop => labels.enter_loop.clone(),
+            EdgeKind::LoopBack => labels.continue_loop.clone(),
+            EdgeKind::LoopExit => labels.exit_loop.clone(),
+            EdgeKind::Break(detail) => keyword("break", detail),
+            EdgeKind::Continue(detail) => keyword("continue", detail),
+            EdgeKind::Return(detail) => keyword("return", detail),
+            EdgeKind::Ok => "Ok".to_string(),
+            EdgeKind::Err => "Err".to_string(),
+            EdgeKind::Resume => labels.resume.clone(),
+            EdgeKind::Diverge => "diverge".to_string(),
+            EdgeKind::Case(Some(cond)) => cond.clone(),
+            EdgeKind::Case(None) => "case".to_string(),
+            EdgeKind::Default => "default".to_string(),
+            EdgeKind::Else => "else".to_string(),
+            EdgeKind::Recursion => labels.recursion.clone(),
+            EdgeKind::Mod => "mod".to_string(),
+            EdgeKind::Use => "use".to_string(),
+            EdgeKind::Cycle => labels.cycle.clone(),
+            EdgeKind::Implements => "implements".to_string(),
+            EdgeKind::Bound => "bound".to_string(),
+            EdgeKind::Dependency { kinds, optional } => {
+                let optional = optional.then_some("optional");
+                optional.into_iter().chain(kinds.iter().map(String::as_str)).collect::<Vec<_>>().join(", ")
+            }
+            EdgeKind::Call | EdgeKind::ExternalCall => String::new(),
+            EdgeKind::Custom(text) => text.clone(),
+        }
+    }
+
+    // 种类名称，用于 JSON、GraphML 等导出
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EdgeKind::Next => "next",
+            EdgeKind::True => "true",
+            EdgeKind::False => "false",
+            EdgeKind::Matched => "matched",
+            EdgeKind::Unmatched => "unmatched",
+            EdgeKind::EnterCondition => "enter_condition",
+            EdgeKind::BranchDone => "branch_done",
+            EdgeKind::EnterLoop => "enter_loop",
+            EdgeKind::LoopBack => "loop_back",
+            EdgeKind::LoopExit => "loop_exit",
+            EdgeKind::Break(_) => "break",
+            EdgeKind::Continue(_) => "continue",
+            EdgeKind::Return(_) => "return",
+            EdgeKind::Ok => "ok",
+            EdgeKind::Err => "err",
+            EdgeKind::Resume => "resume",
+            EdgeKind::Diverge => "diverge",
+            EdgeKind::Case(_) => "case",
+            EdgeKind::Default => "default",
+            EdgeKind::Else => "else",
+            EdgeKind::Recursion => "recursion",
+            EdgeKind::Mod => "mod",
+            EdgeKind::Use => "use",
+            EdgeKind::Cycle => "cycle",
+            EdgeKind::Implements => "implements",
+            EdgeKind::Bound => "bound",
+            EdgeKind::Dependency { .. } => "dependency",
+            EdgeKind::Call => "call",
+            EdgeKind::ExternalCall => "external_call",
+            EdgeKind::Custom(_) => "custom",
+        }
+    }
+
+    // label 的逆过程，用于读取边权还是显示文本的旧版本历史记录；
+    // 无法识别的文本（如 else if 链的条件）原样保留为 Custom
+    pub fn parse(text: &str, labels: &Labels) -> Self {
+        let fixed = [
+            EdgeKind::Next, EdgeKind::True, EdgeKind::False, EdgeKind::Matched, EdgeKind::Unmatched,
+            EdgeKind::EnterCondition, EdgeKind::BranchDone, EdgeKind::EnterLoop, EdgeKind::LoopBack,
+            EdgeKind::LoopExit, EdgeKind::Break(None), EdgeKind::Continue(None), EdgeKind::Return(None),
+            EdgeKind::Ok, EdgeKind::Err, EdgeKind::Resume, EdgeKind::Diverge, EdgeKind::Case(None),
+            EdgeKind::Default, EdgeKind::Else, EdgeKind::Recursion,
+        ];
+        if let Some(kind) = fixed.into_iter().find(|kind| kind.label(labels) == text) {
+            return kind;
+        }
+        let detail = |keyword: &str| text.strip_prefix(keyword).and_then(|rest| rest.strip_prefix(' ')).map(str::to_string);
+        if let Some(detail) = detail("break") {
+            EdgeKind::Break(Some(detail))
+        } else if let Some(detail) = detail("continue") {
+            EdgeKind::Continue(Some(detail))
+        } else if let Some(detail) = detail("return") {
+            EdgeKind::Return(Some(detail))
+        } else {
+            EdgeKind::Custom(text.to_string())
+        }
+    }
+}
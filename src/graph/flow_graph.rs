@@ -1,29 +1,262 @@
 use petgraph::graph::{DiGraph, NodeIndex, Graph};
 use petgraph::visit::{IntoNodeReferences, EdgeRef, DfsPostOrder};
 use petgraph::Direction;
-use std::collections::{HashMap, HashSet, VecDeque};
-use crate::graph::NodeType;
+use std::collections::{HashMap, HashSet};
+use crate::graph::{EdgeKind, GraphArena, Labels, NodeType, SourceSpan};
 use crate::passes::{StylerPass, DotRendererPass};
+use crate::style::Theme;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub is_test: bool,
+    // main、#[tokio::main]、#[no_mangle] 等入口函数
+    pub is_entry: bool,
+    // 闭包或嵌套函数所在函数的完整路径
+    pub parent: Option<String>,
+    // 所在源文件
+    pub file: Option<String>,
+    // 函数签名所在行
+    pub line: Option<usize>,
+    // if/match 分支和循环体的最大嵌套层数，`else if` 不算一层
+    pub max_nesting: usize,
+    // 合并多个图时函数来自哪个分组（通常是模块名），见 FlowGraph::merge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    // 函数签名（多行时合并为一行），用作函数分组框的标签；闭包等伪函数没有签名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
 
 #[derive(Debug, Clone)]
 pub struct GraphConfig {
     pub include_tests: bool,
+    // 非空时只为名称匹配的函数构建控制流图
+    pub function_filter: Vec<String>,
+    // 非空时只为位于这些模块或类型之下的函数构建控制流图，与 function_filter 同时满足
+    pub item_paths: Vec<String>,
+    // 将 panic!/unreachable!/todo!/unimplemented! 视为函数出口
+    pub panic_as_exit: bool,
+    // 按照调用图上到入口函数的距离为函数着色
+    pub reachability: bool,
+    // 函数完整路径 -> 到最近入口函数的调用距离，不在表中的函数不可达
+    pub entry_distances: HashMap<String, usize>,
+    // 按最近的修改频率为函数开始节点着色
+    pub churn: bool,
+    // 函数完整路径 -> 最近修改过它的提交数
+    pub function_churn: HashMap<String, usize>,
+    // 使用带语法高亮的 HTML-like 标签显示代码
+    pub html_labels: bool,
+    // 把连续的 let 声明折叠成一个 "declarations (n)" 节点
+    pub group_declarations: bool,
+    // 把 if 条件中的 `&&`/`||` 拆成按短路求值顺序连接的多个条件节点
+    pub expand_boolean_ops: bool,
+    // 调用自身的语句用虚线连回函数开始节点
+    pub show_recursion: bool,
+    // 标出自然循环的回边和循环头，见 FlowGraph::natural_loops
+    pub highlight_loops: bool,
+    // 分析前先用 cargo expand 展开宏
+    pub expand_macros: bool,
+    // 把 `if .. else if ..` 链画成一个多路判断节点
+    pub flat_else_if: bool,
+    // 基本代码块的粒度
+    pub block_granularity: BlockGranularity,
+    // merged 粒度下一个基本块最多合并的语句数，None 表示不限制
+    pub max_merged_statements: Option<usize>,
+    // 节点和边的配色方案，所有渲染器通过 StylerPass 使用
+    pub theme: Theme,
+    // 边标签和辅助节点文本的语言
+    pub labels: Labels,
+    // 节点链接的 URL 模板，{path}、{file}、{line}、{column} 替换为节点的源码位置；None 表示不生成链接
+    pub link_template: Option<String>,
+    // 按函数覆盖的分析选项，来自 cargo-graph.toml 的 [[overrides]]，多条匹配时后面的优先
+    pub function_overrides: Vec<FunctionOverride>,
+    // 按文件缓存分析结果的目录，None 表示不使用缓存，见 AnalysisCache
+    pub cache_dir: Option<std::path::PathBuf>,
+}
+
+// 匹配的函数使用不同的分析选项，未给出的选项沿用全局设置
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FunctionOverride {
+    // 与 --function 写法相同：完整路径或末尾几段，支持通配符
+    pub function: String,
+    // 不为匹配的函数构建控制流图
+    pub skip: bool,
+    pub group_declarations: Option<bool>,
+    pub expand_boolean_ops: Option<bool>,
+    pub flat_else_if: Option<bool>,
+    pub show_recursion: Option<bool>,
+    pub panic_as_exit: Option<bool>,
+}
+
+impl FunctionOverride {
+    pub fn matches(&self, name: &str) -> bool {
+        let segments: Vec<&str> = name.split("::").collect();
+        (0..segments.len()).any(|start| glob_match(&self.function, &segments[start..].join("::")))
+    }
+
+    fn apply(&self, config: &mut GraphConfig) {
+        let options = [
+            (self.group_declarations, &mut config.group_declarations),
+            (self.expand_boolean_ops, &mut config.expand_boolean_ops),
+            (self.flat_else_if, &mut config.flat_else_if),
+            (self.show_recursion, &mut config.show_recursion),
+            (self.panic_as_exit, &mut config.panic_as_exit),
+        ];
+        for (value, option) in options {
+            if let Some(value) = value {
+                *option = value;
+            }
+        }
+    }
+}
+
+// 基本代码块的粒度：从细到粗依次是 expression、statement、merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockGranularity {
+    // 每条语句一个节点，语句中的函数和方法调用按求值顺序各自成为一个节点
+    Expression,
+    // 每条语句一个节点，不合并
+    Statement,
+    // 合并连续的基本块
+    #[default]
+    Merged,
+}
+
+impl std::str::FromStr for BlockGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "expression" => Ok(Self::Expression),
+            "statement" => Ok(Self::Statement),
+            "merged" => Ok(Self::Merged),
+            _ => Err(format!("未知的粒度 `{}`，可选值：statement、merged、expression", s)),
+        }
+    }
 }
 
 impl Default for GraphConfig {
     fn default() -> Self {
         Self {
             include_tests: false,
+            function_filter: Vec::new(),
+            item_paths: Vec::new(),
+            panic_as_exit: true,
+            reachability: false,
+            entry_distances: HashMap::new(),
+            churn: false,
+            function_churn: HashMap::new(),
+            html_labels: false,
+            group_declarations: false,
+            expand_boolean_ops: false,
+            show_recursion: false,
+            highlight_loops: false,
+            expand_macros: false,
+            flat_else_if: false,
+            block_granularity: BlockGranularity::Merged,
+            max_merged_statements: None,
+            theme: Theme::default(),
+            labels: Labels::default(),
+            link_template: None,
+            function_overrides: Vec::new(),
+            cache_dir: None,
         }
     }
 }
 
+impl GraphConfig {
+    // 应用 function_overrides 后某个函数的分析选项；没有匹配的覆盖时返回 None，沿用当前设置
+    pub fn for_function(&self, name: &str) -> Option<GraphConfig> {
+        let mut overrides = self.function_overrides.iter().filter(|item| item.matches(name)).peekable();
+        overrides.peek()?;
+        let mut config = self.clone();
+        for item in overrides {
+            item.apply(&mut config);
+        }
+        Some(config)
+    }
+
+    // 是否有覆盖要求跳过该函数
+    pub fn skips_function(&self, name: &str) -> bool {
+        self.function_overrides.iter().any(|item| item.skip && item.matches(name))
+    }
+
+    // 节点源码位置的链接；{path} 为绝对路径，{file} 为显示用的相对路径
+    pub fn node_link(&self, file: &str, span: SourceSpan) -> Option<String> {
+        let template = self.link_template.as_deref()?;
+        let path = std::path::absolute(file).unwrap_or_else(|_| file.into());
+        Some(
+            template
+                .replace("{path}", &path.to_string_lossy().replace('\\', "/"))
+                .replace("{file}", file)
+                .replace("{line}", &span.line.to_string())
+                .replace("{column}", &span.column.to_string()),
+        )
+    }
+
+    // 函数过滤条件可以是完整路径（crate::module::Type::new）或末尾的几段（Type::new、new）；
+    // 路径过滤条件是完整路径中连续的几段，匹配的模块、类型或函数之下的所有函数都会保留。
+    // 两者都支持 `*`（任意字符，可以跨越 `::`）和 `?` 通配符，例如 `MyType::*`、`parse_*`
+    pub fn matches_function(&self, name: &str) -> bool {
+        let segments = &name.split("::").collect::<Vec<_>>();
+        let suffixes = |end: usize| (0..end).map(move |start| segments[start..end].join("::"));
+
+        let function = self.function_filter.is_empty() || self.function_filter.iter()
+            .any(|filter| suffixes(segments.len()).any(|suffix| glob_match(filter, &suffix)));
+        let item = self.item_paths.is_empty() || self.item_paths.iter()
+            .any(|filter| (1..=segments.len()).flat_map(suffixes).any(|path| glob_match(filter, &path)));
+        function && item
+    }
+}
+
+// 简单的通配符匹配：`*` 匹配任意长度的字符，`?` 匹配一个字符
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    // 上一个 `*` 在 pattern 中的位置，以及它当时对应的 text 位置
+    let (mut p, mut t, mut star) = (0, 0, None);
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // 回到上一个 `*`，让它多匹配一个字符
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// 写入磁盘缓存的控制流图，不含配置，读取时使用当前的配置。边按编号顺序保存，恢复后编号不变
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct StoredGraph {
+    nodes: Vec<NodeType>,
+    edges: Vec<(usize, usize, EdgeKind)>,
+    node_owner: Vec<Option<usize>>,
+    node_spans: Vec<Option<SourceSpan>>,
+    functions: Vec<FunctionInfo>,
+}
+
 #[derive(Clone)]
 pub struct FlowGraph {
-    pub(crate) graph: DiGraph<NodeType, String>,
+    pub(crate) graph: DiGraph<NodeType, EdgeKind>,
     #[allow(dead_code)]
     node_map: HashMap<String, NodeIndex>,
     config: GraphConfig,
+    // 与 graph 节点一一对应的所属函数编号，删除节点时与 petgraph 一样交换移除
+    node_owner: Vec<Option<usize>>,
+    // 与 graph 节点一一对应的源码范围，删除节点时同样交换移除
+    node_spans: Vec<Option<SourceSpan>>,
+    functions: Vec<FunctionInfo>,
 }
 
 impl Default for FlowGraph {
@@ -38,6 +271,9 @@ impl FlowGraph {
             graph: DiGraph::new(),
             node_map: HashMap::new(),
             config: GraphConfig::default(),
+            node_owner: Vec::new(),
+            node_spans: Vec::new(),
+            functions: Vec::new(),
         }
     }
 
@@ -46,233 +282,262 @@ impl FlowGraph {
             graph: DiGraph::new(),
             node_map: HashMap::new(),
             config,
+            node_owner: Vec::new(),
+            node_spans: Vec::new(),
+            functions: Vec::new(),
         }
     }
 
     pub fn add_node(&mut self, node_type: NodeType) -> NodeIndex {
-        let id = self.graph.add_node(node_type);
-        id
+        self.node_owner.push(None);
+        self.node_spans.push(None);
+        self.graph.add_node(node_type)
     }
 
-    pub fn add_edge(&mut self, from: NodeIndex, to: NodeIndex, label: String) {
-        self.graph.add_edge(from, to, label);
+    fn remove_node(&mut self, node_id: NodeIndex) {
+        if self.graph.remove_node(node_id).is_some() {
+            self.node_owner.swap_remove(node_id.index());
+            self.node_spans.swap_remove(node_id.index());
+        }
     }
 
-    pub fn to_dot(&self) -> String {
-        let mut merged_graph = self.clone();
-        merged_graph.merge_basic_blocks();
-        let styled = StylerPass::apply_style(&merged_graph);
-        DotRendererPass::render(&styled)
+    pub fn add_edge(&mut self, from: NodeIndex, to: NodeIndex, kind: EdgeKind) {
+        self.graph.add_edge(from, to, kind);
     }
 
-    fn merge_basic_blocks(&mut self) {
-        let mut merged: HashSet<NodeIndex> = HashSet::new();
-        let mut to_merge: VecDeque<NodeIndex> = VecDeque::new();
-        let mut function_starts: HashSet<NodeIndex> = HashSet::new();
-
-        // 首先收集所有函数的开始节点
-        for node_id in self.graph.node_indices() {
-            if let Some(NodeType::Start(_, _)) = self.graph.node_weight(node_id) {
-                function_starts.insert(node_id);
-            }
+    // 把另一个控制流图（通常来自另一个文件）并入当前图：节点编号和函数编号依次后移，
+    // 并入的函数记录来源的命名空间，渲染时按命名空间分组；已经记录过命名空间的保持不变。配置沿用当前图的
+    pub fn merge(&mut self, other: FlowGraph, namespace: &str) {
+        let node_offset = self.graph.node_count();
+        let function_offset = self.functions.len();
+        self.functions.extend(other.functions.into_iter().map(|mut info| {
+            info.namespace.get_or_insert_with(|| namespace.to_string());
+            info
+        }));
+        self.node_owner.extend(other.node_owner.into_iter().map(|owner| owner.map(|function| function + function_offset)));
+        self.node_spans.extend(other.node_spans);
+
+        let (nodes, edges) = other.graph.into_nodes_edges();
+        self.graph.reserve_nodes(nodes.len());
+        self.graph.reserve_edges(edges.len());
+        for node in nodes {
+            self.graph.add_node(node.weight);
         }
+        for edge in edges {
+            self.graph.add_edge(
+                NodeIndex::new(edge.source().index() + node_offset),
+                NodeIndex::new(edge.target().index() + node_offset),
+                edge.weight,
+            );
+        }
+    }
 
-        // 收集可合并的基本块
-        for node_id in self.graph.node_indices() {
-            if !merged.contains(&node_id) && 
-               !function_starts.contains(&node_id) && 
-               self.is_mergeable_block(node_id) {
-                to_merge.push_back(node_id);
-            }
+    // 将缓冲区中的函数整体写入图中，返回局部索引到全局索引的偏移
+    pub fn append_arena(&mut self, arena: &mut GraphArena, info: FunctionInfo) -> usize {
+        let offset = self.graph.node_count();
+        let function_id = self.functions.len();
+        self.functions.push(info);
+        self.graph.reserve_nodes(arena.nodes.len());
+        self.graph.reserve_edges(arena.edges.len());
+        self.node_owner.reserve(arena.nodes.len());
+        self.node_spans.reserve(arena.nodes.len());
+
+        for node_type in arena.nodes.drain(..) {
+            self.graph.add_node(node_type);
+            self.node_owner.push(Some(function_id));
         }
+        self.node_spans.append(&mut arena.spans);
+        for (from, to, kind) in arena.edges.drain(..) {
+            self.graph.add_edge(
+                NodeIndex::new(from.index() + offset),
+                NodeIndex::new(to.index() + offset),
+                kind,
+            );
+        }
+
+        offset
+    }
+
+    pub fn to_dot(&self) -> String {
+        let styled = StylerPass::apply_style(self);
+        DotRendererPass::render(&styled)
+    }
 
-        while let Some(start_node) = to_merge.pop_front() {
-            if merged.contains(&start_node) {
+    // 合并连续基本块后的副本，只用于显示；统计和基准比较仍使用逐条语句的图
+    pub fn merged_blocks(&self) -> FlowGraph {
+        let mut merged_graph = self.clone();
+        merged_graph.merge_basic_blocks();
+        merged_graph
+    }
+
+    fn merge_basic_blocks(&mut self) {
+        // 先找出所有可以合并的链，再统一修改图：删除节点会交换索引，边找边删会删错节点
+        let mut chains: Vec<Vec<NodeIndex>> = Vec::new();
+        for head in self.graph.node_indices() {
+            if !self.is_basic_block(head) || self.mergeable_predecessor(head).is_some() {
                 continue;
             }
-
-            // 收集可合并的序列
-            let sequence = self.collect_mergeable_sequence(start_node, &merged, &function_starts);
-            
-            if sequence.len() > 1 {
-                // 验证序列的有效性
-                if self.validate_merge_sequence(&sequence) {
-                    self.merge_sequence(&sequence);
-                    merged.extend(sequence.iter());
+            let mut chain = vec![head];
+            let mut current = head;
+            while let Some(next) = self.mergeable_successor(current) {
+                if next == head {
+                    break;
+                }
+                // 达到合并语句数上限时从下一个节点开始新的一块
+                if self.config.max_merged_statements.is_some_and(|max| chain.len() >= max) {
+                    chains.push(std::mem::take(&mut chain));
                 }
+                chain.push(next);
+                current = next;
             }
+            chains.push(chain);
         }
-    }
 
-    fn validate_merge_sequence(&self, sequence: &[NodeIndex]) -> bool {
-        // 检查序列中的所有节点是否都存在且是基本块
-        for &node_id in sequence {
-            if self.graph.node_weight(node_id).is_none() {
-                return false;
-            }
-            if !matches!(self.graph.node_weight(node_id), Some(NodeType::BasicBlock(_))) {
-                return false;
+        let mut removed = Vec::new();
+        for chain in chains.into_iter().filter(|chain| chain.len() > 1) {
+            let first = chain[0];
+            let last = chain[chain.len() - 1];
+            let merged_content = chain.iter()
+                .filter_map(|&node_id| match self.graph.node_weight(node_id) {
+                    Some(NodeType::BasicBlock(content)) => Some(content.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.graph[first] = NodeType::BasicBlock(merged_content);
+            self.node_spans[first.index()] = chain.iter()
+                .filter_map(|node_id| self.node_spans[node_id.index()])
+                .reduce(SourceSpan::join);
+
+            // 链中最后一个节点的出边改为从第一个节点出发
+            let out_edges: Vec<_> = self.graph.edges_directed(last, Direction::Outgoing)
+                .map(|e| (e.target(), e.weight().clone()))
+                .collect();
+            for (target, weight) in out_edges {
+                self.graph.add_edge(first, target, weight);
             }
+            removed.extend_from_slice(&chain[1..]);
         }
 
-        // 检查序列中的节点是否正确连接
-        for window in sequence.windows(2) {
-            let current = window[0];
-            let next = window[1];
-            
-            // 验证边的连接
-            let has_edge = self.graph.edges_directed(current, Direction::Outgoing)
-                .any(|e| e.target() == next);
-            
-            if !has_edge {
-                return false;
-            }
+        // 从大到小删除，被交换到空位的节点总是当前最后一个，不会影响还没删除的节点
+        removed.sort_unstable_by(|a, b| b.cmp(a));
+        for node_id in removed {
+            self.remove_node(node_id);
         }
+    }
 
-        true
+    fn is_basic_block(&self, node_id: NodeIndex) -> bool {
+        matches!(self.graph.node_weight(node_id), Some(NodeType::BasicBlock(_)))
     }
 
-    fn collect_mergeable_sequence(
-        &self,
-        start_node: NodeIndex,
-        merged: &HashSet<NodeIndex>,
-        function_starts: &HashSet<NodeIndex>
-    ) -> Vec<NodeIndex> {
-        let mut sequence = Vec::new();
-        let mut current = start_node;
+    // 节点只有一条 Next 出边，指向只有这一条入边的基本块时，两者可以合并
+    fn mergeable_successor(&self, node_id: NodeIndex) -> Option<NodeIndex> {
+        let mut edges = self.graph.edges_directed(node_id, Direction::Outgoing);
+        let edge = edges.next()?;
+        if edges.next().is_some() || *edge.weight() != EdgeKind::Next || !self.is_basic_block(edge.target()) {
+            return None;
+        }
+        let in_degree = self.graph.edges_directed(edge.target(), Direction::Incoming).count();
+        (in_degree == 1).then_some(edge.target())
+    }
 
-        sequence.push(current);
+    fn mergeable_predecessor(&self, node_id: NodeIndex) -> Option<NodeIndex> {
+        let mut edges = self.graph.edges_directed(node_id, Direction::Incoming);
+        let edge = edges.next()?;
+        if edges.next().is_some() || !self.is_basic_block(edge.source()) {
+            return None;
+        }
+        (self.mergeable_successor(edge.source()) == Some(node_id)).then_some(edge.source())
+    }
 
-        while let Some(next) = self.get_single_successor(current) {
-            if merged.contains(&next) || 
-               function_starts.contains(&next) || 
-               !self.is_mergeable_block(next) {
-                break;
+    pub(crate) fn get_function_nodes(&self, start_node: NodeIndex) -> HashSet<NodeIndex> {
+        let mut nodes = HashSet::new();
+        let mut stack = vec![start_node];
+        
+        while let Some(node_id) = stack.pop() {
+            if nodes.insert(node_id) {
+                for edge in self.graph.edges(node_id) {
+                    stack.push(edge.target());
+                }
             }
-            sequence.push(next);
-            current = next;
         }
-
-        sequence
+        nodes
     }
 
-    fn is_mergeable_block(&self, node_id: NodeIndex) -> bool {
-        if let Some(NodeType::BasicBlock(_)) = self.graph.node_weight(node_id) {
-            let in_degree = self.graph.edges_directed(node_id, Direction::Incoming).count();
-            let out_degree = self.graph.edges_directed(node_id, Direction::Outgoing).count();
-            
-            // 确保节点有且仅有一个前驱和一个后继
-            if in_degree != 1 || out_degree != 1 {
-                return false;
-            }
+    // 圈复杂度 E - N + 2，只计算从函数开始可达的节点和边
+    pub(crate) fn function_complexity(&self, start_node: NodeIndex) -> usize {
+        let nodes = self.get_function_nodes(start_node);
+        let edges: usize = nodes.iter().map(|&node_id| self.graph.edges(node_id).count()).sum();
+        (edges + 2).saturating_sub(nodes.len()).max(1)
+    }
 
-            // 检查前驱和后继节点的类型
-            let has_valid_neighbors = self.graph.edges_directed(node_id, Direction::Incoming)
-                .all(|e| self.is_valid_neighbor(e.source())) &&
-                self.graph.edges_directed(node_id, Direction::Outgoing)
-                .all(|e| self.is_valid_neighbor(e.target()));
+    // 按名称查找函数的圈复杂度，名称可以是完整路径或最后一段；同名函数取第一个
+    pub fn cyclomatic_complexity(&self, fn_name: &str) -> Option<usize> {
+        self.function_starts().into_iter()
+            .find(|&start| matches!(&self.graph[start], NodeType::Start(name, _)
+                if name == fn_name || name.strip_suffix(fn_name).is_some_and(|prefix| prefix.ends_with("::"))))
+            .map(|start| self.function_complexity(start))
+    }
 
-            has_valid_neighbors
-        } else {
-            false
-        }
+    pub fn function_info(&self, function: usize) -> Option<&FunctionInfo> {
+        self.functions.get(function)
     }
 
-    fn is_valid_neighbor(&self, node_id: NodeIndex) -> bool {
-        if let Some(node_type) = self.graph.node_weight(node_id) {
-            match node_type {
-                NodeType::Start(_, _) | NodeType::End(_, _) => false,
-                _ => true
-            }
-        } else {
-            false
+    // 闭包和嵌套函数的函数编号 -> 所在函数的编号；同一命名空间中的同名函数取第一个
+    pub fn function_parents(&self) -> HashMap<usize, usize> {
+        let mut by_name: HashMap<(Option<&str>, &str), usize> = HashMap::new();
+        for (id, info) in self.functions.iter().enumerate() {
+            by_name.entry((info.namespace.as_deref(), info.name.as_str())).or_insert(id);
         }
+        self.functions.iter()
+            .enumerate()
+            .filter_map(|(id, info)| {
+                let parent = by_name.get(&(info.namespace.as_deref(), info.parent.as_deref()?)).copied()?;
+                (parent != id).then_some((id, parent))
+            })
+            .collect()
     }
 
-    fn get_single_successor(&self, node_id: NodeIndex) -> Option<NodeIndex> {
-        let mut successors = self.graph.neighbors_directed(node_id, Direction::Outgoing);
-        let next = successors.next();
-        if successors.next().is_none() {
-            next
-        } else {
-            None
-        }
+    pub fn function_of(&self, node_id: NodeIndex) -> Option<usize> {
+        self.node_owner.get(node_id.index()).copied().flatten()
     }
 
-    fn merge_sequence(&mut self, sequence: &[NodeIndex]) {
-        if sequence.is_empty() {
-            return;
-        }
+    // 节点对应的源码范围；合并后的基本块覆盖其中所有语句
+    pub fn node_span(&self, node_id: NodeIndex) -> Option<SourceSpan> {
+        self.node_spans.get(node_id.index()).copied().flatten()
+    }
 
-        // 保存所有需要的边信息
-        let first = sequence[0];
-        let last = *sequence.last().unwrap();
-        
-        // 收集入边（除了第一个节点的）
-        let in_edges: Vec<_> = sequence.iter().skip(1)
-            .flat_map(|&node_id| {
-                self.graph.edges_directed(node_id, Direction::Incoming)
-                    .map(|e| (e.source(), e.target(), e.weight().clone()))
-                    .collect::<Vec<_>>()
-            })
-            .collect();
+    // 节点所在的源文件，即所属函数的文件
+    pub fn node_file(&self, node_id: NodeIndex) -> Option<&str> {
+        self.function_of(node_id)
+            .and_then(|function| self.function_info(function))
+            .and_then(|info| info.file.as_deref())
+    }
 
-        // 收集出边（除了最后一个节点的）
-        let out_edges: Vec<_> = sequence.iter().take(sequence.len() - 1)
-            .flat_map(|&node_id| {
-                self.graph.edges_directed(node_id, Direction::Outgoing)
-                    .map(|e| (e.source(), e.target(), e.weight().clone()))
-                    .collect::<Vec<_>>()
-            })
+    // 每个节点所属的函数编号；手工添加的节点按照从 Start 节点的可达性归属
+    pub fn function_groups(&self) -> HashMap<NodeIndex, usize> {
+        let mut groups: HashMap<NodeIndex, usize> = self.graph.node_indices()
+            .filter_map(|id| self.function_of(id).map(|function| (id, function)))
             .collect();
 
-        // 合并内容
-        let mut merged_content = String::new();
-        for &node_id in sequence {
-            if let Some(NodeType::BasicBlock(content)) = self.graph.node_weight(node_id) {
-                if !merged_content.is_empty() {
-                    merged_content.push_str("\n");
-                }
-                merged_content.push_str(content);
+        let mut next_id = self.functions.len();
+        for start in self.function_starts() {
+            if groups.contains_key(&start) {
+                continue;
             }
-        }
-
-        // 更新第一个节点的内容
-        if let Some(node_weight) = self.graph.node_weight_mut(first) {
-            *node_weight = NodeType::BasicBlock(merged_content);
-        }
-
-        // 删除其他节点
-        for &node_id in &sequence[1..] {
-            self.graph.remove_node(node_id);
-        }
-
-        // 重新连接需要保留的边
-        for (source, _, weight) in in_edges {
-            if source != first && self.graph.node_weight(source).is_some() {
-                self.graph.add_edge(source, first, weight);
+            for node_id in self.get_function_nodes(start) {
+                groups.entry(node_id).or_insert(next_id);
             }
+            next_id += 1;
         }
 
-        for (_, target, weight) in out_edges {
-            if target != last && self.graph.node_weight(target).is_some() {
-                self.graph.add_edge(first, target, weight);
-            }
-        }
+        groups
     }
 
-    fn get_function_nodes(&self, start_node: NodeIndex) -> HashSet<NodeIndex> {
-        let mut nodes = HashSet::new();
-        let mut stack = vec![start_node];
-        
-        while let Some(node_id) = stack.pop() {
-            if nodes.insert(node_id) {
-                for edge in self.graph.edges(node_id) {
-                    stack.push(edge.target());
-                }
-            }
-        }
-        nodes
+    pub fn function_starts(&self) -> Vec<NodeIndex> {
+        self.graph.node_indices()
+            .filter(|&id| self.is_function_start(id))
+            .collect()
     }
 
     fn is_function_start(&self, node_id: NodeIndex) -> bool {
@@ -284,24 +549,20 @@ impl FlowGraph {
     }
 
     fn get_visible_nodes(&self) -> HashSet<NodeIndex> {
-        let mut visible_nodes = HashSet::new();
-        let mut test_function_nodes = HashSet::new();
-
-        for (id, node) in self.graph.node_references() {
-            if let NodeType::Start(_, is_test) = node {
-                if *is_test {
-                    test_function_nodes.extend(self.get_function_nodes(id));
-                }
-            }
+        if self.config.include_tests {
+            return self.graph.node_indices().collect();
         }
 
-        for (id, _) in self.graph.node_references() {
-            if self.config.include_tests || !test_function_nodes.contains(&id) {
-                visible_nodes.insert(id);
-            }
-        }
+        // 按函数归属隐藏测试函数，不可达的节点也随所属函数一起隐藏
+        let groups = self.function_groups();
+        let test_functions: HashSet<usize> = self.graph.node_references()
+            .filter(|(_, node)| matches!(node, NodeType::Start(_, true)))
+            .filter_map(|(id, _)| groups.get(&id).copied())
+            .collect();
 
-        visible_nodes
+        self.graph.node_indices()
+            .filter(|id| groups.get(id).is_none_or(|group| !test_functions.contains(group)))
+            .collect()
     }
 
     pub fn nodes(&self) -> impl Iterator<Item = (NodeIndex, &NodeType)> {
@@ -310,7 +571,7 @@ impl FlowGraph {
             .filter(move |(id, _)| visible_nodes.contains(id))
     }
 
-    pub fn edges(&self) -> impl Iterator<Item = (NodeIndex, NodeIndex, &String)> {
+    pub fn edges(&self) -> impl Iterator<Item = (NodeIndex, NodeIndex, &EdgeKind)> {
         let visible_nodes = self.get_visible_nodes();
         self.graph.edge_references()
             .filter(move |e| {
@@ -319,11 +580,133 @@ impl FlowGraph {
             .map(|e| (e.source(), e.target(), e.weight()))
     }
 
+    // 底层的 petgraph 图（包括被隐藏的测试函数），作为库使用时可以直接运行 petgraph 的图算法；
+    // 节点所属的函数和源码位置见 function_of 和 node_span
+    pub fn graph(&self) -> &DiGraph<NodeType, EdgeKind> {
+        &self.graph
+    }
+
+    // 所有函数的信息，下标即 function_of 返回的函数编号
+    pub fn functions(&self) -> &[FunctionInfo] {
+        &self.functions
+    }
+
     pub fn config(&self) -> &GraphConfig {
         &self.config
     }
 
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.config.theme = theme;
+    }
+
+    pub fn set_labels(&mut self, labels: Labels) {
+        self.config.labels = labels;
+    }
+
     pub fn set_config(&mut self, config: GraphConfig) {
         self.config = config;
     }
-} 
\ No newline at end of file
+
+    pub(crate) fn to_stored(&self) -> StoredGraph {
+        StoredGraph {
+            nodes: self.graph.node_weights().cloned().collect(),
+            edges: self.graph.edge_references()
+                .map(|edge| (edge.source().index(), edge.target().index(), edge.weight().clone()))
+                .collect(),
+            node_owner: self.node_owner.clone(),
+            node_spans: self.node_spans.clone(),
+            functions: self.functions.clone(),
+        }
+    }
+
+    pub(crate) fn from_stored(stored: StoredGraph, config: GraphConfig) -> FlowGraph {
+        let mut graph = DiGraph::with_capacity(stored.nodes.len(), stored.edges.len());
+        for node in stored.nodes {
+            graph.add_node(node);
+        }
+        for (from, to, kind) in stored.edges {
+            graph.add_edge(NodeIndex::new(from), NodeIndex::new(to), kind);
+        }
+        FlowGraph {
+            graph,
+            node_map: HashMap::new(),
+            config,
+            node_owner: stored.node_owner,
+            node_spans: stored.node_spans,
+            functions: stored.functions,
+        }
+    }
+} 
+// JSON 导出中的节点；ID 由所在文件和节点编号组成，同一份源码多次导出时保持不变
+#[derive(serde::Serialize)]
+struct SerializedNode<'a> {
+    id: String,
+    kind: &'static str,
+    label: String,
+    function: Option<&'a str>,
+    file: Option<&'a str>,
+    // 节点自身的源码范围，没有时（辅助节点）为所属函数签名所在行
+    line: Option<usize>,
+    span: Option<SourceSpan>,
+    node: &'a NodeType,
+}
+
+#[derive(serde::Serialize)]
+struct SerializedEdge {
+    id: String,
+    from: String,
+    to: String,
+    kind: &'static str,
+    label: String,
+}
+
+impl FlowGraph {
+    fn serialized_id(&self, groups: &HashMap<NodeIndex, usize>, prefix: char, node_id: NodeIndex, index: usize) -> String {
+        let file = groups.get(&node_id)
+            .and_then(|&function| self.function_info(function))
+            .and_then(|info| info.file.as_deref());
+        match file {
+            Some(file) => format!("{}#{}{}", file, prefix, index),
+            None => format!("{}{}", prefix, index),
+        }
+    }
+}
+
+impl Serialize for FlowGraph {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let groups = self.function_groups();
+        let visible_nodes = self.get_visible_nodes();
+
+        let nodes: Vec<_> = self.nodes()
+            .map(|(id, node)| {
+                let info = groups.get(&id).and_then(|&function| self.function_info(function));
+                SerializedNode {
+                    id: self.serialized_id(&groups, 'n', id, id.index()),
+                    kind: node.kind(),
+                    label: node.label(),
+                    function: info.map(|info| info.name.as_str()),
+                    file: info.and_then(|info| info.file.as_deref()),
+                    line: self.node_span(id).map(|span| span.line).or(info.and_then(|info| info.line)),
+                    span: self.node_span(id),
+                    node,
+                }
+            })
+            .collect();
+        let edges: Vec<_> = self.graph.edge_references()
+            .filter(|edge| visible_nodes.contains(&edge.source()) && visible_nodes.contains(&edge.target()))
+            .map(|edge| SerializedEdge {
+                id: self.serialized_id(&groups, 'e', edge.source(), edge.id().index()),
+                from: self.serialized_id(&groups, 'n', edge.source(), edge.source().index()),
+                to: self.serialized_id(&groups, 'n', edge.target(), edge.target().index()),
+                kind: edge.weight().kind(),
+                label: edge.weight().label(&self.config.labels),
+            })
+            .collect();
+
+        let mut state = serializer.serialize_struct("FlowGraph", 3)?;
+        state.serialize_field("functions", &self.functions)?;
+        state.serialize_field("nodes", &nodes)?;
+        state.serialize_field("edges", &edges)?;
+        state.end()
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// 分析时生成的边标签和辅助节点文本。内置 zh（默认）和 en 两套，
+// 也可以从 TOML 文件加载：文件中的值覆盖 `extends` 指定的内置语言（默认 zh）。
+// 代码本身（条件、语句）和 next、return、break、case 等关键字不翻译
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Labels {
+    // 函数开始 / 结束节点的前缀，zh 中沿用原来的 Start / End
+    pub start: String,
+    pub end: String,
+    // 条件成立 / 不成立
+    pub yes: String,
+    pub no: String,
+    // `if let`、`while let` 的模式匹配 / 不匹配
+    pub matched: String,
+    pub unmatched: String,
+    pub enter_condition: String,
+    // 分支执行完后到合并点的边
+    pub branch_done: String,
+    pub merge_point: String,
+    // `if .. else if` 链展开成的多路判断节点，`{}` 替换为条件个数
+    pub else_if_chain: String,
+    // 有兜底分支的 match 判断节点后面的标注
+    pub wildcard: String,
+    pub enter_loop: String,
+    pub continue_loop: String,
+    // for 循环迭代结束
+    pub exit_loop: String,
+    pub loop_end: String,
+    // await 之后恢复执行
+    pub resume: String,
+    pub recursion: String,
+    // 模块之间的循环依赖
+    pub cycle: String,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self::zh()
+    }
+}
+
+pub const BUILTIN_LANGUAGES: [&str; 2] = ["zh", "en"];
+
+impl Labels {
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "zh" => Some(Self::zh()),
+            "en" => Some(Self::en()),
+            _ => None,
+        }
+    }
+
+    // 参数是内置语言名称或 TOML 文件的路径
+    pub fn load(spec: &str) -> Result<Self> {
+        if let Some(labels) = Self::builtin(spec) {
+            return Ok(labels);
+        }
+        let path = Path::new(spec);
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read labels {} (built-in languages: {})", spec, BUILTIN_LANGUAGES.join(", ")))?;
+        let mut overrides: toml::Table = source.parse()
+            .with_context(|| format!("Invalid labels file: {}", path.display()))?;
+
+        let base = match overrides.remove("extends") {
+            Some(toml::Value::String(name)) => Self::builtin(&name)
+                .ok_or_else(|| anyhow!("Unknown built-in language `{}` in {}", name, path.display()))?,
+            Some(_) => bail!("`extends` in {} must be the name of a built-in language", path.display()),
+            None => Self::zh(),
+        };
+        let toml::Value::Table(mut merged) = toml::Value::try_from(&base)? else {
+            bail!("Failed to serialize the base labels");
+        };
+        merged.extend(overrides);
+        toml::Value::Table(merged).try_into()
+            .with_context(|| format!("Invalid labels file: {}", path.display()))
+    }
+
+    pub fn zh() -> Self {
+        Self {
+            start: "Start".to_string(),
+            end: "End".to_string(),
+            yes: "是".to_string(),
+            no: "否".to_string(),
+            matched: "匹配".to_string(),
+            unmatched: "不匹配".to_string(),
+            enter_condition: "进入判断".to_string(),
+            branch_done: "完成分支".to_string(),
+            merge_point: "分支合并点".to_string(),
+            else_if_chain: "if / else if 链（{} 个条件）".to_string(),
+            wildcard: "[兜底分支]".to_string(),
+            enter_loop: "进入循环".to_string(),
+            continue_loop: "继续循环".to_string(),
+            exit_loop: "退出循环".to_string(),
+            loop_end: "循环结束".to_string(),
+            resume: "恢复".to_string(),
+            recursion: "递归调用".to_string(),
+            cycle: "循环依赖".to_string(),
+        }
+    }
+
+    pub fn en() -> Self {
+        Self {
+            start: "Start".to_string(),
+            end: "End".to_string(),
+            yes: "yes".to_string(),
+            no: "no".to_string(),
+            matched: "matches".to_string(),
+            unmatched: "no match".to_string(),
+            enter_condition: "check".to_string(),
+            branch_done: "done".to_string(),
+            merge_point: "merge".to_string(),
+            else_if_chain: "if / else if ({})".to_string(),
+            wildcard: "[wildcard]".to_string(),
+            enter_loop: "enter loop".to_string(),
+            continue_loop: "next iteration".to_string(),
+            exit_loop: "exhausted".to_string(),
+            loop_end: "end of loop".to_string(),
+            resume: "resume".to_string(),
+            recursion: "recursive call".to_string(),
+            cycle: "cyclic dependency".to_string(),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::graph::{EdgeKind, FlowGraph};
+use petgraph::Direction;
+use petgraph::algo::dominators::{self, Dominators};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+// 控制流图中的自然循环：入口（循环头）支配回边的起点，
+// 循环体是能不经过循环头到达回边起点的所有节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaturalLoop {
+    pub header: NodeIndex,
+    // 回到循环头的边，continue 和循环体末尾各对应一条
+    pub back_edges: Vec<(NodeIndex, NodeIndex)>,
+    // 包含循环头本身
+    pub body: BTreeSet<NodeIndex>,
+    // 直接包含该循环的外层循环在结果中的下标
+    pub parent: Option<usize>,
+    // 嵌套深度，最外层为 1
+    pub depth: usize,
+}
+
+impl FlowGraph {
+    // 以 start（通常是函数开始节点）为根的支配树，从 start 不可达的节点不在树中
+    pub fn dominators(&self, start: NodeIndex) -> Dominators<NodeIndex> {
+        dominators::simple_fast(&self.graph, start)
+    }
+
+    // 从 start 出发可达部分中的自然循环，外层循环排在内层循环之前；同一循环头的回边合并为一个循环。
+    // 递归调用的边不算回边
+    pub fn natural_loops(&self, start: NodeIndex) -> Vec<NaturalLoop> {
+        let dominators = self.dominators(start);
+        let dominates = |header: NodeIndex, node_id: NodeIndex| {
+            dominators.dominators(node_id).is_some_and(|mut chain| chain.any(|dominator| dominator == header))
+        };
+        let mut back_edges: BTreeMap<NodeIndex, Vec<(NodeIndex, NodeIndex)>> = BTreeMap::new();
+        for edge in self.graph.edge_references() {
+            if *edge.weight() != EdgeKind::Recursion && dominates(edge.target(), edge.source()) {
+                back_edges.entry(edge.target()).or_default().push((edge.source(), edge.target()));
+            }
+        }
+
+        let mut loops: Vec<NaturalLoop> = back_edges.into_iter()
+            .map(|(header, mut back_edges)| {
+                back_edges.sort();
+                let mut body = BTreeSet::from([header]);
+                let mut stack: Vec<NodeIndex> = back_edges.iter().map(|&(from, _)| from).collect();
+                while let Some(node_id) = stack.pop() {
+                    if body.insert(node_id) {
+                        stack.extend(self.graph.edges_directed(node_id, Direction::Incoming)
+                            .filter(|edge| *edge.weight() != EdgeKind::Recursion)
+                            .map(|edge| edge.source()));
+                    }
+                }
+                NaturalLoop { header, back_edges, body, parent: None, depth: 1 }
+            })
+            .collect();
+
+        // 外层循环的循环体严格包含内层循环，按大小排序后父循环总在前面
+        loops.sort_by(|a, b| b.body.len().cmp(&a.body.len()).then(a.header.cmp(&b.header)));
+        for index in 0..loops.len() {
+            let parent = (0..index).rev().find(|&outer| loops[outer].body.contains(&loops[index].header));
+            loops[index].parent = parent;
+            loops[index].depth = parent.map_or(1, |outer| loops[outer].depth + 1);
+        }
+        loops
+    }
+
+    // 所有函数中的回边
+    pub fn back_edges(&self) -> HashSet<(NodeIndex, NodeIndex)> {
+        self.function_starts().into_iter()
+            .flat_map(|start| self.natural_loops(start))
+            .flat_map(|natural_loop| natural_loop.back_edges)
+            .collect()
+    }
+}
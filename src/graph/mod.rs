@@ -1,5 +1,16 @@
+mod arena;
 mod flow_graph;
 mod node_type;
+mod labels;
+mod edge_kind;
+mod span;
+mod loops;
 
-pub use flow_graph::{FlowGraph, GraphConfig};
-pub use node_type::{NodeType, LoopKind}; 
\ No newline at end of file
+pub use arena::GraphArena;
+pub use flow_graph::{BlockGranularity, FlowGraph, FunctionInfo, FunctionOverride, GraphConfig};
+pub(crate) use flow_graph::{StoredGraph, glob_match};
+pub use node_type::{NodeType, LoopKind, TypeKind};
+pub use labels::{Labels, BUILTIN_LANGUAGES};
+pub use edge_kind::EdgeKind;
+pub use span::SourceSpan;
+pub use loops::NaturalLoop; 
\ No newline at end of file
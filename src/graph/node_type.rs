@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LoopKind {
     While(String),     // while 循环，带条件
     For(String),       // for 循环，带迭代器表达式
@@ -17,13 +18,38 @@ impl fmt::Display for LoopKind {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TypeKind {
+    Struct,
+    Enum,
+    Trait,
+}
+
+impl fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeKind::Struct => write!(f, "struct"),
+            TypeKind::Enum => write!(f, "enum"),
+            TypeKind::Trait => write!(f, "trait"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NodeType {
     Start(String, bool),           // 函数开始，bool表示是否是测试函数
     End(String, bool),            // 函数结束，bool表示是否是测试函数
     BasicBlock(String),     // 基本代码块
     Condition(String),      // if/match条件
     Loop(LoopKind),        // 循环结构
+    Unreachable(String),   // 位于发散语句之后、不可能执行到的代码
+    Declarations(Vec<String>), // 连续的 let 声明折叠成的一个节点
+    Await(String),         // async 函数中的 `.await` 挂起点，内容为被等待的表达式
+    Abort(String),         // panic!、std::process::exit 等终止执行的语句，没有后继
+    Unsafe(String),        // 位于 unsafe 块或 unsafe fn 中的基本代码块
+    Module(String),        // 模块依赖图中的模块，内容为模块完整路径
+    Package(String, bool), // crate 依赖图中的包（名称和版本），bool表示是否是 workspace 成员
+    Type(TypeKind, String), // 类型关系图中的结构体、枚举或 trait，内容为完整路径
 }
 
 impl NodeType {
@@ -31,15 +57,56 @@ impl NodeType {
         match self {
             NodeType::Start(name, _) => format!("Start: {}", name),
             NodeType::End(name, _) => format!("End: {}", name),
-            NodeType::BasicBlock(content) => {
-                let mut result = content.replace(";", ";\n");
-                if result.ends_with('\n') {
-                    result.pop();
-                }
-                result
+            NodeType::BasicBlock(content) | NodeType::Unsafe(content) => {
+                // 合并后的基本块每条语句已占一行，行末的分号不再额外换行
+                content.lines()
+                    .map(|line| line.replace(';', ";\n").trim_end_matches('\n').to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
             },
             NodeType::Condition(cond) => format!("Condition: {}", cond),
             NodeType::Loop(kind) => format!("Loop: {}", kind),
+            NodeType::Unreachable(content) => format!("unreachable: {}", content),
+            NodeType::Declarations(decls) => format!("declarations ({})", decls.len()),
+            NodeType::Await(future) => format!("await: {}", future),
+            NodeType::Abort(content) => format!("abort: {}", content),
+            NodeType::Module(path) => format!("mod {}", path),
+            NodeType::Package(package, _) => package.clone(),
+            NodeType::Type(kind, path) => format!("{} {}", kind, path),
+        }
+    }
+
+    // 节点类型的名称，用于导出给其他工具的属性
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NodeType::Start(_, _) => "start",
+            NodeType::End(_, _) => "end",
+            NodeType::BasicBlock(_) => "basic_block",
+            NodeType::Condition(_) => "condition",
+            NodeType::Loop(_) => "loop",
+            NodeType::Unreachable(_) => "unreachable",
+            NodeType::Declarations(_) => "declarations",
+            NodeType::Await(_) => "await",
+            NodeType::Abort(_) => "abort",
+            NodeType::Unsafe(_) => "unsafe",
+            NodeType::Module(_) => "module",
+            NodeType::Package(_, _) => "package",
+            NodeType::Type(_, _) => "type",
+        }
+    }
+
+    // 悬停时显示的完整代码：标签会按宽度折行、折叠声明，这里保留源码原样
+    pub fn tooltip(&self) -> Option<String> {
+        match self {
+            NodeType::Declarations(decls) => Some(decls.join("\n")),
+            NodeType::BasicBlock(content)
+            | NodeType::Unsafe(content)
+            | NodeType::Condition(content)
+            | NodeType::Unreachable(content)
+            | NodeType::Await(content)
+            | NodeType::Abort(content) => Some(content.clone()),
+            NodeType::Loop(kind) => Some(kind.to_string()),
+            _ => None,
         }
     }
 
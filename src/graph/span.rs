@@ -0,0 +1,33 @@
+use proc_macro2::Span;
+use serde::{Deserialize, Serialize};
+
+// 节点对应的源码范围，行号和列号都从 1 开始；所在文件见节点所属函数的 FunctionInfo。
+// 开启 --expand-macros 时对应的是展开后的代码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl SourceSpan {
+    // proc-macro2 的列号从 0 开始；没有位置信息的 span（如 quote! 生成的代码）行号为 0
+    pub fn from_span(span: Span) -> Option<Self> {
+        let (start, end) = (span.start(), span.end());
+        (start.line > 0).then_some(Self {
+            line: start.line,
+            column: start.column + 1,
+            end_line: end.line,
+            end_column: end.column + 1,
+        })
+    }
+
+    // 覆盖两个范围的最小范围，用于合并后的基本块
+    pub fn join(self, other: SourceSpan) -> Self {
+        let (start, end) = ((self.line, self.column), (self.end_line, self.end_column));
+        let start = start.min((other.line, other.column));
+        let end = end.max((other.end_line, other.end_column));
+        Self { line: start.0, column: start.1, end_line: end.0, end_column: end.1 }
+    }
+}
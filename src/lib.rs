@@ -1,18 +1,42 @@
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use std::fs;
 use std::path::Path;
+use std::time::Instant;
 
+mod config;
 mod graph;
 mod passes;
 mod style;
+mod timings;
 
-pub use graph::{FlowGraph, NodeType};
+pub use config::ProjectConfig;
+pub use graph::{BlockGranularity, EdgeKind, FlowGraph, FunctionInfo, FunctionOverride, GraphConfig, Labels, NaturalLoop, NodeType, SourceSpan, TypeKind, BUILTIN_LANGUAGES};
 pub use passes::*;
+pub use style::{D2Escape, DotEscape, EdgeTheme, FontTheme, LabelSanitizer, MermaidEscape, NodeTheme, PlantUmlEscape, Theme, BUILTIN_THEMES};
+pub use timings::{PassTiming, Timings, TimingReport};
 
 pub trait GraphRenderer {
     fn render(&self, graph: &FlowGraph) -> Result<String>;
     fn style(&self) -> &str;
     fn template(&self) -> &str;
+
+    fn render_timed(&self, graph: &FlowGraph, timings: &mut Timings) -> Result<String> {
+        let start = Instant::now();
+        let output = self.render(graph)?;
+        timings.record_since("render", start, output.len());
+        Ok(output)
+    }
+}
+
+fn style_and_render(graph: &FlowGraph, timings: &mut Timings, render: fn(&StyledGraph) -> String) -> String {
+    let start = Instant::now();
+    let styled = StylerPass::apply_style(graph);
+    timings.record_since("style", start, styled.nodes.len());
+
+    let start = Instant::now();
+    let output = render(&styled);
+    timings.record_since("render", start, output.len());
+    output
 }
 
 pub struct DotRenderer {
@@ -33,6 +57,10 @@ impl GraphRenderer for DotRenderer {
         Ok(DotRendererPass::render(&styled))
     }
 
+    fn render_timed(&self, graph: &FlowGraph, timings: &mut Timings) -> Result<String> {
+        Ok(style_and_render(graph, timings, DotRendererPass::render))
+    }
+
     fn style(&self) -> &str {
         &self.graph_type
     }
@@ -60,6 +88,10 @@ impl GraphRenderer for CStyleFlowchartRenderer {
         Ok(DotRendererPass::render(&styled))
     }
 
+    fn render_timed(&self, graph: &FlowGraph, timings: &mut Timings) -> Result<String> {
+        Ok(style_and_render(graph, timings, DotRendererPass::render))
+    }
+
     fn style(&self) -> &str {
         "c-style"
     }
@@ -69,21 +101,264 @@ impl GraphRenderer for CStyleFlowchartRenderer {
     }
 }
 
-pub fn analyze_file_with_renderer<R: GraphRenderer + ?Sized>(
+// Mermaid 流程图，适合粘贴到不支持 Graphviz 的 Markdown 文档中
+#[derive(Default)]
+pub struct MermaidRenderer;
+
+impl GraphRenderer for MermaidRenderer {
+    fn render(&self, graph: &FlowGraph) -> Result<String> {
+        let styled = StylerPass::apply_style(graph);
+        Ok(MermaidRendererPass::render(&styled))
+    }
+
+    fn render_timed(&self, graph: &FlowGraph, timings: &mut Timings) -> Result<String> {
+        Ok(style_and_render(graph, timings, MermaidRendererPass::render))
+    }
+
+    fn style(&self) -> &str {
+        "mermaid"
+    }
+
+    fn template(&self) -> &str {
+        "mermaid"
+    }
+}
+
+// PlantUML 活动图，便于放进已有 PlantUML 工具链的设计文档
+#[derive(Default)]
+pub struct PlantUmlRenderer;
+
+impl GraphRenderer for PlantUmlRenderer {
+    fn render(&self, graph: &FlowGraph) -> Result<String> {
+        let styled = StylerPass::apply_style(graph);
+        Ok(PlantUmlRendererPass::render(&styled))
+    }
+
+    fn render_timed(&self, graph: &FlowGraph, timings: &mut Timings) -> Result<String> {
+        Ok(style_and_render(graph, timings, PlantUmlRendererPass::render))
+    }
+
+    fn style(&self) -> &str {
+        "plantuml"
+    }
+
+    fn template(&self) -> &str {
+        "plantuml"
+    }
+}
+
+// GraphML 导出，节点属性来自 FlowGraph 本身而不是样式
+#[derive(Default)]
+pub struct GraphMlRenderer;
+
+impl GraphRenderer for GraphMlRenderer {
+    fn render(&self, graph: &FlowGraph) -> Result<String> {
+        Ok(GraphMlRendererPass::render(graph))
+    }
+
+    fn style(&self) -> &str {
+        "graphml"
+    }
+
+    fn template(&self) -> &str {
+        "graphml"
+    }
+}
+
+// 原始图模型的 JSON 导出（不合并基本块），供网页查看器、CI 分析等下游工具使用
+#[derive(Default)]
+pub struct JsonRenderer;
+
+impl GraphRenderer for JsonRenderer {
+    fn render(&self, graph: &FlowGraph) -> Result<String> {
+        Ok(serde_json::to_string_pretty(graph)?)
+    }
+
+    fn style(&self) -> &str {
+        "json"
+    }
+
+    fn template(&self) -> &str {
+        "json"
+    }
+}
+
+// D2 图，可以交给 D2 的自动布局引擎或 Terrastruct 的工具处理
+#[derive(Default)]
+pub struct D2Renderer;
+
+impl GraphRenderer for D2Renderer {
+    fn render(&self, graph: &FlowGraph) -> Result<String> {
+        let styled = StylerPass::apply_style(graph);
+        Ok(D2RendererPass::render(&styled))
+    }
+
+    fn render_timed(&self, graph: &FlowGraph, timings: &mut Timings) -> Result<String> {
+        Ok(style_and_render(graph, timings, D2RendererPass::render))
+    }
+
+    fn style(&self) -> &str {
+        "d2"
+    }
+
+    fn template(&self) -> &str {
+        "d2"
+    }
+}
+
+// 终端中的制表符流程图，通过 SSH 查看单个函数时不需要任何外部工具
+#[derive(Default)]
+pub struct TerminalRenderer;
+
+impl GraphRenderer for TerminalRenderer {
+    fn render(&self, graph: &FlowGraph) -> Result<String> {
+        let styled = StylerPass::apply_style(graph);
+        Ok(TerminalRendererPass::render(&styled))
+    }
+
+    fn render_timed(&self, graph: &FlowGraph, timings: &mut Timings) -> Result<String> {
+        Ok(style_and_render(graph, timings, TerminalRendererPass::render))
+    }
+
+    fn style(&self) -> &str {
+        "ascii"
+    }
+
+    fn template(&self) -> &str {
+        "ascii"
+    }
+}
+
+pub fn collect_file_functions(path: &Path) -> Result<Vec<CollectedFunction>> {
+    let source = fs::read_to_string(path)?;
+    let ast = ParserPass::parse(&source)?;
+    Ok(FunctionCollectorPass::in_module(&ParserPass::module_path(path))
+        .with_file(&ParserPass::display_path(path))
+        .run(&ast))
+}
+
+pub fn collect_file_imports(path: &Path) -> Result<ImportTable> {
+    let source = fs::read_to_string(path)?;
+    let ast = ParserPass::parse(&source)?;
+    Ok(ImportTable::collect(&ast, &ParserPass::module_path(path)))
+}
+
+pub fn collect_file_modules(path: &Path) -> Result<Vec<String>> {
+    let source = fs::read_to_string(path)?;
+    let ast = ParserPass::parse(&source)?;
+    Ok(ModuleGraphPass::collect(&ast, &ParserPass::module_path(path)))
+}
+
+pub fn collect_file_types(path: &Path) -> Result<TypeItems> {
+    let source = fs::read_to_string(path)?;
+    let ast = ParserPass::parse(&source)?;
+    Ok(TypeGraphPass::collect(&ast, &ParserPass::module_path(path)))
+}
+
+pub fn collect_file_features(path: &Path) -> Result<Vec<FeatureGate>> {
+    let source = fs::read_to_string(path)?;
+    let ast = ParserPass::parse(&source)?;
+    Ok(FeatureMapPass::collect(&ast, &ParserPass::module_path(path)))
+}
+
+pub fn analyze_file(path: &Path) -> Result<FlowGraph> {
+    analyze_file_with_timings(path, &GraphConfig::default(), &mut Timings::new())
+}
+
+pub fn analyze_file_with_timings(
     path: &Path,
-    renderer: &R
-) -> Result<String> {
+    config: &GraphConfig,
+    timings: &mut Timings,
+) -> Result<FlowGraph> {
     // 1. 读取源码
     let source = fs::read_to_string(path)?;
-    
+
+    // 内容和分析选项都没有变化时直接使用缓存的结果；展开宏的结果还取决于其他文件，不缓存
+    let cache = config.cache_dir.as_deref().filter(|_| !config.expand_macros).map(AnalysisCache::new);
+    if let Some(cache) = &cache {
+        let start = Instant::now();
+        if let Some(graph) = cache.load(path, &source, config) {
+            timings.record_since("cache", start, 1);
+            return Ok(graph);
+        }
+    }
+    let flow_graph = analyze_file_contents(path, &source, config, timings)?;
+    if let Some(cache) = &cache {
+        cache.store(path, &source, config, &flow_graph);
+    }
+    Ok(flow_graph)
+}
+
+// 分析给定内容，path 只用于推断模块路径和显示文件名，例如 git 中某个版本的文件
+pub fn analyze_file_contents(
+    path: &Path,
+    source: &str,
+    config: &GraphConfig,
+    timings: &mut Timings,
+) -> Result<FlowGraph> {
     // 2. 解析源码
-    let ast = ParserPass::parse(&source)?;
+    let start = Instant::now();
+    let ast = ParserPass::parse(source)?;
+    timings.record_since("parse", start, ast.items.len());
+
+    // 需要时用 cargo expand 展开宏，之后分析展开后的代码
+    let (ast, source) = if config.expand_macros {
+        let start = Instant::now();
+        let expanded = MacroExpansionPass::expand_file(path, &ast)?;
+        timings.record_since("expand", start, expanded.0.items.len());
+        expanded
+    } else {
+        (ast, source.to_string())
+    };
     
     // 3. 收集函数
-    let functions = FunctionCollectorPass::collect(&ast);
+    let start = Instant::now();
+    let functions = FunctionCollectorPass::in_module(&ParserPass::module_path(path))
+        .with_file(&ParserPass::display_path(path))
+        .run(&ast);
+    timings.record_since("collect", start, functions.len());
     
     // 4. 构建控制流图
-    let flow_graph = GraphBuilderPass::build(functions);
+    let start = Instant::now();
+    let flow_graph = analyze_functions(functions, Some(&source), config);
+    timings.record_since("analyze", start, flow_graph.graph.node_count());
+    Ok(flow_graph)
+}
+
+// 分析一段不属于任何文件的源码，例如编辑器中未保存的内容；函数路径以 crate 为根。
+// 展开宏需要源文件，不支持 expand_macros
+pub fn analyze_source(source: &str, config: &GraphConfig) -> Result<FlowGraph> {
+    if config.expand_macros {
+        bail!("Macro expansion requires a source file, use analyze_file_contents instead");
+    }
+    let ast = ParserPass::parse(source)?;
+    Ok(analyze_functions(FunctionCollectorPass::collect(&ast), Some(source), config))
+}
+
+// 为已经收集（可能经过筛选）的函数构建控制流图；给出源码时节点标签使用原文，否则由语法树生成
+pub fn analyze_functions(functions: Vec<CollectedFunction>, source: Option<&str>, config: &GraphConfig) -> FlowGraph {
+    GraphBuilderPass::build_with_source(functions, config.clone(), source)
+}
+
+// 分析多个文件并按给出的顺序合并为一个图（见 FlowGraph::merge），每个文件的函数以去掉扩展名的
+// 显示路径（如 src/parser）为命名空间；config.cache_dir 不为空时使用分析缓存
+pub fn analyze_paths<P: AsRef<Path>>(paths: &[P], config: &GraphConfig) -> Result<FlowGraph> {
+    let mut merged = FlowGraph::with_config(config.clone());
+    let mut timings = Timings::new();
+    for path in paths {
+        let path = path.as_ref();
+        let graph = analyze_file_with_timings(path, config, &mut timings)
+            .with_context(|| format!("Failed to analyze {}", path.display()))?;
+        merged.merge(graph, &ParserPass::display_path(&path.with_extension("")));
+    }
+    Ok(merged)
+}
+
+pub fn analyze_file_with_renderer<R: GraphRenderer + ?Sized>(
+    path: &Path,
+    renderer: &R
+) -> Result<String> {
+    let flow_graph = analyze_file(path)?;
     
     // 5. 渲染图
     renderer.render(&flow_graph)
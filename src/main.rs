@@ -1,54 +1,378 @@
-use std::path::{Path, PathBuf};
-use std::collections::HashMap;
-use walkdir::WalkDir;
-use anyhow::{Result, bail};
-use clap::Parser;
-use cargo_graph::{analyze_file_with_renderer, DotRenderer, CStyleFlowchartRenderer, GraphRenderer};
+use std::path::{Component, Path, PathBuf};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::IsTerminal;
+use std::sync::{OnceLock, mpsc};
+use std::time::{Duration, Instant};
+use ignore::WalkBuilder;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use notify::{RecursiveMode, Watcher};
+use anyhow::{Context, Result, bail};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use cargo_graph::{FlowGraph, AccessibilityPass, BuiltinSvgPass, HtmlViewerPass, SourcePanel, BlockGranularity, DotRendererPass, CollectedFunction, DotEscape, MermaidEscape, LayoutPass, MatrixPass, OpenMetricsPass, HistoryPass, Snapshot, FunctionSnapshot, VerifyPass, DiffPass, ChurnPass, CodeOwners, analyze_file_contents, analyze_file_with_timings, CallGraphPass, CrateFunctions, collect_file_features, collect_file_functions, collect_file_imports, collect_file_modules, collect_file_types, DependencyGraphPass, DependencyOptions, ImportTable, ModuleGraphPass, FeatureMapPass, DotRenderer, CStyleFlowchartRenderer, MermaidRenderer, PlantUmlRenderer, GraphMlRenderer, JsonRenderer, D2Renderer, TerminalRenderer, ReportDiagrams, ReportPass, GraphConfig, GraphRenderer, Labels, Theme, ParserPass, ReachabilityPass, ScipIndex, StatsPass, TypeGraphPass, TypeItems, UnreachableCodePass, StylerPass, PathFilter, ProjectConfig, FunctionOverride, WorkspacePackage, WorkspacePass, TimingReport, Timings};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, bin_name = "cargo graph")]
 struct Args {
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     input: Option<PathBuf>,
     
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     output: Option<PathBuf>,
     
     #[arg(short, long, default_value = "svg")]
     format: String,
     
-    #[arg(short, long, default_value = "default")]
+    #[arg(long, global = true)]
+    stdout: bool,
+    
+    #[arg(short, long, default_value = "default", global = true)]
     style: String,
     
+    #[arg(long, global = true)]
+    timings: bool,
+    
+    #[arg(long, global = true)]
+    watch: bool,
+    
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    
+    #[arg(long, global = true)]
+    include_tests: bool,
+    
+    #[arg(long = "function", global = true)]
+    functions: Vec<String>,
+    
+    #[arg(long = "item-path", global = true)]
+    item_paths: Vec<String>,
+    
+    #[arg(long, global = true)]
+    reachability: bool,
+    
+    #[arg(long, global = true)]
+    with_build_script: bool,
+    
+    #[arg(short = 'p', long = "package", global = true)]
+    packages: Vec<String>,
+    
+    #[arg(long, global = true)]
+    workspace: bool,
+    
+    #[arg(long, global = true)]
+    include: Vec<String>,
+    
+    #[arg(long, global = true)]
+    exclude: Vec<String>,
+    
+    #[arg(long, global = true)]
+    no_ignore: bool,
+    
+    #[arg(long, global = true)]
+    no_cache: bool,
+    
+    #[arg(long, global = true)]
+    index: Option<PathBuf>,
+    
+    #[arg(long, global = true)]
+    changed_files: Option<String>,
+    
+    #[arg(long, global = true)]
+    html_labels: bool,
+    
+    #[arg(long, global = true)]
+    group_declarations: bool,
+    
+    #[arg(long, global = true)]
+    expand_boolean_ops: bool,
+    
+    #[arg(long, global = true)]
+    show_recursion: bool,
+    
+    #[arg(long, global = true)]
+    highlight_loops: bool,
+    
+    #[arg(long, global = true)]
+    expand_macros: bool,
+    
+    #[arg(long, global = true)]
+    flat_else_if: bool,
+    
+    #[arg(long, default_value = "merged", global = true)]
+    block_granularity: BlockGranularity,
+    
+    #[arg(long, global = true)]
+    max_merged: Option<usize>,
+    
+    #[arg(long, default_value = "dot", global = true)]
+    renderer: SvgRenderer,
+    
+    #[arg(long, global = true)]
+    keep_dot: bool,
+    
+    #[arg(long, global = true)]
+    open: bool,
+
+    #[arg(long, global = true)]
+    split_by: Option<SplitBy>,
+
+    #[arg(long, default_value = "light", global = true)]
+    theme: String,
+
+    #[arg(long, default_value = "zh", global = true)]
+    lang: String,
+
+    #[arg(long, default_value = "vscode://file/{path}:{line}", global = true)]
+    link_template: String,
+    
+    #[arg(long, global = true)]
+    churn: bool,
+    
+    #[arg(long, default_value = "3 months ago", global = true)]
+    churn_since: String,
+    
+    #[arg(long, global = true)]
+    ownership: bool,
+    
+    #[arg(long, global = true)]
+    owners_file: Option<PathBuf>,
+    
     #[command(subcommand)]
-    command: Option<Commands>,
+    command: Option<GraphAction>,
+}
+
+// 生成 SVG 的方式：调用外部的 Graphviz dot 命令，或使用内置的 layout-rs（无需安装 Graphviz）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SvgRenderer {
+    Dot,
+    Builtin,
 }
 
+impl std::str::FromStr for SvgRenderer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "builtin" => Ok(Self::Builtin),
+            _ => Err(format!("未知的渲染方式 `{}`，可选值：dot、builtin", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SvgOptions {
+    renderer: SvgRenderer,
+    // 生成 SVG 后保留中间的 DOT 文件
+    keep_dot: bool,
+    // 写完后用系统默认程序打开
+    open: bool,
+}
+
+// 拆分输出：每个函数或每个模块（源文件）一张图
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SplitBy {
+    Function,
+    Module,
+}
+
+impl std::str::FromStr for SplitBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "function" => Ok(Self::Function),
+            "module" => Ok(Self::Module),
+            _ => Err(format!("未知的拆分方式 `{}`，可选值：function、module", s)),
+        }
+    }
+}
+
+// 不指定子命令时生成控制流图，与 `graph` 子命令相同
 #[derive(clap::Subcommand, Debug)]
-enum Commands {
+enum GraphAction {
+    /// 生成控制流图（默认）
     Graph,
+    /// 输出每个函数的分支/循环/节点数分布统计
+    Stats {
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        #[arg(long)]
+        chart: Option<PathBuf>,
+    },
+    /// 按圈复杂度列出函数及其节点数和最大嵌套层数，超过 --threshold 时失败，用于 CI
+    Complexity {
+        #[arg(long)]
+        threshold: Option<usize>,
+    },
+    /// 列出从函数开始不可达的代码，存在时失败，用于 CI
+    Lint,
+    /// 输出 feature 与其控制的函数/代码块之间的二部图
+    Features,
+    /// 输出整个 workspace 的调用图，每个成员 crate 一个分组
+    Workspace,
+    /// 输出当前 crate 的函数调用图，可以只显示从指定函数出发能到达的部分
+    Call {
+        #[arg(long = "root")]
+        roots: Vec<String>,
+    },
+    /// 输出模块依赖图，循环依赖以红色粗线标出
+    Modules,
+    /// 输出结构体、枚举和 trait 之间的组合、实现和约束关系
+    Types,
+    /// 通过 cargo metadata 输出 crate 依赖图
+    Deps {
+        #[arg(long)]
+        depth: Option<usize>,
+
+        #[arg(long)]
+        dedupe_versions: bool,
+
+        #[arg(long, value_delimiter = ',')]
+        features: Vec<String>,
+
+        /// 以红色标出许可证表达式中包含这些许可证（SPDX 标识符）的包，可重复或逗号分隔
+        #[arg(long = "deny-license", value_delimiter = ',')]
+        deny_licenses: Vec<String>,
+    },
+    /// 与基准控制流图比较，控制流发生变化时失败，用于 CI
+    Verify {
+        #[arg(long)]
+        against: PathBuf,
+
+        #[arg(long)]
+        update: bool,
+    },
+    /// 比较两个 git 版本的控制流图，新增、删除和变化的节点分别以绿色、红色和黄色标出
+    Diff {
+        #[arg(long, default_value = "main")]
+        base: String,
+
+        #[arg(long, default_value = "HEAD")]
+        head: String,
+    },
+    /// 每个模块生成一份 Markdown 文档，每个函数一节，包含复杂度和控制流图
+    Report {
+        #[arg(long, default_value = "mermaid")]
+        diagrams: ReportDiagrams,
+    },
+    /// 启动本地 HTTP 服务：首页列出所有模块和函数，打开某个函数时才分析对应文件并渲染 SVG
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// 按 git 提交记录和查看函数控制流图的历史
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum HistoryAction {
+    /// 把当前提交的所有函数控制流图保存到 .cargo-graph/history
+    Record,
+    /// 显示一个函数的控制流图和复杂度在各次提交之间的变化
+    Show {
+        function: String,
+    },
 }
 
 fn get_crate_root() -> Result<PathBuf> {
     let current_dir = std::env::current_dir()?;
-    let cargo_toml = current_dir.join("Cargo.toml");
-    
-    if cargo_toml.exists() {
-        Ok(current_dir)
+    // 与 cargo 相同，从当前目录向上找到第一个 Cargo.toml
+    match current_dir.ancestors().find(|dir| dir.join("Cargo.toml").exists()) {
+        Some(root) => Ok(root.to_path_buf()),
+        None => bail!("Could not find Cargo.toml in current directory or any parent directory"),
+    }
+}
+
+// --workspace 或 -p 选中的 workspace 成员；在只有 [workspace] 的根目录下默认选中所有成员。
+// 都不是时返回 None，按原来的方式遍历当前 crate
+fn selected_packages(names: &[String], workspace: bool) -> Result<Option<Vec<WorkspacePackage>>> {
+    if !workspace && names.is_empty() {
+        let Ok(root) = get_crate_root() else {
+            return Ok(None);
+        };
+        let manifest: toml::Table = std::fs::read_to_string(root.join("Cargo.toml"))?.parse()?;
+        if manifest.contains_key("package") || !manifest.contains_key("workspace") {
+            return Ok(None);
+        }
+    }
+    let (_, members) = WorkspacePass::load(&get_crate_root()?)?;
+    if names.is_empty() {
+        Ok(Some(members))
     } else {
-        bail!("Could not find Cargo.toml in current directory or any parent directory")
+        WorkspacePass::select(members, names).map(Some)
     }
 }
 
-fn find_rust_files(dir: &Path) -> Result<Vec<PathBuf>> {
+// 成员 crate 的源文件及其分组名称（`包名/相对于包目录的路径`），每个包的文件在输出中各自分组
+fn package_files(packages: &[WorkspacePackage], filter: &PathFilter) -> Result<Vec<(String, PathBuf)>> {
     let mut files = Vec::new();
+    for package in packages {
+        for dir in &package.source_dirs {
+            for file in find_rust_files(dir, filter)? {
+                let relative = file.strip_prefix(&package.root).unwrap_or(&file).with_extension("");
+                let module = format!("{}/{}", package.name, relative.to_string_lossy().replace('\\', "/"));
+                files.push((module, file));
+            }
+        }
+    }
+    Ok(files)
+}
+
+// 与 source_files 相同，同时给出每个文件的分组名称（相对路径，去掉扩展名）
+fn source_modules(input: Option<PathBuf>, packages: Option<&[WorkspacePackage]>, filter: &PathFilter) -> Result<Vec<(String, PathBuf)>> {
+    match (input, packages) {
+        (Some(input_file), _) => {
+            let crate_root = get_crate_root().ok();
+            let relative = crate_root.as_deref()
+                .and_then(|root| input_file.strip_prefix(root).ok())
+                .unwrap_or(&input_file)
+                .with_extension("");
+            Ok(vec![(relative.to_string_lossy().replace('\\', "/"), input_file)])
+        }
+        (None, Some(packages)) => package_files(packages, filter),
+        (None, None) => {
+            let crate_root = get_crate_root()?;
+            module_names(&crate_root, find_rust_files(&crate_root, filter)?)
+        }
+    }
+}
+
+// 要分析的源文件：-i 指定的文件、选中的 workspace 成员，或者当前 crate 的所有文件
+fn source_files(input: Option<PathBuf>, packages: Option<&[WorkspacePackage]>, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    match (input, packages) {
+        (Some(input_file), _) => Ok(vec![input_file]),
+        (None, Some(packages)) => Ok(package_files(packages, filter)?.into_iter().map(|(_, file)| file).collect()),
+        (None, None) => find_rust_files(&get_crate_root()?, filter),
+    }
+}
+
+fn find_rust_files(dir: &Path, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    // 构建脚本只在 --with-build-script 时单独分析
+    let build_files = build_script_files(dir);
     
-    for entry in WalkDir::new(dir)
-        .into_iter()
+    // 默认遵循 .gitignore、.ignore 和 git 的全局排除规则，--no-ignore 时遍历所有文件
+    let walk_filter = filter.clone();
+    for entry in WalkBuilder::new(dir)
+        .standard_filters(filter.respect_ignore)
+        // 不进入构建输出目录、git 子模块和被 --exclude 排除的目录
+        .filter_entry(move |e| {
+            e.depth() == 0 || !e.file_type().is_some_and(|file_type| file_type.is_dir()) || !walk_filter.skips_dir(e.path())
+        })
+        .build()
         .filter_map(|e| e.ok())
         .filter(|e| {
             e.path().extension().map_or(false, |ext| ext == "rs") &&
-            !e.path().to_string_lossy().contains("target") // 排除 target 目录
+            filter.allows(e.path()) &&
+            !build_files.iter().any(|build_file| build_file == e.path())
         })
     {
         files.push(entry.path().to_path_buf());
@@ -57,190 +381,1672 @@ fn find_rust_files(dir: &Path) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-fn analyze_crate(crate_root: &Path, renderer: &dyn GraphRenderer) -> Result<String> {
-    let rust_files = find_rust_files(crate_root)?;
-    println!("Found {} Rust files", rust_files.len());
+// 构建脚本（Cargo.toml 中 package.build 指定，默认 build.rs）及其声明的模块
+fn build_script_files(crate_root: &Path) -> Vec<PathBuf> {
+    let manifest = std::fs::read_to_string(crate_root.join("Cargo.toml"))
+        .ok()
+        .and_then(|manifest| manifest.parse::<toml::Table>().ok());
+    let build = manifest.as_ref()
+        .and_then(|manifest| manifest.get("package"))
+        .and_then(|package| package.get("build"));
+    let script = match build {
+        Some(toml::Value::String(path)) => crate_root.join(path),
+        Some(toml::Value::Boolean(false)) => return Vec::new(),
+        _ => crate_root.join("build.rs"),
+    };
+    if !script.exists() {
+        return Vec::new();
+    }
+
+    let mut files = vec![script];
+    let mut index = 0;
+    while index < files.len() {
+        let file = files[index].clone();
+        if let Ok(ast) = std::fs::read_to_string(&file).map_err(anyhow::Error::from)
+            .and_then(|source| ParserPass::parse(&source))
+        {
+            for module in ParserPass::declared_modules(&file, &ast, index == 0) {
+                if !files.contains(&module) {
+                    files.push(module);
+                }
+            }
+        }
+        index += 1;
+    }
+    files
+}
+
+fn analyze_file_timed(file: &Path, config: &GraphConfig, report: &mut TimingReport) -> Result<FlowGraph> {
+    let mut timings = Timings::new();
+    let result = analyze_file_with_timings(file, config, &mut timings);
+    report.files.push((file.display().to_string(), timings));
+    result
+}
+
+// 分析得到的各个文件的控制流图，每种输出格式从这里渲染，不需要重新分析
+struct AnalyzedGraphs {
+    // (模块名, 控制流图)
+    graphs: Vec<(String, FlowGraph)>,
+    // 模块名 -> 负责团队，只在 --ownership 时存在
+    teams: Option<HashMap<String, Option<String>>>,
+    // -i 指定单个文件时直接输出渲染结果，不按模块合并
+    single: bool,
+}
+
+fn graph_renderer(style: &str) -> Result<Box<dyn GraphRenderer>> {
+    Ok(match style {
+        "default" => Box::new(DotRenderer::default()),
+        "c-style" => Box::new(CStyleFlowchartRenderer::default()),
+        "mermaid" => Box::new(MermaidRenderer),
+        "plantuml" => Box::new(PlantUmlRenderer),
+        "graphml" => Box::new(GraphMlRenderer),
+        "json" => Box::new(JsonRenderer),
+        "d2" => Box::new(D2Renderer),
+        "ascii" => Box::new(TerminalRenderer),
+        style => bail!("Unsupported style: {}", style),
+    })
+}
+
+// 用一种渲染器渲染所有文件，再按渲染器的格式合并
+fn render_graphs(analyzed: &AnalyzedGraphs, renderer: &dyn GraphRenderer, report: &mut TimingReport) -> Result<String> {
+    if analyzed.single {
+        let (_, graph) = analyzed.graphs.first().context("No graph to render")?;
+        return renderer.render_timed(graph, &mut report.global);
+    }
+
+    // DOT 输出先把各个文件的控制流图合并为一个图，再统一样式化和渲染一次
+    if let "default" | "c-style" = renderer.style() {
+        let start = Instant::now();
+        let merged = merge_graphs(&analyzed.graphs);
+        report.global.record_since("merge", start, analyzed.graphs.len());
+
+        let start = Instant::now();
+        let styled = StylerPass::apply_style(&merged);
+        report.global.record_since("style", start, styled.nodes.len());
+
+        let start = Instant::now();
+        let dot = match &analyzed.teams {
+            Some(teams) => DotRendererPass::render_with_owners(&styled, teams),
+            None => DotRendererPass::render(&styled),
+        };
+        report.global.record_since("render", start, dot.len());
+        return Ok(dot);
+    }
+
+    let mut graphs = Vec::new();
+    for (module_name, graph) in &analyzed.graphs {
+        match renderer.render_timed(graph, &mut report.global) {
+            Ok(rendered) => graphs.push((module_name.clone(), rendered)),
+            Err(e) => log::warn!("Failed to render {}: {}", module_name, e),
+        }
+    }
+    let start = Instant::now();
+    let graph_count = graphs.len();
+    let merged = match renderer.style() {
+        "mermaid" => merge_mermaid(graphs),
+        "plantuml" => merge_plantuml(graphs),
+        "graphml" => merge_graphml(graphs),
+        "json" => merge_json(graphs)?,
+        "d2" => merge_d2(graphs),
+        "ascii" => merge_terminal(graphs),
+        style => bail!("Unsupported style: {}", style),
+    };
+    report.global.record_since("merge", start, graph_count);
+    Ok(merged)
+}
+
+// HTML 查看器的源码面板，与 render_graphs 以同样的方式合并和样式化，节点名称与 DOT 一致。
+// 展开宏后节点的行号对应展开后的代码，不提供源码
+fn source_panel(analyzed: &AnalyzedGraphs, args: &Args) -> Option<SourcePanel> {
+    if args.expand_macros {
+        return None;
+    }
+    let merged;
+    let graph = match analyzed.graphs.as_slice() {
+        [(_, graph)] if analyzed.single => graph,
+        graphs => {
+            merged = merge_graphs(graphs);
+            &merged
+        }
+    };
+    Some(SourcePanel::build(&StylerPass::apply_style(graph)))
+}
+
+// 读取变更文件列表：`-` 表示标准输入，`@path` 表示从文件读取，每行一个路径。
+// 相对路径以 crate 根目录为基准，已删除的文件、非 Rust 文件和被 --include/--exclude 排除的文件会被跳过
+fn read_changed_files(source: &str, crate_root: &Path, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    let content = match source {
+        "-" => std::io::read_to_string(std::io::stdin())?,
+        source => match source.strip_prefix('@') {
+            Some(list) => std::fs::read_to_string(list)?,
+            None => bail!("--changed-files expects '-' or @file, got: {}", source),
+        },
+    };
+
+    let mut files = Vec::new();
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        // 先去掉 `..`，`../sibling/src/lib.rs` 这样的路径不会通过下面的 crate 根目录检查
+        let file = normalize_path(&crate_root.join(line));
+        if file.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        if !file.starts_with(crate_root) {
+            log::warn!("Skipping file outside the crate: {}", line);
+        } else if !file.exists() {
+            log::info!("Skipping deleted file: {}", line);
+        } else if !filter.allows(&file) {
+            log::info!("Skipping excluded file: {}", line);
+        } else if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+// 按字面去掉路径中的 `.` 和 `..`，不访问文件系统，与 find_rust_files 找到的路径保持相同的形式
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// 文件相对于 crate 根目录、去掉扩展名的路径作为分组名称
+fn module_names(crate_root: &Path, files: impl IntoIterator<Item = PathBuf>) -> Result<Vec<(String, PathBuf)>> {
+    files.into_iter()
+        .map(|file| {
+            let relative_path = file.strip_prefix(crate_root)?.to_string_lossy().into_owned();
+            Ok((relative_path.replace(".rs", ""), file))
+        })
+        .collect()
+}
+
+// analyze_crate 要分析的文件范围
+struct CrateFiles<'a> {
+    with_build_script: bool,
+    // 只重新分析这些变更文件
+    changed: Option<&'a [PathBuf]>,
+    // 选中的 workspace 成员，None 表示当前 crate
+    packages: Option<&'a [WorkspacePackage]>,
+    filter: &'a PathFilter,
+}
+
+fn analyze_crate(
+    crate_root: &Path,
+    config: &GraphConfig,
+    selection: CrateFiles,
+    owners: Option<&CodeOwners>,
+    report: &mut TimingReport,
+) -> Result<AnalyzedGraphs> {
+    let CrateFiles { with_build_script, changed, packages, filter } = selection;
+    let start = Instant::now();
+    // 选中 workspace 成员时按包分组，构建脚本只分析当前 crate 的
+    let build_files = if packages.is_none() { build_script_files(crate_root) } else { Vec::new() };
+    // 只给出变更文件时，只重新分析这些文件中的函数
+    let rust_files: Vec<(String, PathBuf)> = match (packages, changed) {
+        (Some(packages), changed) => package_files(packages, filter)?
+            .into_iter()
+            .filter(|(_, file)| changed.is_none_or(|changed| changed.contains(file)))
+            .collect(),
+        (None, Some(changed)) => module_names(crate_root, changed.iter().filter(|file| !build_files.contains(file)).cloned())?,
+        (None, None) => module_names(crate_root, find_rust_files(crate_root, filter)?)?,
+    };
+    report.global.record_since("walk", start, rust_files.len());
+    log::info!("Found {} Rust files", rust_files.len());
     
     let mut graphs = Vec::new();
+    // 模块名 -> 负责团队
+    let mut teams: HashMap<String, Option<String>> = HashMap::new();
     
-    // 按模块分组处理文件
-    let mut module_files: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    // 按模块分组处理文件，模块和文件都按名称排序，每次运行的输出顺序相同
+    let mut module_files: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    let progress = progress_bar(rust_files.len());
     
-    for file in rust_files {
-        let relative_path = file.strip_prefix(crate_root)?.to_str().unwrap().to_string();
-        println!("Processing file: {} as module: {}", file.display(), relative_path);
+    for (module_name, file) in rust_files {
+        log::debug!("Processing file: {} as module: {}", file.display(), module_name);
         
-        let module_name = relative_path.replace(".rs", "");
+        if let Some(owners) = owners {
+            teams.insert(module_name.clone(), owners.team_of(&file).map(str::to_string));
+        }
         module_files.entry(module_name.clone())
             .or_default()
             .push(file);
     }
     
     // 分析每个模块
-    for (module_name, files) in module_files {
-        println!("Analyzing module: {} with {} files", module_name, files.len());
+    for (module_name, mut files) in module_files {
+        files.sort();
+        log::debug!("Analyzing module: {} with {} files", module_name, files.len());
         
         for file in files {
-            match analyze_file_with_renderer(&file, renderer) {
+            progress.set_message(ParserPass::display_path(&file));
+            progress.inc(1);
+            match analyze_file_timed(&file, config, report) {
                 Ok(graph) => {
-                    println!("Successfully analyzed {}", file.display());
+                    log::debug!("Successfully analyzed {}", file.display());
                     graphs.push((module_name.clone(), graph));
                 }
                 Err(e) => {
-                    eprintln!("Warning: Failed to analyze {}: {}", file.display(), e);
+                    log::warn!("Failed to analyze {}: {}", file.display(), e);
                 }
             }
         }
     }
+    progress.finish_and_clear();
     
-    println!("Generated {} graphs", graphs.len());
-    Ok(merge_graphs(graphs))
-}
-
-fn merge_graphs(graphs: Vec<(String, String)>) -> String {
-    let mut merged = String::from("digraph G {\n");
-    
-    // 添加全局属性
-    merged.push_str("    graph [\n");
-    merged.push_str("        rankdir=TB;\n");
-    merged.push_str("        nodesep=1.2;\n");
-    merged.push_str("        ranksep=1.5;\n");
-    merged.push_str("        splines=ortho;\n");
-    merged.push_str("        concentrate=true;\n");
-    merged.push_str("        compound=true;\n");
-    merged.push_str("        newrank=true\n");
-    merged.push_str("    ];\n\n");
-    
-    // 添加全局节点属性
-    merged.push_str("    node [\n");
-    merged.push_str("        fontname=\"Arial\";\n");
-    merged.push_str("        fontsize=12;\n");
-    merged.push_str("        margin=\"0.5,0.3\";\n");
-    merged.push_str("        height=0;\n");
-    merged.push_str("        width=0\n");
-    merged.push_str("    ];\n\n");
-    
-    // 添加全局边属性
-    merged.push_str("    edge [\n");
-    merged.push_str("        fontname=\"Arial\";\n");
-    merged.push_str("        fontsize=10;\n");
-    merged.push_str("        dir=forward;\n");
-    merged.push_str("        arrowsize=0.8;\n");
-    merged.push_str("        penwidth=1;\n");
-    merged.push_str("        minlen=2\n");
-    merged.push_str("    ];\n\n");
-    
-    // 合并所有子图
-    for (file_name, graph_content) in graphs {
-        let mut nodes = Vec::new();
-        let mut edges = Vec::new();
-        
-        // 解析子图内容
-        for line in graph_content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with("digraph") || line.starts_with("}") {
+    // 构建脚本的所有文件放在同一个 "build script" 分组中；
+    // 每个文件单独保留，JSON、GraphML 等输出不能直接拼接
+    if with_build_script {
+        let mut analyzed = false;
+        for file in build_files.iter().filter(|file| changed.is_none_or(|changed| changed.contains(file))) {
+            match analyze_file_timed(file, config, report) {
+                Ok(graph) => {
+                    graphs.push(("build script".to_string(), graph));
+                    analyzed = true;
+                }
+                Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+            }
+        }
+        if analyzed && let Some(owners) = owners {
+            teams.insert("build script".to_string(), build_files.first().and_then(|file| owners.team_of(file)).map(str::to_string));
+        }
+    }
+    
+    log::info!("Generated {} graphs", graphs.len());
+    Ok(AnalyzedGraphs { graphs, teams: owners.map(|_| teams), single: false })
+}
+
+// --owners-file 指定的团队映射文件中的路径相对于 crate 根目录；
+// 只给出 --ownership 时从 crate 根目录向上查找 CODEOWNERS
+fn load_owners(enabled: bool, owners_file: Option<&Path>) -> Result<Option<CodeOwners>> {
+    match owners_file {
+        Some(file) => Ok(Some(CodeOwners::load(file, &get_crate_root()?)?)),
+        None if enabled => match CodeOwners::discover(&get_crate_root()?) {
+            Some(owners) => Ok(Some(owners?)),
+            None => {
+                log::warn!("No CODEOWNERS file found, ownership coloring disabled");
+                Ok(None)
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+// 可达性需要整个 crate 的调用关系，先对所有文件做一次轻量的函数收集
+fn entry_distances(files: &[PathBuf], index: Option<&ScipIndex>) -> HashMap<String, usize> {
+    let (functions, imports) = collect_crate(files);
+    ReachabilityPass::entry_distances(&functions, &imports, index)
+}
+
+// 根据 git 历史统计每个函数在 since 之后被多少个提交修改过
+fn function_churn(files: &[PathBuf], since: &str) -> HashMap<String, usize> {
+    let mut churn = HashMap::new();
+    for file in files {
+        match collect_file_functions(file).and_then(|functions| Ok((functions, ChurnPass::history(file, since)?))) {
+            Ok((functions, history)) => churn.extend(ChurnPass::function_churn(&functions, &history)),
+            Err(e) => log::warn!("Failed to compute churn for {}: {}", file.display(), e),
+        }
+    }
+    churn
+}
+
+// 收集一组文件中的所有函数和 use 语句，用于跨文件的调用解析
+fn collect_crate(files: &[PathBuf]) -> (Vec<CollectedFunction>, ImportTable) {
+    let mut functions = Vec::new();
+    let mut imports = ImportTable::default();
+    for file in files {
+        match collect_file_functions(file).and_then(|collected| Ok((collected, collect_file_imports(file)?))) {
+            Ok((collected, table)) => {
+                functions.extend(collected);
+                imports.extend(table);
+            }
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+    (functions, imports)
+}
+
+fn run_stats(
+    input: Option<PathBuf>,
+    packages: Option<&[WorkspacePackage]>,
+    filter: &PathFilter,
+    config: &GraphConfig,
+    format: &str,
+    chart: Option<PathBuf>,
+) -> Result<()> {
+    let files = source_files(input, packages, filter)?;
+
+    let mut functions = Vec::new();
+    for file in files {
+        match analyze_file_with_timings(&file, config, &mut Timings::new()) {
+            Ok(graph) => functions.extend(StatsPass::collect(&graph)),
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+
+    let report = StatsPass::summarize(functions);
+    match format {
+        "text" => print!("{}", StatsPass::render_text(&report)),
+        "json" => println!("{}", StatsPass::render_json(&report)?),
+        format => bail!("Unsupported stats format: {}", format),
+    }
+
+    if let Some(chart_path) = chart {
+        std::fs::write(&chart_path, StatsPass::render_svg(&report))?;
+        log::info!("Stats chart saved to: {}", chart_path.display());
+    }
+
+    Ok(())
+}
+
+// 超过阈值的函数在表格中以 `!` 标出，并返回错误（退出码非 0）
+fn run_complexity(input: Option<PathBuf>, packages: Option<&[WorkspacePackage]>, filter: &PathFilter, config: &GraphConfig, threshold: Option<usize>) -> Result<()> {
+    let files = source_files(input, packages, filter)?;
+
+    let mut functions = Vec::new();
+    for file in files {
+        match analyze_file_with_timings(&file, config, &mut Timings::new()) {
+            Ok(graph) => functions.extend(StatsPass::collect(&graph)),
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+
+    print!("{}", StatsPass::render_complexity(&functions, threshold));
+    let Some(threshold) = threshold else {
+        return Ok(());
+    };
+    let exceeded = functions.iter().filter(|f| f.complexity > threshold).count();
+    if exceeded > 0 {
+        bail!("{} functions exceed the complexity threshold {}", exceeded, threshold);
+    }
+    Ok(())
+}
+
+fn run_lint(input: Option<PathBuf>, packages: Option<&[WorkspacePackage]>, filter: &PathFilter, config: &GraphConfig) -> Result<()> {
+    let files = source_files(input, packages, filter)?;
+
+    let mut findings = Vec::new();
+    for file in files {
+        match analyze_file_with_timings(&file, config, &mut Timings::new()) {
+            Ok(graph) => findings.extend(UnreachableCodePass::lint(&graph)),
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+
+    if findings.is_empty() {
+        println!("No unreachable code found");
+        return Ok(());
+    }
+    print!("{}", UnreachableCodePass::render_text(&findings));
+    bail!("Found {} unreachable code blocks", findings.len())
+}
+
+// 每个函数单独输出到 <输出目录>/<模块>/<函数>.svg，或每个模块输出到 <输出目录>/<模块>.svg，
+// 再生成链接到所有图的 index.html。大的 crate 合成一张图时 Graphviz 布局很慢，也难以阅读
+fn run_split(
+    modules: Vec<(String, PathBuf)>,
+    output: Option<PathBuf>,
+    config: &GraphConfig,
+    split: SplitBy,
+    svg: SvgOptions,
+) -> Result<()> {
+    let output_dir = output.unwrap_or_else(|| PathBuf::from("out"));
+    let mut sections = Vec::new();
+    let mut count = 0;
+    let progress = progress_bar(modules.len());
+    for (module_name, file) in modules {
+        progress.set_message(ParserPass::display_path(&file));
+        progress.inc(1);
+        let graph = match analyze_file_with_timings(&file, config, &mut Timings::new()) {
+            Ok(graph) => graph,
+            Err(e) => {
+                log::warn!("Failed to analyze {}: {}", file.display(), e);
                 continue;
             }
-            
-            if line.contains("->") {
-                // 处理边
-                edges.push(format!("        {}", line));
-            } else if line.contains("node_") && line.contains("[") && line.contains("]") {
-                // 处理节点
-                let mut node_line = line.to_string();
-                
-                // 根据节点类型设置不同的形状
-                if node_line.contains("Condition:") {
-                    node_line = node_line.replace("shape=\"oval\"", "shape=\"diamond\"");
-                } else if node_line.contains("Loop:") {
-                    node_line = node_line.replace("shape=\"oval\"", "shape=\"hexagon\"");
-                } else {
-                    node_line = node_line.replace("shape=\"oval\"", "shape=\"box\"");
+        };
+
+        let styled = StylerPass::apply_style(&graph);
+        let mut diagrams = Vec::new();
+        match split {
+            SplitBy::Module if !styled.nodes.is_empty() => {
+                diagrams.push((ParserPass::module_path(&file), format!("{}.svg", module_name), DotRendererPass::render(&styled)));
+            }
+            SplitBy::Module => {}
+            SplitBy::Function => {
+                let module = ParserPass::module_path(&file);
+                let mut functions: Vec<(usize, &str)> = styled.names.iter().map(|(id, name)| (*id, name.as_str())).collect();
+                functions.sort_by_key(|&(id, name)| (name, id));
+                let mut used = HashSet::new();
+                for (function, name) in functions {
+                    let subgraph = styled.function_subgraph(function);
+                    if subgraph.nodes.is_empty() {
+                        continue;
+                    }
+                    // 同名函数（如不同 cfg 下的两个版本）加上序号区分
+                    let short_name = ReportPass::short_name(name, &module);
+                    let mut stem = DotEscape::identifier(short_name);
+                    for i in 2.. {
+                        if used.insert(stem.clone()) {
+                            break;
+                        }
+                        stem = format!("{}_{}", DotEscape::identifier(short_name), i);
+                    }
+                    diagrams.push((short_name.to_string(), format!("{}/{}.svg", module_name, stem), DotRendererPass::render(&subgraph)));
                 }
-                
-                nodes.push(format!("        {}", node_line));
             }
         }
-        
-        // 只有当有实际内容时才创建子图
-        if !nodes.is_empty() || !edges.is_empty() {
-            // 处理文件名，使其适合作为子图名称
-            let cluster_name = file_name.replace('\\', "_").replace('/', "_").replace('.', "_");
-            let display_name = file_name.replace('\\', "/");
-            
-            merged.push_str(&format!("    subgraph cluster_{} {{\n", cluster_name));
-            merged.push_str(&format!("        label=\"{}\";\n", display_name));
-            merged.push_str("        style=rounded;\n");
-            merged.push_str("        color=gray;\n");
-            merged.push_str("        bgcolor=aliceblue;\n");
-            merged.push_str("        fontsize=12;\n");
-            merged.push_str("        margin=16;\n");
-            merged.push_str("        node [style=filled];\n\n");
-            
-            // 先添加所有节点
-            if !nodes.is_empty() {
-                merged.push_str(&nodes.join("\n"));
-                merged.push_str("\n");
+
+        let mut links = Vec::new();
+        for (title, path, dot) in diagrams {
+            let full_path = output_dir.join(&path);
+            std::fs::create_dir_all(full_path.parent().unwrap_or(&output_dir))?;
+            write_svg(&dot, &full_path, &title, svg)?;
+            links.push((title, path));
+        }
+        count += links.len();
+        if !links.is_empty() {
+            sections.push((module_name, links));
+        }
+    }
+
+    progress.finish_and_clear();
+
+    sections.sort();
+    std::fs::create_dir_all(&output_dir)?;
+    std::fs::write(output_dir.join("index.html"), HtmlViewerPass::render_index("控制流图", &sections))?;
+    print_saved(&format!("{} diagrams", count), &output_dir);
+    open_output(output_dir.join("index.html"), svg);
+    Ok(())
+}
+
+// 每次请求时重新读取源文件，修改代码后刷新页面即可看到新的控制流图
+fn run_serve(
+    port: u16,
+    modules: impl Fn() -> Result<Vec<(String, PathBuf)>>,
+    config: &GraphConfig,
+    svg: SvgOptions,
+) -> Result<()> {
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow::anyhow!("Failed to listen on port {}: {}", port, e))?;
+    println!("Serving control flow graphs at http://127.0.0.1:{}/ (press Ctrl+C to stop)", port);
+    open_output(format!("http://127.0.0.1:{}/", port), svg);
+    for request in server.incoming_requests() {
+        let (status, content_type, body) = match modules().and_then(|modules| serve_request(request.url(), &modules, config, svg)) {
+            Ok(Some((content_type, body))) => (200, content_type, body),
+            Ok(None) => (404, "text/plain; charset=utf-8", "Not found".to_string()),
+            Err(e) => (500, "text/plain; charset=utf-8", format!("{:#}", e)),
+        };
+        let header = tiny_http::Header::from_bytes("Content-Type", content_type).expect("valid header");
+        let response = tiny_http::Response::from_string(body).with_status_code(status).with_header(header);
+        if let Err(e) = request.respond(response) {
+            log::warn!("Failed to send response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// `/` 为首页，`/svg?module=<模块>&function=<函数完整路径>` 为单个函数的控制流图，省略 function 时为整个模块；
+// 找不到对应的模块或函数时返回 None
+fn serve_request(
+    url: &str,
+    modules: &[(String, PathBuf)],
+    config: &GraphConfig,
+    svg: SvgOptions,
+) -> Result<Option<(&'static str, String)>> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params: HashMap<String, String> = query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+        .collect();
+
+    match path {
+        "/" => {
+            let mut sections = Vec::new();
+            for (module, file) in modules {
+                let href = |function: Option<&str>| match function {
+                    Some(function) => format!("/svg?module={}&function={}", percent_encode(module), percent_encode(function)),
+                    None => format!("/svg?module={}", percent_encode(module)),
+                };
+                let mut links = vec![("（整个模块）".to_string(), href(None))];
+                // 首页只收集函数名，不构建控制流图
+                let mut functions = collect_file_functions(file).unwrap_or_default();
+                functions.retain(|func| {
+                    (config.include_tests || !func.is_test)
+                        && config.matches_function(&func.path)
+                        && !config.skips_function(&func.path)
+                });
+                functions.sort_by(|a, b| a.path.cmp(&b.path));
+                let module_path = ParserPass::module_path(file);
+                links.extend(functions.iter()
+                    .map(|func| (ReportPass::short_name(&func.path, &module_path).to_string(), href(Some(&func.path)))));
+                sections.push((module.clone(), links));
             }
-            
-            // 再添加所有边
-            if !edges.is_empty() {
-                merged.push_str(&edges.join("\n"));
-                merged.push_str("\n");
+            sections.sort();
+            Ok(Some(("text/html; charset=utf-8", HtmlViewerPass::render_index("控制流图", &sections))))
+        }
+        "/svg" => {
+            let Some((module, file)) = modules.iter().find(|(module, _)| params.get("module") == Some(module)) else {
+                return Ok(None);
+            };
+            let graph = analyze_file_with_timings(file, config, &mut Timings::new())?;
+            let styled = StylerPass::apply_style(&graph);
+            let (styled, title) = match params.get("function") {
+                Some(function) => match styled.names.iter().find(|(_, name)| *name == function) {
+                    Some((&id, _)) => (styled.function_subgraph(id), function),
+                    None => return Ok(None),
+                },
+                None => (styled, module),
+            };
+            Ok(Some(("image/svg+xml", render_svg(&DotRendererPass::render(&styled), &temp_output_path(), title, svg)?)))
+        }
+        _ => Ok(None),
+    }
+}
+
+// 查询参数只需要处理模块路径和函数路径中可能出现的字符
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
             }
-            
-            merged.push_str("    }\n\n");
         }
     }
-    
-    merged.push_str("}\n");
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn run_features(input: Option<PathBuf>, filter: &PathFilter, output: Option<PathBuf>, svg: SvgOptions) -> Result<()> {
+    let crate_root = get_crate_root().ok();
+    let files = match input {
+        Some(input_file) => vec![input_file],
+        None => find_rust_files(crate_root.as_deref().unwrap_or(Path::new(".")), filter)?,
+    };
+
+    let mut gates = Vec::new();
+    for file in files {
+        match collect_file_features(&file) {
+            Ok(collected) => gates.extend(collected),
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+
+    // Cargo.toml 中声明但没有被使用的 feature 也显示出来
+    let declared = match crate_root {
+        Some(root) => FeatureMapPass::declared_features(&std::fs::read_to_string(root.join("Cargo.toml"))?)?,
+        None => Vec::new(),
+    };
+
+    let output_path = output.unwrap_or_else(|| PathBuf::from("features.svg"));
+    write_svg(&FeatureMapPass::render_dot(&gates, &declared), &output_path, "Feature 映射", svg)?;
+    print_saved("Feature map", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+// 默认包含所有成员，-p 时只包含选中的成员
+fn run_workspace(
+    packages: Option<Vec<WorkspacePackage>>,
+    filter: &PathFilter,
+    output: Option<PathBuf>,
+    index: Option<&ScipIndex>,
+    owners: Option<&CodeOwners>,
+    svg: SvgOptions,
+) -> Result<()> {
+    let packages = match packages {
+        Some(packages) => packages,
+        None => WorkspacePass::load(&get_crate_root()?)?.1,
+    };
+    let mut crates = Vec::new();
+    for package in packages {
+        // 只遍历 lib 和 bin target 的源文件目录
+        let mut files = Vec::new();
+        for dir in &package.library_dirs {
+            files.extend(find_rust_files(dir, filter)?);
+        }
+        let (functions, imports) = collect_crate(&files);
+        crates.push(CrateFunctions { name: package.name, root: package.root, functions, imports });
+    }
+
+    let graph = CallGraphPass::build(&crates, index);
+    let output_path = output.unwrap_or_else(|| PathBuf::from("workspace_calls.svg"));
+    write_svg(&CallGraphPass::render_workspace_dot(&crates, &graph, owners), &output_path, "Workspace 调用图", svg)?;
+    print_saved("Workspace call graph", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+// 只包含当前 crate（或指定文件）的调用图输入
+fn current_crate(input: Option<PathBuf>, filter: &PathFilter) -> Result<Vec<CrateFunctions>> {
+    let crate_root = get_crate_root()?;
+    let files = match input {
+        Some(input_file) => vec![input_file],
+        None => find_rust_files(&crate_root, filter)?,
+    };
+    let name = WorkspacePass::load(&crate_root)
+        .ok()
+        .and_then(|(_, members)| members.into_iter().find(|package| package.root == crate_root))
+        .map(|package| package.name)
+        .unwrap_or_else(|| "crate".to_string());
+
+    let (functions, imports) = collect_crate(&files);
+    Ok(vec![CrateFunctions { name, root: crate_root, functions, imports }])
+}
+
+// 当前 crate 的函数调用图；指定根函数时只保留从根函数出发能到达的函数
+fn run_call(
+    input: Option<PathBuf>,
+    filter: &PathFilter,
+    output: Option<PathBuf>,
+    index: Option<&ScipIndex>,
+    roots: &[String],
+    theme: &Theme,
+    svg: SvgOptions,
+) -> Result<()> {
+    let crates = current_crate(input, filter)?;
+    let mut graph = CallGraphPass::build(&crates, index);
+    if !roots.is_empty() {
+        graph = graph.reachable_from(roots);
+        if graph.nodes.is_empty() {
+            bail!("No function matches --root {}", roots.join(", "));
+        }
+    }
+
+    let output_path = output.unwrap_or_else(|| PathBuf::from("call_graph.svg"));
+    let dot = DotRendererPass::render(&CallGraphPass::styled(&crates, &graph, roots, theme));
+    write_svg(&dot, &output_path, "函数调用图", svg)?;
+    print_saved("Call graph", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+// 模块树和模块之间的 use 依赖，使用控制流图的样式和渲染流程
+fn run_modules(filter: &PathFilter, output: Option<PathBuf>, theme: &Theme, labels: &Labels, svg: SvgOptions) -> Result<()> {
+    let mut modules = BTreeSet::new();
+    let mut imports = ImportTable::default();
+    for file in find_rust_files(&get_crate_root()?, filter)? {
+        match collect_file_modules(&file).and_then(|collected| Ok((collected, collect_file_imports(&file)?))) {
+            Ok((collected, table)) => {
+                modules.extend(collected);
+                imports.extend(table);
+            }
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+
+    let mut graph = ModuleGraphPass::build(&modules, &imports);
+    graph.set_theme(theme.clone());
+    graph.set_labels(labels.clone());
+    let output_path = output.unwrap_or_else(|| PathBuf::from("modules.svg"));
+    write_svg(&DotRenderer::default().render(&graph)?, &output_path, "模块依赖图", svg)?;
+    print_saved("Module graph", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+fn run_types(input: Option<PathBuf>, packages: Option<&[WorkspacePackage]>, filter: &PathFilter, output: Option<PathBuf>, theme: &Theme, svg: SvgOptions) -> Result<()> {
+    let files = source_files(input, packages, filter)?;
+    let mut items = TypeItems::default();
+    for file in files {
+        match collect_file_types(&file) {
+            Ok(collected) => items.extend(collected),
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+
+    let mut graph = TypeGraphPass::build(&items);
+    graph.set_theme(theme.clone());
+    let output_path = output.unwrap_or_else(|| PathBuf::from("types.svg"));
+    write_svg(&DotRenderer::default().render(&graph)?, &output_path, "类型关系图", svg)?;
+    print_saved("Type graph", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+fn run_deps(output: Option<PathBuf>, options: DependencyOptions, theme: &Theme, svg: SvgOptions) -> Result<()> {
+    let mut deps = DependencyGraphPass::build(&get_crate_root()?, &options)?;
+    deps.graph.set_theme(theme.clone());
+    for package in deps.denied.values() {
+        log::warn!("Denied license in dependency graph: {}", package);
+    }
+    let output_path = output.unwrap_or_else(|| PathBuf::from("deps.svg"));
+    write_svg(&DependencyGraphPass::to_dot(&deps), &output_path, "Crate 依赖图", svg)?;
+    print_saved("Dependency graph", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+// 当前 crate 的调用图邻接矩阵和每个函数的度/中心性指标。
+// 输出文件扩展名为 .json 时输出 JSON，否则输出两个 CSV（矩阵和 .metrics.csv）
+fn run_matrix(input: Option<PathBuf>, filter: &PathFilter, output: Option<PathBuf>, index: Option<&ScipIndex>) -> Result<()> {
+    let crates = current_crate(input, filter)?;
+    let matrix = MatrixPass::build(&crates, &CallGraphPass::build(&crates, index));
+
+    let output_path = output.unwrap_or_else(|| PathBuf::from("call_matrix.csv"));
+    if output_path.extension().is_some_and(|ext| ext == "json") {
+        std::fs::write(&output_path, MatrixPass::render_json(&matrix)?)?;
+        print_saved("Call matrix", &output_path);
+    } else {
+        let metrics_path = output_path.with_extension("metrics.csv");
+        std::fs::write(&output_path, MatrixPass::render_csv(&matrix))?;
+        std::fs::write(&metrics_path, MatrixPass::render_metrics_csv(&matrix))?;
+        print_saved("Call matrix", &output_path);
+        print_saved("Call graph metrics", &metrics_path);
+    }
+    Ok(())
+}
+
+// 复杂度、函数数、unsafe 数量和节点数，输出为 OpenMetrics 文本格式，
+// 可以交给 node_exporter 的 textfile collector 或 Pushgateway 采集
+fn run_openmetrics(input: Option<PathBuf>, packages: Option<&[WorkspacePackage]>, filter: &PathFilter, output: Option<PathBuf>, config: &GraphConfig) -> Result<()> {
+    let files = source_files(input, packages, filter)?;
+
+    let mut metrics = Vec::new();
+    for file in files {
+        let result = collect_file_functions(&file).and_then(|functions| {
+            let graph = analyze_file_with_timings(&file, config, &mut Timings::new())?;
+            Ok(OpenMetricsPass::collect(&functions, &graph))
+        });
+        match result {
+            Ok(collected) => metrics.extend(collected),
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+
+    let output_path = output.unwrap_or_else(|| PathBuf::from("crate_metrics.prom"));
+    std::fs::write(&output_path, OpenMetricsPass::render(&metrics))?;
+    print_saved("Metrics", &output_path);
+    Ok(())
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// crate 中所有函数的控制流图，可以序列化保存
+fn snapshot_crate(crate_root: &Path, filter: &PathFilter, config: &GraphConfig) -> Result<Vec<FunctionSnapshot>> {
+    let mut functions = Vec::new();
+    for file in find_rust_files(crate_root, filter)? {
+        match analyze_file_with_timings(&file, config, &mut Timings::new()) {
+            Ok(graph) => functions.extend(HistoryPass::snapshot(&graph)),
+            Err(e) => log::warn!("Failed to analyze {}: {}", file.display(), e),
+        }
+    }
+    Ok(functions)
+}
+
+// 重新分析 crate 并与提交到仓库中的基准控制流图比较，有差异时返回错误（退出码非 0）；
+// --update 时用当前结果覆盖基准
+fn run_verify(against: &Path, update: bool, filter: &PathFilter, config: &GraphConfig) -> Result<()> {
+    let functions = snapshot_crate(&get_crate_root()?, filter, config)?;
+    if update {
+        let count = functions.len();
+        VerifyPass::save(against, &VerifyPass::baseline(functions))?;
+        print_saved(&format!("Baseline with {} functions", count), against);
+        return Ok(());
+    }
+
+    let report = VerifyPass::compare(&VerifyPass::load(against)?, &functions);
+    if report.is_clean() {
+        println!("Control flow matches baseline: {}", against.display());
+        return Ok(());
+    }
+    print!("{}", report.render_text());
+    bail!(
+        "Control flow differs from baseline {} ({} added, {} removed, {} changed functions); \
+         review the changes and run with --update to accept them",
+        against.display(),
+        report.added_functions.len(),
+        report.removed_functions.len(),
+        report.changed_functions.len()
+    )
+}
+
+// 用 git 读取某个版本中 crate 的所有源文件并分析，不改动工作区
+fn snapshot_revision(crate_root: &Path, revision: &str, filter: &PathFilter, config: &GraphConfig) -> Result<Vec<FunctionSnapshot>> {
+    let build_files = build_script_files(crate_root);
+    let mut functions = Vec::new();
+    for file in git(crate_root, &["ls-tree", "-r", "--name-only", revision, "--", "."])?.lines() {
+        let path = crate_root.join(file);
+        if !file.ends_with(".rs") || !filter.allows(&path) || build_files.contains(&path) {
+            continue;
+        }
+        let source = git(crate_root, &["show", &format!("{}:./{}", revision, file)])?;
+        match analyze_file_contents(&path, &source, config, &mut Timings::new()) {
+            Ok(graph) => functions.extend(HistoryPass::snapshot(&graph)),
+            Err(e) => log::warn!("Failed to analyze {} at {}: {}", file, revision, e),
+        }
+    }
+    Ok(functions)
+}
+
+// 先输出与 verify 相同的文本差异，再把发生变化的函数画成一张图
+fn run_diff(base: &str, head: &str, filter: &PathFilter, output: Option<PathBuf>, config: &GraphConfig, svg: SvgOptions) -> Result<()> {
+    let crate_root = get_crate_root()?;
+    let mut config = config.clone();
+    if config.expand_macros {
+        log::warn!("--expand-macros is not supported by diff, analyzing unexpanded code");
+        config.expand_macros = false;
+    }
+    let before = snapshot_revision(&crate_root, base, filter, &config)?;
+    let after = snapshot_revision(&crate_root, head, filter, &config)?;
+
+    let report = VerifyPass::compare(&VerifyPass::baseline(before.clone()), &after);
+    if report.is_clean() {
+        println!("No control flow changes between {} and {}", base, head);
+        return Ok(());
+    }
+    print!("{}", report.render_text());
+
+    let diff = DiffPass::compare(&before, &after, &config);
+    let output_path = output.unwrap_or_else(|| PathBuf::from("graph_diff.svg"));
+    write_svg(&DiffPass::to_dot(&diff), &output_path, &format!("{}..{} 的控制流变化", base, head), svg)?;
+    print_saved("Diff graph", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+// 以 HEAD 提交为键保存整个 crate 的控制流图，同一提交重复记录时覆盖
+fn run_history_record(filter: &PathFilter, config: &GraphConfig) -> Result<()> {
+    let crate_root = get_crate_root()?;
+    let commit = git(&crate_root, &["rev-parse", "HEAD"])?;
+    let timestamp = git(&crate_root, &["show", "-s", "--format=%ct", "HEAD"])?.parse()?;
+    let date = git(&crate_root, &["show", "-s", "--format=%cI", "HEAD"])?;
+    if !git(&crate_root, &["status", "--porcelain", "--", ".", ":!.cargo-graph"])?.is_empty() {
+        log::warn!("Working tree has uncommitted changes, recording them under {}", commit);
+    }
+
+    let functions = snapshot_crate(&crate_root, filter, config)?;
+    let count = functions.len();
+    let path = HistoryPass::save(&crate_root, &Snapshot { commit, timestamp, date, functions })?;
+    print_saved(&format!("{} function snapshots", count), &path);
+    Ok(())
+}
+
+// 按提交时间列出函数的复杂度变化，并把各个版本的控制流图并排画在一张图中
+fn run_history_show(function: &str, output: Option<PathBuf>, theme: &Theme, svg: SvgOptions) -> Result<()> {
+    let crate_root = get_crate_root()?;
+    let snapshots = HistoryPass::load(&crate_root)?;
+    let versions = HistoryPass::versions(&snapshots, function);
+    if versions.is_empty() {
+        bail!("No recorded history for function: {}", function);
+    }
+
+    print!("{}", HistoryPass::render_text(&versions));
+    let output_path = output.unwrap_or_else(|| PathBuf::from("history_flow.svg"));
+    let mut graph = HistoryPass::evolution_graph(&versions);
+    graph.set_theme(theme.clone());
+    write_svg(&graph.to_dot(), &output_path, &format!("{} 的控制流图历史", function), svg)?;
+    print_saved("History chart", &output_path);
+    open_output(&output_path, svg);
+    Ok(())
+}
+
+// 文档按源文件的相对路径放在输出目录下（src/net/conn.rs -> src/net/conn.md），
+// SVG 图片放在与文档同名的目录中，最后生成一个索引页
+fn run_report(
+    input: Option<PathBuf>,
+    filter: &PathFilter,
+    output: Option<PathBuf>,
+    config: &GraphConfig,
+    diagrams: ReportDiagrams,
+    svg: SvgOptions,
+) -> Result<()> {
+    let crate_root = get_crate_root().ok();
+    let files = match input {
+        Some(input_file) => vec![input_file],
+        None => find_rust_files(crate_root.as_deref().context("Could not find Cargo.toml")?, filter)?,
+    };
+    let output_dir = output.unwrap_or_else(|| PathBuf::from("graph-report"));
+
+    let mut index = String::from("# 控制流报告\n\n| 模块 | 源文件 |\n|---|---|\n");
+    let mut count = 0;
+    for file in files {
+        let graph = match analyze_file_with_timings(&file, config, &mut Timings::new()) {
+            Ok(graph) => graph,
+            Err(e) => {
+                log::warn!("Failed to analyze {}: {}", file.display(), e);
+                continue;
+            }
+        };
+        let relative = crate_root.as_deref()
+            .and_then(|root| file.strip_prefix(root).ok())
+            .unwrap_or(&file)
+            .with_extension("");
+        let relative_name = relative.to_string_lossy().replace('\\', "/");
+        let module = ParserPass::module_path(&file);
+        let image_dir = relative.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let report = ReportPass::render_module(&graph, &module, &ParserPass::display_path(&file), diagrams, &image_dir);
+
+        let document = output_dir.join(format!("{}.md", relative_name));
+        let document_dir = document.parent().unwrap_or(&output_dir).to_path_buf();
+        std::fs::create_dir_all(&document_dir)?;
+        std::fs::write(&document, &report.markdown)?;
+        for (image, function, dot) in &report.images {
+            let path = document_dir.join(image);
+            std::fs::create_dir_all(path.parent().unwrap_or(&document_dir))?;
+            write_svg(dot, &path, function, svg)?;
+        }
+        index.push_str(&format!("| [`{}`]({}.md) | `{}` |\n", module, relative_name, ParserPass::display_path(&file)));
+        count += 1;
+    }
+
+    std::fs::create_dir_all(&output_dir)?;
+    std::fs::write(output_dir.join("index.md"), index)?;
+    print_saved(&format!("Report for {} modules", count), &output_dir);
+    Ok(())
+}
+
+// 通过临时 DOT 文件调用 dot 命令生成 SVG，并补充标题、描述和 ARIA role 等无障碍信息
+fn write_svg(dot_content: &str, output_path: &Path, title: &str, svg: SvgOptions) -> Result<()> {
+    write_atomic(output_path, render_svg(dot_content, output_path, title, svg)?)
+}
+
+// --quiet 时只输出保存的路径，便于脚本使用
+fn print_saved(what: &str, path: &Path) {
+    if log::max_level() <= log::LevelFilter::Error {
+        println!("{}", path.display());
+    } else {
+        println!("{} saved to: {}", what, path.display());
+    }
+}
+
+// --open 时用系统默认程序打开生成的文件（或预览服务的地址）。只打开第一个结果，
+// 输出多种格式或 --watch 重新生成时不再重复打开
+fn open_output(target: impl AsRef<std::ffi::OsStr>, svg: SvgOptions) {
+    static OPENED: std::sync::Once = std::sync::Once::new();
+    if svg.open {
+        OPENED.call_once(|| {
+            if let Err(e) = open::that_detached(target.as_ref()) {
+                log::warn!("Failed to open {}: {}", target.as_ref().to_string_lossy(), e);
+            }
+        });
+    }
+}
+
+// 先写到同一目录下的临时文件再重命名，--watch 时浏览器不会读到写了一半的文件
+fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let file_name = path.file_name().context("Output path has no file name")?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    std::fs::write(&temp_path, contents)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+// 按输出格式写出渲染结果：文本格式直接写入，dot 写出 DOT 源码，其余经过 Graphviz 布局。
+// output_path 为 None 时写到标准输出，不打印保存位置
+// sources 为 HTML 查看器中点击节点显示的源码
+fn write_graph_output(
+    format: &str,
+    style: &str,
+    content: &str,
+    output_path: Option<&Path>,
+    sources: Option<&SourcePanel>,
+    args: &Args,
+    report: &mut TimingReport,
+) -> Result<()> {
+    let start = Instant::now();
+    // Mermaid、PlantUML 和 GraphML 由各自的工具渲染，不需要 Graphviz
+    let text_output = match style {
+        "mermaid" => Some("Mermaid flowchart"),
+        "plantuml" => Some("PlantUML activity diagram"),
+        "graphml" => Some("GraphML"),
+        "json" => Some("Graph JSON"),
+        "d2" => Some("D2 diagram"),
+        "ascii" => Some("Terminal flowchart"),
+        _ if format == "dot" => Some("DOT source"),
+        _ => None,
+    };
+    let (kind, output) = if let Some(kind) = text_output {
+        (kind, content.to_string())
+    } else if format == "html" {
+        let svg = render_svg(content, output_path.unwrap_or(&temp_output_path()), "控制流图", svg_options(args))?;
+        let html = match sources {
+            Some(sources) => HtmlViewerPass::render_with_sources(&svg, content, "控制流图", sources),
+            None => HtmlViewerPass::render(&svg, content, "控制流图"),
+        };
+        report.global.record_since("graphviz", start, 1);
+        ("Interactive viewer", if args.watch { HtmlViewerPass::live_reload(&html) } else { html })
+    } else if format == "layout" {
+        // 只输出布局后的节点坐标和边的样条，供自定义查看器使用
+        let layout = LayoutPass::layout(content)?;
+        report.global.record_since("graphviz", start, 1);
+        ("Layout", LayoutPass::render_json(&layout)?)
+    } else {
+        // 使用 dot 命令转换为 SVG
+        let svg = render_svg(content, output_path.unwrap_or(&temp_output_path()), "控制流图", svg_options(args))?;
+        report.global.record_since("graphviz", start, 1);
+        ("Flow chart", svg)
+    };
+
+    match output_path {
+        Some(output_path) => {
+            write_atomic(output_path, output)?;
+            print_saved(kind, output_path);
+            // 只打开可以直接查看的 SVG 和 HTML
+            if text_output.is_none() && format != "layout" {
+                open_output(output_path, svg_options(args));
+            }
+        }
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(output.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+// 不写文件时（标准输出、预览服务）调用 dot 用的临时路径，临时 DOT 文件放在系统临时目录中
+fn temp_output_path() -> PathBuf {
+    std::env::temp_dir().join(format!("cargo-graph-{}.svg", std::process::id()))
+}
+
+// 渲染为带无障碍标注的 SVG。使用 dot 时中间的 DOT 文件放在输出文件旁边，成功后删除（--keep-dot 时保留），
+// 失败时保留以便排查；PATH 中找不到 dot 时退回内置渲染器
+fn render_svg(dot_content: &str, output_path: &Path, title: &str, svg: SvgOptions) -> Result<String> {
+    let dot_path = output_path.with_extension("dot");
+    // 已有的同名文件（如同时输出的 dot 格式）不删除
+    let existed = dot_path.exists();
+    let graphviz = match svg.renderer {
+        SvgRenderer::Dot => find_graphviz(),
+        SvgRenderer::Builtin => None,
+    };
+    if graphviz.is_some() || svg.keep_dot {
+        std::fs::write(&dot_path, dot_content)?;
+    }
+
+    let rendered = match &graphviz {
+        Some(dot) => {
+            let output = std::process::Command::new(dot)
+                .arg("-Tsvg")
+                .arg(&dot_path)
+                .output()
+                .with_context(|| format!("Failed to run {}, the DOT source was kept at {}", dot.display(), dot_path.display()))?;
+            if !output.status.success() {
+                bail!(
+                    "Graphviz failed to convert {} to SVG: {}",
+                    dot_path.display(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            String::from_utf8(output.stdout)?
+        }
+        None if svg.renderer == SvgRenderer::Dot => {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| log::warn!(
+                "Graphviz `dot` was not found on PATH, falling back to the builtin renderer (no cluster boxes). \
+                 Install Graphviz from https://graphviz.org/download/ for full output, or pass --renderer builtin to silence this warning"
+            ));
+            match BuiltinSvgPass::render(dot_content) {
+                Ok(rendered) => rendered,
+                Err(e) => {
+                    std::fs::write(&dot_path, dot_content)?;
+                    bail!(
+                        "Graphviz `dot` was not found on PATH and the builtin renderer failed: {}. \
+                         The DOT source was saved to {}; install Graphviz from https://graphviz.org/download/ and run `dot -Tsvg {} -o {}`",
+                        e,
+                        dot_path.display(),
+                        dot_path.display(),
+                        output_path.display()
+                    );
+                }
+            }
+        }
+        None => BuiltinSvgPass::render(dot_content)?,
+    };
+
+    if graphviz.is_some() && !svg.keep_dot && !existed {
+        std::fs::remove_file(&dot_path)?;
+    }
+    Ok(AccessibilityPass::annotate(&rendered, title))
+}
+
+// 在 PATH 中查找 Graphviz 的 dot 命令
+fn find_graphviz() -> Option<PathBuf> {
+    let name = if cfg!(windows) { "dot.exe" } else { "dot" };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+// 每个模块一个子图；各文件输出中的 `flowchart TD` 头只保留一份
+fn merge_mermaid(graphs: Vec<(String, String)>) -> String {
+    let mut merged = String::from("flowchart TD\n");
+    for (i, (module_name, graph)) in graphs.iter().enumerate() {
+        merged.push_str(&format!("    subgraph module_{}[\"{}\"]\n", i, MermaidEscape::text(module_name)));
+        for line in graph.lines().filter(|line| *line != "flowchart TD") {
+            merged.push_str("    ");
+            merged.push_str(line);
+            merged.push('\n');
+        }
+        merged.push_str("    end\n");
+    }
     merged
 }
 
+// 每个函数已经是独立的 `@startuml` 块，按模块依次排列并以注释标出模块名
+fn merge_plantuml(graphs: Vec<(String, String)>) -> String {
+    let mut merged = String::new();
+    for (module_name, graph) in graphs {
+        merged.push_str(&format!("' module: {}\n\n", module_name));
+        merged.push_str(&graph);
+    }
+    merged
+}
+
+fn merge_terminal(graphs: Vec<(String, String)>) -> String {
+    graphs.into_iter()
+        .map(|(module_name, graph)| format!("### {}\n\n{}", module_name, graph))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 每个模块一个容器，各文件的边在容器内用相对路径引用节点；classes 只能定义在顶层，合并后统一输出
+fn merge_d2(graphs: Vec<(String, String)>) -> String {
+    let mut classes = BTreeSet::new();
+    let mut modules = String::new();
+    for (i, (module_name, graph)) in graphs.iter().enumerate() {
+        modules.push_str(&format!("m{}: \"{}\" {{\n", i, module_name.replace('\\', "/").replace('"', "\\\"")));
+        let mut in_classes = false;
+        for line in graph.lines() {
+            match line {
+                "direction: down" => {}
+                "classes: {" => in_classes = true,
+                "}" if in_classes => in_classes = false,
+                _ if in_classes => {
+                    classes.insert(line.to_string());
+                }
+                _ => {
+                    modules.push_str("  ");
+                    modules.push_str(line);
+                    modules.push('\n');
+                }
+            }
+        }
+        modules.push_str("}\n");
+    }
+
+    let mut merged = String::from("direction: down\n");
+    if !classes.is_empty() {
+        merged.push_str("classes: {\n");
+        for class in classes {
+            merged.push_str(&class);
+            merged.push('\n');
+        }
+        merged.push_str("}\n");
+    }
+    merged.push_str(&modules);
+    merged
+}
+
+// 各文件的函数、节点和边依次拼接；ID 已经包含所在文件，不会冲突
+fn merge_json(graphs: Vec<(String, String)>) -> Result<String> {
+    let mut merged = serde_json::json!({ "functions": [], "nodes": [], "edges": [] });
+    for (_, graph) in graphs {
+        let graph: serde_json::Value = serde_json::from_str(&graph)?;
+        for key in ["functions", "nodes", "edges"] {
+            if let (Some(target), Some(items)) = (merged[key].as_array_mut(), graph[key].as_array()) {
+                target.extend(items.iter().cloned());
+            }
+        }
+    }
+    Ok(serde_json::to_string_pretty(&merged)?)
+}
+
+// 取出各文件 GraphML 中的节点和边放进同一个图；节点和边的 ID 加上文件序号前缀以免冲突，
+// 每个节点已经带有所在文件的属性
+fn merge_graphml(graphs: Vec<(String, String)>) -> String {
+    let mut header = String::new();
+    let mut body = String::new();
+    for (i, (_, graph)) in graphs.iter().enumerate() {
+        let Some((head, rest)) = graph.split_once("  <graph ") else {
+            continue;
+        };
+        if header.is_empty() {
+            header = head.to_string();
+        }
+        let content = rest.split_once('\n').map_or("", |(_, content)| content);
+        for line in content.lines().filter(|line| *line != "  </graph>" && *line != "</graphml>") {
+            body.push_str(&line
+                .replace(" id=\"", &format!(" id=\"m{}_", i))
+                .replace(" source=\"", &format!(" source=\"m{}_", i))
+                .replace(" target=\"", &format!(" target=\"m{}_", i)));
+            body.push('\n');
+        }
+    }
+    format!("{}  <graph id=\"G\" edgedefault=\"directed\">\n{}  </graph>\n</graphml>\n", header, body)
+}
+
+// 各文件的控制流图依次并入同一个图，函数记录所在的模块，渲染时每个模块一个分组；
+// 节点编号随之后移，不同文件中的节点不会冲突
+fn merge_graphs(graphs: &[(String, FlowGraph)]) -> FlowGraph {
+    let config = graphs.first().map(|(_, graph)| graph.config().clone()).unwrap_or_default();
+    let mut merged = FlowGraph::with_config(config);
+    // 按模块名和源文件合并，节点编号与文件的遍历和分析顺序无关
+    let mut ordered: Vec<_> = graphs.iter().collect();
+    ordered.sort_by_cached_key(|(module_name, graph)| {
+        (module_name.clone(), graph.function_info(0).and_then(|info| info.file.clone()))
+    });
+    for (module_name, graph) in ordered {
+        merged.merge(graph.clone(), module_name);
+    }
+    merged
+}
+
+// cargo-graph.toml 中的设置只在命令行没有给出对应参数时生效，返回其中按函数覆盖的选项
+fn apply_project_config(args: &mut Args, matches: &ArgMatches, project: ProjectConfig) -> Result<Vec<FunctionOverride>> {
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+    if unset("style") && let Some(style) = project.style {
+        args.style = style;
+    }
+    if unset("format") && let Some(format) = project.format {
+        args.format = format;
+    }
+    if unset("renderer") && let Some(renderer) = project.renderer {
+        args.renderer = renderer.parse().map_err(anyhow::Error::msg)?;
+    }
+    if unset("theme") && let Some(theme) = project.theme {
+        args.theme = theme;
+    }
+    if unset("lang") && let Some(lang) = project.lang {
+        args.lang = lang;
+    }
+    if unset("link_template") && let Some(link_template) = project.link_template {
+        args.link_template = link_template;
+    }
+    if unset("include") && let Some(include) = project.include {
+        args.include = include;
+    }
+    if unset("exclude") && let Some(exclude) = project.exclude {
+        args.exclude = exclude;
+    }
+    if unset("block_granularity") && let Some(block_granularity) = project.block_granularity {
+        args.block_granularity = block_granularity.parse().map_err(anyhow::Error::msg)?;
+    }
+    if unset("max_merged") && let Some(max_merged) = project.max_merged {
+        args.max_merged = Some(max_merged);
+    }
+    let flags = [
+        ("include_tests", project.include_tests, &mut args.include_tests),
+        ("html_labels", project.html_labels, &mut args.html_labels),
+        ("group_declarations", project.group_declarations, &mut args.group_declarations),
+        ("expand_boolean_ops", project.expand_boolean_ops, &mut args.expand_boolean_ops),
+        ("flat_else_if", project.flat_else_if, &mut args.flat_else_if),
+        ("show_recursion", project.show_recursion, &mut args.show_recursion),
+        ("highlight_loops", project.highlight_loops, &mut args.highlight_loops),
+    ];
+    for (id, value, flag) in flags {
+        if unset(id) && let Some(value) = value {
+            *flag = value;
+        }
+    }
+    Ok(project.overrides)
+}
+
+// 命令行参数与 cargo-graph.toml 合并后的结果，以及按函数覆盖的选项
+fn load_args(matches: &ArgMatches, crate_root: Option<&Path>) -> Result<(Args, Vec<FunctionOverride>)> {
+    let mut args = Args::from_arg_matches(matches).unwrap_or_else(|e| e.exit());
+    let overrides = match crate_root.map(ProjectConfig::load).transpose()?.flatten() {
+        Some(project) => apply_project_config(&mut args, matches, project)?,
+        None => Vec::new(),
+    };
+    Ok((args, overrides))
+}
+
+fn svg_options(args: &Args) -> SvgOptions {
+    SvgOptions { renderer: args.renderer, keep_dot: args.keep_dot, open: args.open }
+}
+
+// --include / --exclude 的模式相对于 crate 根目录；找不到 cargo metadata 时认为构建输出在 <根目录>/target
+fn path_filter(args: &Args, crate_root: Option<&Path>) -> PathFilter {
+    let target_dir = crate_root
+        .filter(|_| !args.no_ignore)
+        .map(|root| WorkspacePass::target_dir(root).unwrap_or_else(|_| root.join("target")));
+    PathFilter {
+        root: crate_root.map_or_else(|| PathBuf::from("."), Path::to_path_buf),
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        respect_ignore: !args.no_ignore,
+        target_dir,
+    }
+}
+
+// 分析结果缓存在 cargo 的构建输出目录下，--no-cache 时不使用
+fn cache_dir(args: &Args, crate_root: Option<&Path>, filter: &PathFilter) -> Option<PathBuf> {
+    let crate_root = crate_root.filter(|_| !args.no_cache)?;
+    let target_dir = filter.target_dir.clone()
+        .unwrap_or_else(|| WorkspacePass::target_dir(crate_root).unwrap_or_else(|_| crate_root.join("target")));
+    Some(target_dir.join("cargo-graph").join("cache"))
+}
+
+// 先生成一次，之后每当参与分析的 .rs 文件（或 -i 指定的文件）变化时重新生成；
+// 出错时只打印错误并继续监视，改好代码后会自动恢复
+fn run_watch(matches: &ArgMatches) -> Result<()> {
+    let crate_root = get_crate_root().ok();
+    let (args, _) = load_args(matches, crate_root.as_deref())?;
+    let filter = path_filter(&args, crate_root.as_deref());
+    let watched = match (args.input.clone(), crate_root) {
+        (Some(input_file), _) => input_file,
+        (None, Some(root)) => root,
+        (None, None) => bail!("Could not find Cargo.toml in current directory or any parent directory"),
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&watched, RecursiveMode::Recursive)?;
+    let changed = |event: notify::Result<notify::Event>| {
+        event.is_ok_and(|event| {
+            !event.kind.is_access()
+                && event.paths.iter().any(|path| path.extension().is_some_and(|ext| ext == "rs") && filter.allows(path))
+        })
+    };
+    loop {
+        if let Err(e) = run(matches) {
+            log::error!("{:#}", e);
+        }
+        log::info!("Watching {} for changes (press Ctrl+C to stop)", watched.display());
+        while !changed(receiver.recv()?) {}
+        // 编辑器保存时往往连续产生多个事件，稍等片刻合并为一次重新生成
+        while receiver.recv_timeout(Duration::from_millis(200)).is_ok() {}
+    }
+}
+
 fn main() -> Result<()> {
-    let args = Args::parse();
+    // 通过 `cargo graph` 调用时，cargo 会把子命令名 `graph` 作为第一个参数传入
+    let mut argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    if argv.get(1).is_some_and(|arg| arg == "graph") {
+        argv.remove(1);
+    }
+    let matches = Args::command().get_matches_from(argv);
+    init_logger(&matches);
+    if matches.get_flag("watch") {
+        run_watch(&matches)
+    } else {
+        run(&matches)
+    }
+}
+
+// 默认输出 info 级别的进度信息，-v 增加每个文件的调试信息，-vv 连同依赖库的日志全部输出；
+// --quiet 只保留错误。RUST_LOG 可以进一步按模块调整
+fn init_logger(matches: &ArgMatches) {
+    use log::LevelFilter;
+    let (dependencies, own) = match (matches.get_flag("quiet"), matches.get_count("verbose")) {
+        (true, _) => (LevelFilter::Error, LevelFilter::Error),
+        (false, 0) => (LevelFilter::Warn, LevelFilter::Info),
+        (false, 1) => (LevelFilter::Warn, LevelFilter::Debug),
+        (false, _) => (LevelFilter::Trace, LevelFilter::Trace),
+    };
+    let logger = env_logger::Builder::new()
+        .filter_level(dependencies)
+        .filter_module("cargo_graph", own)
+        .parse_default_env()
+        .format(|buf, record| {
+            use std::io::Write;
+            match record.level() {
+                log::Level::Error => writeln!(buf, "Error: {}", record.args()),
+                log::Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+                _ => writeln!(buf, "{}", record.args()),
+            }
+        })
+        .build();
+    // 输出日志时先收起进度条，避免两者混在同一行
+    let level = logger.filter();
+    let multi = PROGRESS.get_or_init(MultiProgress::new).clone();
+    if indicatif_log_bridge::LogWrapper::new(multi, logger).try_init().is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+// 分析大量文件时在标准错误上显示进度（第几个文件和当前路径）；标准错误不是终端或 --quiet 时不显示
+fn progress_bar(len: usize) -> ProgressBar {
+    match PROGRESS.get() {
+        Some(multi) if std::io::stderr().is_terminal() && log::max_level() > log::LevelFilter::Error => {
+            let style = ProgressStyle::with_template("{bar:30.cyan/blue} {pos}/{len} {wide_msg}")
+                .expect("valid progress template");
+            multi.add(ProgressBar::new(len as u64).with_style(style))
+        }
+        _ => ProgressBar::hidden(),
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<()> {
+    let crate_root = get_crate_root().ok();
+    let (args, overrides) = load_args(matches, crate_root.as_deref())?;
+    // 提供 SCIP 索引时用索引精确解析调用，否则按名称和 use 语句推断
+    let index = args.index.as_deref().map(ScipIndex::load).transpose()?;
+    let owners = load_owners(args.ownership, args.owners_file.as_deref())?;
+    let packages = match args.input {
+        Some(_) => None,
+        None => selected_packages(&args.packages, args.workspace)?,
+    };
+    let filter = path_filter(&args, crate_root.as_deref());
+    let mut config = GraphConfig {
+        include_tests: args.include_tests,
+        function_filter: args.functions.clone(),
+        item_paths: args.item_paths.clone(),
+        reachability: args.reachability,
+        html_labels: args.html_labels,
+        group_declarations: args.group_declarations,
+        expand_boolean_ops: args.expand_boolean_ops,
+        show_recursion: args.show_recursion,
+        highlight_loops: args.highlight_loops,
+        expand_macros: args.expand_macros,
+        flat_else_if: args.flat_else_if,
+        block_granularity: args.block_granularity,
+        max_merged_statements: args.max_merged,
+        churn: args.churn,
+        theme: Theme::load(&args.theme)?,
+        labels: Labels::load(&args.lang)?,
+        link_template: Some(args.link_template.clone()).filter(|template| !template.is_empty()),
+        function_overrides: overrides,
+        cache_dir: cache_dir(&args, crate_root.as_deref(), &filter),
+        ..GraphConfig::default()
+    };
+    // 内置渲染器无法解析 HTML-like 标签，退回普通标签
+    if args.renderer == SvgRenderer::Builtin && config.html_labels {
+        log::warn!("--html-labels is not supported by the builtin renderer, using plain labels");
+        config.html_labels = false;
+    }
+    let svg = svg_options(&args);
     
     match args.command {
-        Some(Commands::Graph) => {
-            let renderer: Box<dyn GraphRenderer> = match args.style.as_str() {
-                "default" => Box::new(DotRenderer::default()),
-                "c-style" => Box::new(CStyleFlowchartRenderer::default()),
-                style => bail!("Unsupported style: {}", style),
-            };
+        Some(GraphAction::Stats { format, chart }) => {
+            run_stats(args.input, packages.as_deref(), &filter, &config, &format, chart)
+        }
+        Some(GraphAction::Complexity { threshold }) => {
+            run_complexity(args.input, packages.as_deref(), &filter, &config, threshold)
+        }
+        Some(GraphAction::Lint) => {
+            run_lint(args.input, packages.as_deref(), &filter, &config)
+        }
+        Some(GraphAction::Features) => {
+            run_features(args.input, &filter, args.output, svg)
+        }
+        Some(GraphAction::Workspace) => {
+            run_workspace(packages, &filter, args.output, index.as_ref(), owners.as_ref(), svg)
+        }
+        Some(GraphAction::Call { roots }) => {
+            run_call(args.input, &filter, args.output, index.as_ref(), &roots, &config.theme, svg)
+        }
+        Some(GraphAction::Modules) => {
+            run_modules(&filter, args.output, &config.theme, &config.labels, svg)
+        }
+        Some(GraphAction::Types) => {
+            run_types(args.input, packages.as_deref(), &filter, args.output, &config.theme, svg)
+        }
+        Some(GraphAction::Deps { depth, dedupe_versions, features, deny_licenses }) => {
+            run_deps(args.output, DependencyOptions { depth, dedupe_versions, features, deny_licenses }, &config.theme, svg)
+        }
+        Some(GraphAction::Verify { against, update }) => {
+            run_verify(&against, update, &filter, &config)
+        }
+        Some(GraphAction::Diff { base, head }) => {
+            run_diff(&base, &head, &filter, args.output, &config, svg)
+        }
+        Some(GraphAction::Report { diagrams }) => {
+            run_report(args.input, &filter, args.output, &config, diagrams, svg)
+        }
+        Some(GraphAction::Serve { port }) => {
+            let modules = || source_modules(args.input.clone(), packages.as_deref(), &filter);
+            run_serve(port, modules, &config, svg)
+        }
+        Some(GraphAction::History { action: HistoryAction::Record }) => {
+            run_history_record(&filter, &config)
+        }
+        Some(GraphAction::History { action: HistoryAction::Show { function } }) => {
+            run_history_show(&function, args.output, &config.theme, svg)
+        }
+        None | Some(GraphAction::Graph) if let Some(split) = args.split_by => {
+            run_split(source_modules(args.input, packages.as_deref(), &filter)?, args.output, &config, split, svg)
+        }
+        None | Some(GraphAction::Graph) if args.format == "matrix" => {
+            run_matrix(args.input, &filter, args.output, index.as_ref())
+        }
+        None | Some(GraphAction::Graph) if args.format == "openmetrics" => {
+            run_openmetrics(args.input, packages.as_deref(), &filter, args.output, &config)
+        }
+        None | Some(GraphAction::Graph) => {
+            // 逗号分隔的多种格式共用一次分析，例如 `--format svg,dot,json`
+            let formats: Vec<&str> = args.format.split(',').map(str::trim).filter(|format| !format.is_empty()).collect();
+            if formats.is_empty() {
+                bail!("No output format given");
+            }
+            // 调用矩阵和 OpenMetrics 不从控制流图渲染，只能单独输出
+            if let Some(format) = formats.iter().find(|format| matches!(**format, "matrix" | "openmetrics")) {
+                bail!("--format {} cannot be combined with other formats, run it separately", format);
+            }
+            // `-o -` 或 --stdout 时输出写到标准输出，便于接到管道中，例如 `--format dot -o - | dot -Tpng`
+            let to_stdout = args.stdout || args.output.as_deref() == Some(Path::new("-"));
+            if to_stdout && formats.len() > 1 {
+                bail!("Only one output format can be written to stdout");
+            }
+            let outputs: Vec<(&str, &str, Option<PathBuf>)> = formats.iter()
+                .map(|&format| {
+                    // --format mmd 等同于 --style mermaid，--format puml 等同于 --style plantuml
+                    let style = match format {
+                        "mmd" => "mermaid",
+                        "puml" => "plantuml",
+                        "graphml" => "graphml",
+                        "json" => "json",
+                        "d2" => "d2",
+                        "ascii" => "ascii",
+                        _ => args.style.as_str(),
+                    };
+                    let extension = match format {
+                        "layout" => "json",
+                        "ascii" => "txt",
+                        _ if style == "mermaid" => "mmd",
+                        _ if style == "plantuml" => "puml",
+                        format => format,
+                    };
+                    // 多种格式时 -o 只决定文件名，扩展名按格式替换；None 表示写到标准输出，
+                    // 终端流程图没有指定 -o 时也直接打印
+                    let output_path = match &args.output {
+                        _ if to_stdout => None,
+                        Some(output) if formats.len() > 1 => Some(output.with_extension(extension)),
+                        Some(output) => Some(output.clone()),
+                        None if style == "ascii" => None,
+                        None => Some(PathBuf::from(format!("crate_flow.{}", extension))),
+                    };
+                    (format, style, output_path)
+                })
+                .collect();
+            
+            let mut report = TimingReport::new();
             
-            let output_path = args.output.unwrap_or_else(|| {
-                PathBuf::from(format!("crate_flow.{}", args.format))
-            });
+            if args.reachability || args.churn {
+                let files = match &args.input {
+                    Some(input_file) => vec![input_file.clone()],
+                    None if packages.is_some() => source_files(None, packages.as_deref(), &filter)?,
+                    None => {
+                        let crate_root = get_crate_root()?;
+                        let mut files = find_rust_files(&crate_root, &filter)?;
+                        if args.with_build_script {
+                            files.extend(build_script_files(&crate_root));
+                        }
+                        files
+                    }
+                };
+                if args.reachability {
+                    config.entry_distances = entry_distances(&files, index.as_ref());
+                }
+                if args.churn {
+                    config.function_churn = function_churn(&files, &args.churn_since);
+                }
+            }
             
-            // 生成 DOT 内容
-            let dot_content = if let Some(input_file) = args.input {
-                analyze_file_with_renderer(&input_file, &*renderer)?
+            // 分析一次，之后每种输出格式从同一组控制流图渲染
+            let analyzed = if let Some(input_file) = &args.input {
+                let graph = analyze_file_timed(input_file, &config, &mut report)?;
+                AnalyzedGraphs { graphs: vec![(String::new(), graph)], teams: None, single: true }
             } else {
                 let crate_root = get_crate_root()?;
-                analyze_crate(&crate_root, &*renderer)?
+                let changed = args.changed_files.as_deref()
+                    .map(|source| read_changed_files(source, &crate_root, &filter))
+                    .transpose()?;
+                let selection = CrateFiles {
+                    with_build_script: args.with_build_script,
+                    changed: changed.as_deref(),
+                    packages: packages.as_deref(),
+                    filter: &filter,
+                };
+                analyze_crate(&crate_root, &config, selection, owners.as_ref(), &mut report)?
             };
             
-            // 创建临时 DOT 文件
-            let temp_dot = output_path.with_extension("dot");
-            std::fs::write(&temp_dot, dot_content)?;
-            
-            // 使用 dot 命令转换为 SVG
-            let status = std::process::Command::new("dot")
-                .args(["-Tsvg", temp_dot.to_str().unwrap(), "-o", output_path.to_str().unwrap()])
-                .status()?;
-                
-            // 删除临时文件
-            std::fs::remove_file(temp_dot)?;
-            
-            if !status.success() {
-                bail!("Failed to convert DOT to SVG");
+            // 同一渲染风格只渲染一次，svg、dot、html 和 layout 共用同一份 DOT
+            let mut rendered: HashMap<&str, String> = HashMap::new();
+            for (format, style, output_path) in &outputs {
+                if !rendered.contains_key(style) {
+                    let renderer = graph_renderer(style)?;
+                    rendered.insert(style, render_graphs(&analyzed, &*renderer, &mut report)?);
+                }
+                let sources = (*format == "html").then(|| source_panel(&analyzed, &args)).flatten();
+                write_graph_output(format, style, &rendered[style], output_path.as_deref(), sources.as_ref(), &args, &mut report)?;
             }
             
-            println!("Flow chart saved to: {}", output_path.display());
-            Ok(())
-        }
-        None => {
-            println!("Please use 'cargo graph' instead of 'cargo-graph'");
+            if args.timings {
+                eprint!("{}", report.render());
+            }
             Ok(())
         }
     }
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+// Graphviz 生成的 SVG 中的一个顶层元素（节点、边或分组）
+struct Element {
+    class: String,
+    // DOT 中的名称，边为 (起点, 终点)
+    name: String,
+    // 元素内所有文本，已经是 XML 转义后的形式
+    text: String,
+    markup: String,
+}
+
+pub struct AccessibilityPass;
+
+impl AccessibilityPass {
+    // 为 Graphviz 输出的 SVG 补充无障碍信息：
+    // - 根元素标记为 graphics-document，并带有整张图的标题
+    // - 每个节点/边/分组的 <title> 使用可读的标签文本，并加上 <desc> 和 role
+    // - 调整元素顺序，每个节点之后紧跟它的出边，便于屏幕阅读器按控制流顺序朗读
+    pub fn annotate(svg: &str, title: &str) -> String {
+        let Some(graph_start) = svg.find("<g id=\"graph0\"") else {
+            return svg.to_string();
+        };
+        let Some(body_start) = svg[graph_start..].find('>').map(|i| graph_start + i + 1) else {
+            return svg.to_string();
+        };
+        let Some(body_end) = Self::matching_close(svg, graph_start) else {
+            return svg.to_string();
+        };
+
+        // 顶层元素之前的部分（图标题、背景）原样保留
+        let body = &svg[body_start..body_end];
+        let mut elements = Vec::new();
+        let mut prefix_end = body.len();
+        let mut cursor = 0;
+        while let Some(offset) = body[cursor..].find("<g id=\"") {
+            let start = cursor + offset;
+            let Some(end) = Self::matching_close(body, start) else {
+                break;
+            };
+            let end = end + "</g>".len();
+            prefix_end = prefix_end.min(start);
+            elements.push(Self::parse_element(&body[start..end]));
+            cursor = end;
+        }
+        if elements.is_empty() {
+            return svg.to_string();
+        }
+
+        let labels: HashMap<&str, &str> = elements.iter()
+            .filter(|element| element.class == "node")
+            .map(|element| (element.name.as_str(), element.text.as_str()))
+            .collect();
+        let label_of = |name: &str| labels.get(name).copied().unwrap_or(name).to_string();
+
+        // 按节点的出现顺序排列，分组在最前
+        let mut ordered = Vec::new();
+        for element in elements.iter().filter(|element| element.class == "cluster") {
+            let title = if element.text.is_empty() { element.name.clone() } else { element.text.clone() };
+            ordered.push(Self::rewrite(element, "group", &title, "分组"));
+        }
+        for node in elements.iter().filter(|element| element.class == "node") {
+            let outgoing: Vec<&Element> = elements.iter()
+                .filter(|element| element.class == "edge" && Self::edge_ends(&element.name).0 == node.name)
+                .collect();
+            let targets: Vec<String> = outgoing.iter()
+                .map(|edge| {
+                    let target = label_of(Self::edge_ends(&edge.name).1);
+                    if edge.text.is_empty() { target } else { format!("{} ({})", target, edge.text) }
+                })
+                .collect();
+            let desc = if targets.is_empty() {
+                "无后继节点".to_string()
+            } else {
+                format!("后继节点: {}", targets.join("; "))
+            };
+            ordered.push(Self::rewrite(node, "graphics-symbol", &node.text, &desc));
+
+            for edge in outgoing {
+                let (tail, head) = Self::edge_ends(&edge.name);
+                let title = format!("{} → {}", label_of(tail), label_of(head));
+                let desc = if edge.text.is_empty() { "边".to_string() } else { edge.text.clone() };
+                ordered.push(Self::rewrite(edge, "graphics-symbol", &title, &desc));
+            }
+        }
+        // 起点不是节点的边（理论上不会出现）放在最后
+        for edge in elements.iter().filter(|element| {
+            element.class == "edge" && !labels.contains_key(Self::edge_ends(&element.name).0)
+        }) {
+            ordered.push(Self::rewrite(edge, "graphics-symbol", &edge.name, "边"));
+        }
+        for element in elements.iter().filter(|element| !matches!(element.class.as_str(), "node" | "edge" | "cluster")) {
+            ordered.push(element.markup.clone());
+        }
+
+        let mut result = String::with_capacity(svg.len() + 1024);
+        result.push_str(&svg[..body_start]);
+        result.push('\n');
+        result.push_str(&Self::strip_comments(&body[..prefix_end]));
+        for markup in ordered {
+            result.push('\n');
+            result.push_str(&markup);
+        }
+        result.push('\n');
+        result.push_str(&svg[body_end..]);
+
+        Self::annotate_root(&result, &Self::escape(title))
+    }
+
+    fn annotate_root(svg: &str, title: &str) -> String {
+        let Some(start) = svg.find("<svg") else {
+            return svg.to_string();
+        };
+        let Some(end) = svg[start..].find('>').map(|i| start + i) else {
+            return svg.to_string();
+        };
+        format!(
+            "{} role=\"graphics-document document\" aria-label=\"{}\">\n<title>{}</title>{}",
+            &svg[..end], title, title, &svg[end + 1..]
+        )
+    }
+
+    fn parse_element(markup: &str) -> Element {
+        let attr = |name: &str| -> String {
+            let pattern = format!("{}=\"", name);
+            markup.find(&pattern)
+                .and_then(|i| {
+                    let value = &markup[i + pattern.len()..];
+                    value.find('"').map(|end| value[..end].to_string())
+                })
+                .unwrap_or_default()
+        };
+        let name = Self::between(markup, "<title>", "</title>").next().unwrap_or_default().to_string();
+        let text = Self::between(markup, "<text", "</text>")
+            .filter_map(|text| text.split_once('>').map(|(_, content)| content.trim()))
+            .filter(|content| !content.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Element { class: attr("class"), name, text, markup: markup.to_string() }
+    }
+
+    // 把原来的 <title>（DOT 名称）换成可读的标题和描述，并添加 role；
+    // 标题和描述来自 SVG 中的文本，已经转义过
+    fn rewrite(element: &Element, role: &str, title: &str, desc: &str) -> String {
+        let class_attr = format!("class=\"{}\"", element.class);
+        let mut markup = element.markup.replacen(
+            &class_attr,
+            &format!("{} role=\"{}\" aria-roledescription=\"{}\" tabindex=\"0\"", class_attr, role, Self::role_description(&element.class)),
+            1,
+        );
+        let old_title = format!("<title>{}</title>", element.name);
+        markup = markup.replacen(
+            &old_title,
+            &format!("<title>{}</title>\n<desc>{}</desc>", title, desc),
+            1,
+        );
+        markup
+    }
+
+    fn role_description(class: &str) -> &'static str {
+        match class {
+            "node" => "节点",
+            "edge" => "边",
+            _ => "分组",
+        }
+    }
+
+    // Graphviz 把边的名称写作 `tail&#45;&gt;head`
+    fn edge_ends(name: &str) -> (&str, &str) {
+        name.split_once("&#45;&gt;").unwrap_or((name, ""))
+    }
+
+    fn between<'a>(text: &'a str, open: &'a str, close: &'a str) -> impl Iterator<Item = &'a str> {
+        text.split(open).skip(1).filter_map(move |part| part.split_once(close).map(|(inner, _)| inner))
+    }
+
+    // 从 start 处的 `<g` 开始找到与之配对的 `</g>` 的位置
+    fn matching_close(text: &str, start: usize) -> Option<usize> {
+        let mut depth = 0usize;
+        let mut cursor = start;
+        loop {
+            let close = text[cursor..].find("</g>").map(|i| cursor + i)?;
+            match Self::next_group_open(text, cursor) {
+                Some(open) if open < close => {
+                    depth += 1;
+                    cursor = open + 2;
+                }
+                _ => {
+                    depth = depth.checked_sub(1)?;
+                    if depth == 0 {
+                        return Some(close);
+                    }
+                    cursor = close + 4;
+                }
+            }
+        }
+    }
+
+    // 下一个 `<g ` 或 `<g>`，跳过 `<glyph` 之类的其他标签
+    fn next_group_open(text: &str, from: usize) -> Option<usize> {
+        let mut cursor = from;
+        while let Some(i) = text[cursor..].find("<g") {
+            let index = cursor + i;
+            if matches!(text.as_bytes().get(index + 2), Some(b' ' | b'>')) {
+                return Some(index);
+            }
+            cursor = index + 2;
+        }
+        None
+    }
+
+    // Graphviz 在每个元素前写有 `<!-- 名称 -->` 注释，重新排序后不再保留
+    fn strip_comments(text: &str) -> String {
+        let mut result = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("<!--") {
+            result.push_str(&rest[..start]);
+            rest = rest[start..].find("-->").map(|end| &rest[start + end + 3..]).unwrap_or("");
+        }
+        result.push_str(rest);
+        result.trim().to_string()
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+}
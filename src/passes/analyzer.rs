@@ -1,200 +1,1175 @@
-use crate::graph::{FlowGraph, NodeType, LoopKind};
+use crate::graph::{BlockGranularity, EdgeKind, FlowGraph, FunctionInfo, GraphArena, GraphConfig, Labels, NodeType, LoopKind, SourceSpan};
 use petgraph::graph::NodeIndex;
-use syn::{Block, Expr, ExprIf, ExprLoop, ExprMatch, ExprWhile, ItemFn, Stmt, ExprForLoop};
-use quote::quote;
-use crate::passes::ParserPass;
+use std::collections::HashSet;
+use syn::{BinOp, Block, Expr, ExprBreak, ExprContinue, ExprIf, ExprLoop, ExprMatch, ExprWhile, Item, ItemFn, Label, Lifetime, Local, Macro, Pat, Stmt, ExprForLoop};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use quote::ToTokens;
+use crate::passes::{CollectedFunction, ParserPass};
+use crate::style::LabelSanitizer;
+
+// 边标签中代码片段的最大显示宽度
+const MAX_EDGE_LABEL_WIDTH: usize = 30;
+
+// 标准库中返回 `!` 的函数（`process::exit`/`process::abort` 作为终止节点单独处理）
+const DIVERGING_STD_FUNCTIONS: &[&str] = &["panic_any", "resume_unwind", "unreachable_unchecked"];
+
+// 尚未连接到后继节点的出边，kind 为 None 时由连接方决定边的种类
+#[derive(Debug, Clone)]
+struct PendingEdge {
+    from: NodeIndex,
+    kind: Option<EdgeKind>,
+}
+
+impl PendingEdge {
+    fn new(from: NodeIndex) -> Self {
+        Self { from, kind: None }
+    }
+
+    fn labeled(from: NodeIndex, kind: EdgeKind) -> Self {
+        Self { from, kind: Some(kind) }
+    }
+}
+
+// 正在分析的循环：continue 回到入口节点，break 的出边在循环结束节点创建后再连接
+struct LoopContext {
+    // 循环标签（不含 `'`），用于带标签的 break/continue
+    label: Option<String>,
+    entry: NodeIndex,
+    breaks: Vec<PendingEdge>,
+    // 是否有带值的 break，即 `loop` 作为表达式产生值
+    has_value: bool,
+}
+
+// 语句内部可能离开或挂起当前函数的表达式
+enum NestedPoint<'ast> {
+    Try(&'ast syn::ExprTry),
+    Await(&'ast syn::ExprAwait),
+    // 只在 expression 粒度下展开为单独的节点
+    Call(&'ast syn::ExprCall),
+    MethodCall(&'ast syn::ExprMethodCall),
+}
+
+impl NestedPoint<'_> {
+    fn span(&self) -> proc_macro2::Span {
+        match self {
+            NestedPoint::Try(expr) => expr.span(),
+            NestedPoint::Await(expr) => expr.span(),
+            NestedPoint::Call(expr) => expr.span(),
+            NestedPoint::MethodCall(expr) => expr.span(),
+        }
+    }
+}
+
+// 查找语句内部嵌套的 return、`?` 和 `.await`，闭包、async 块和嵌套函数中的不属于当前函数
+#[derive(Default)]
+struct NestedFlow<'ast> {
+    returns: bool,
+    // 按求值顺序排列：`a().await?` 中内层的 `.await` 在前
+    points: Vec<NestedPoint<'ast>>,
+    // 不带路径的调用 `name`、`Self::name` 和 `self.name`，用于识别递归；
+    // `Vec::new()` 等带其他路径的调用不可能是当前函数自身
+    calls: HashSet<String>,
+    // 语句中是否有 unsafe 块
+    has_unsafe: bool,
+}
+
+impl<'ast> Visit<'ast> for NestedFlow<'ast> {
+    fn visit_expr_return(&mut self, node: &'ast syn::ExprReturn) {
+        self.returns = true;
+        visit::visit_expr_return(self, node);
+    }
+
+    fn visit_expr_try(&mut self, node: &'ast syn::ExprTry) {
+        visit::visit_expr_try(self, node);
+        self.points.push(NestedPoint::Try(node));
+    }
+
+    fn visit_expr_await(&mut self, node: &'ast syn::ExprAwait) {
+        visit::visit_expr_await(self, node);
+        self.points.push(NestedPoint::Await(node));
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast syn::ExprCall) {
+        if let Expr::Path(func) = &*node.func
+            && func.qself.is_none()
+            && func.path.leading_colon.is_none()
+        {
+            let segments: Vec<String> = func.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+            match segments.as_slice() {
+                [name] => {
+                    self.calls.insert(name.clone());
+                }
+                [ty, name] if ty == "Self" => {
+                    self.calls.insert(format!("Self::{}", name));
+                }
+                _ => {}
+            }
+        }
+        visit::visit_expr_call(self, node);
+        self.points.push(NestedPoint::Call(node));
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        if let Expr::Path(receiver) = &*node.receiver
+            && receiver.path.is_ident("self")
+        {
+            self.calls.insert(format!("self.{}", node.method));
+        }
+        visit::visit_expr_method_call(self, node);
+        self.points.push(NestedPoint::MethodCall(node));
+    }
+
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        self.has_unsafe = true;
+        visit::visit_expr_unsafe(self, node);
+    }
+
+    fn visit_expr_closure(&mut self, _node: &'ast syn::ExprClosure) {}
+
+    fn visit_expr_async(&mut self, _node: &'ast syn::ExprAsync) {}
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+impl<'ast> NestedFlow<'ast> {
+    fn stmt(stmt: &'ast Stmt) -> Self {
+        let mut finder = Self::default();
+        finder.visit_stmt(stmt);
+        finder
+    }
+
+    fn expr(expr: &'ast Expr) -> Self {
+        let mut finder = Self::default();
+        visit::visit_expr(&mut finder, expr);
+        finder
+    }
+
+    // 没有 return、`?` 和 `.await`，调用不影响控制流
+    fn is_empty(&self) -> bool {
+        !self.returns
+            && self.points.iter().all(|point| matches!(point, NestedPoint::Call(_) | NestedPoint::MethodCall(_)))
+    }
+}
 
 pub struct ControlFlowAnalyzerPass<'a> {
     graph: &'a mut FlowGraph,
-    current_node: Option<NodeIndex>,
+    arena: GraphArena,
+    source: Option<&'a str>,
     fn_start_node: Option<NodeIndex>,
     fn_end_node: Option<NodeIndex>,
+    // 正在分析的代码位于 unsafe 块或 unsafe fn 中的层数
+    unsafe_depth: usize,
+    // 开启 show_recursion 时调用当前函数自身的写法（见 NestedFlow::calls），这样调用的语句连回开始节点
+    self_calls: Vec<String>,
+    // 由内到外嵌套的循环，最内层在栈顶
+    loops: Vec<LoopContext>,
+    // 返回类型为 `!` 的函数名，调用它们不会返回
+    diverging_functions: HashSet<String>,
+    // 当前所在的 if/match 分支和循环体的层数，以及当前函数中的最大层数
+    nesting: usize,
+    max_nesting: usize,
 }
 
 impl<'a> ControlFlowAnalyzerPass<'a> {
     pub fn new(graph: &'a mut FlowGraph) -> Self {
+        Self::with_source(graph, None)
+    }
+
+    pub fn with_source(graph: &'a mut FlowGraph, source: Option<&'a str>) -> Self {
         Self {
             graph,
-            current_node: None,
+            arena: GraphArena::new(),
+            source,
             fn_start_node: None,
             fn_end_node: None,
+            self_calls: Vec::new(),
+            unsafe_depth: 0,
+            loops: Vec::new(),
+            nesting: 0,
+            max_nesting: 0,
+            diverging_functions: HashSet::new(),
         }
     }
-    
+
+    pub fn set_diverging_functions(&mut self, names: HashSet<String>) {
+        self.diverging_functions = names;
+    }
+
+    // 表达式的类型是否为 `!`：调用返回 `!` 的函数（包括标准库中的 `panic_any` 等），
+    // 或者必然先求值的子表达式（参数、接收者、运算数等）发散。
+    // 只做语法层面的轻量检查，条件求值的部分（`&&`/`||` 右侧、闭包）不算
+    fn diverges(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Call(call) => {
+                let diverging = match &*call.func {
+                    Expr::Path(func) => func.path.segments.last().is_some_and(|segment| {
+                        let name = segment.ident.to_string();
+                        self.diverging_functions.contains(&name) || DIVERGING_STD_FUNCTIONS.contains(&name.as_str())
+                    }),
+                    func => self.diverges(func),
+                };
+                diverging || call.args.iter().any(|arg| self.diverges(arg))
+            }
+            Expr::MethodCall(call) => {
+                self.diverging_functions.contains(&call.method.to_string())
+                    || self.diverges(&call.receiver)
+                    || call.args.iter().any(|arg| self.diverges(arg))
+            }
+            Expr::Macro(expr_macro) => self.is_exit_macro(&expr_macro.mac),
+            Expr::Binary(binary) => {
+                self.diverges(&binary.left)
+                    || (!matches!(binary.op, BinOp::And(_) | BinOp::Or(_)) && self.diverges(&binary.right))
+            }
+            Expr::Assign(assign) => self.diverges(&assign.right),
+            Expr::Paren(paren) => self.diverges(&paren.expr),
+            Expr::Group(group) => self.diverges(&group.expr),
+            Expr::Unary(unary) => self.diverges(&unary.expr),
+            Expr::Reference(reference) => self.diverges(&reference.expr),
+            Expr::Cast(cast) => self.diverges(&cast.expr),
+            Expr::Field(field) => self.diverges(&field.base),
+            Expr::Index(index) => self.diverges(&index.expr) || self.diverges(&index.index),
+            Expr::Tuple(tuple) => tuple.elems.iter().any(|elem| self.diverges(elem)),
+            Expr::Array(array) => array.elems.iter().any(|elem| self.diverges(elem)),
+            _ => false,
+        }
+    }
+
+    // 节点文本优先取自原始源码，没有源码时退回到 token 重新打印
+    fn text<T: ToTokens + Spanned>(&self, node: &T) -> String {
+        self.source
+            .and_then(|source| ParserPass::snippet(source, node.span()))
+            .unwrap_or_else(|| node.to_token_stream().to_string())
+    }
+
+    fn connect(&mut self, pending: Vec<PendingEdge>, target: NodeIndex, kind: EdgeKind) {
+        for edge in pending {
+            let kind = edge.kind.unwrap_or_else(|| kind.clone());
+            self.arena.add_edge(edge.from, target, kind);
+        }
+    }
+
     pub fn analyze_function(&mut self, func: &ItemFn) {
-        let (fn_name, is_test) = ParserPass::get_function_info(func);
-        
-        // 创建函数开始和结束节点
-        let start_node = self.graph.add_node(NodeType::Start(fn_name.clone(), is_test));
-        let end_node = self.graph.add_node(NodeType::End(fn_name, is_test));
-        
+        let (name, is_test) = ParserPass::get_function_info(func);
+        let is_entry = ParserPass::is_entry_fn(func);
+        self.analyze_item(FunctionInfo { name, is_test, is_entry, parent: None, file: None, line: Some(func.sig.span().start().line), max_nesting: 0, namespace: None, signature: Some(self.signature(func)) }, func, false);
+    }
+
+    // 之后分析的函数使用的选项
+    pub(crate) fn set_config(&mut self, config: GraphConfig) {
+        self.graph.set_config(config);
+    }
+
+    pub fn analyze_collected(&mut self, func: &CollectedFunction) {
+        let info = FunctionInfo {
+            name: func.path.clone(),
+            is_test: func.is_test,
+            is_entry: func.is_entry,
+            parent: func.parent.clone(),
+            file: func.file.clone(),
+            line: Some(if func.pseudo { func.item.block.brace_token.span.open() } else { func.item.sig.span() }.start().line),
+            max_nesting: 0,
+            namespace: None,
+            signature: (!func.pseudo).then(|| self.signature(&func.item)),
+        };
+        self.analyze_item(info, &func.item, func.associated);
+    }
+
+    // 签名原文中的换行和缩进合并为单个空格，多行参数列表末尾的逗号去掉
+    fn signature(&self, func: &ItemFn) -> String {
+        self.text(&func.sig).split_whitespace().collect::<Vec<_>>().join(" ")
+            .replace("( ", "(")
+            .replace(", )", ")")
+            .replace(" )", ")")
+    }
+
+    fn analyze_item(&mut self, mut info: FunctionInfo, func: &ItemFn, associated: bool) {
+        let fn_name = info.name.clone();
+        let is_test = info.is_test;
+        // 创建函数开始和结束节点，对应函数签名；闭包等伪函数的签名是占位的，对应函数体的开始
+        let head = if info.signature.is_some() { func.sig.span() } else { func.block.brace_token.span.open() };
+        self.arena.set_span(SourceSpan::from_span(head));
+        let start_node = self.arena.add_node(NodeType::Start(fn_name.clone(), is_test));
+        let end_node = self.arena.add_node(NodeType::End(fn_name, is_test));
+
         self.fn_start_node = Some(start_node);
         self.fn_end_node = Some(end_node);
-        self.current_node = Some(start_node);
-
-        // 分析函数体
-        let last_node = self.analyze_block(&func.block, None);
-        self.graph.add_edge(last_node, end_node, "return".to_string());
-    }
-
-    pub fn analyze_block(&mut self, block: &Block, parent: Option<NodeIndex>) -> NodeIndex {
-        let mut last_node = parent.unwrap_or_else(|| self.current_node.unwrap());
-        
-        for stmt in &block.stmts {
-            match stmt {
-                Stmt::Expr(expr, _) => {
-                    match expr {
-                        Expr::If(expr_if) => {
-                            last_node = self.analyze_if(expr_if, last_node);
-                        }
-                        Expr::While(expr_while) => {
-                            last_node = self.analyze_while(expr_while, last_node);
-                        }
-                        Expr::Loop(expr_loop) => {
-                            last_node = self.analyze_loop(expr_loop, last_node);
-                        }
-                        Expr::ForLoop(expr_for) => {
-                            last_node = self.analyze_for(expr_for, last_node);
-                        }
-                        Expr::Match(expr_match) => {
-                            last_node = self.analyze_match(expr_match, last_node);
-                        }
-                        _ => {
-                            // 创建基本块节点
-                            let basic_block = self.graph.add_node(NodeType::BasicBlock(
-                                format!("{}", quote!(#expr))
-                            ));
-                            self.graph.add_edge(last_node, basic_block, "next".to_string());
-                            last_node = basic_block;
-                        }
-                    }
+        // 闭包（`名称@文件:行号`）无法按名称调用自身；普通函数以不带路径的 `name(..)` 调用自身，
+        // 关联函数以 `Self::name(..)` 或 `self.name(..)` 调用自身
+        self.self_calls = match info.name.rsplit("::").next() {
+            Some(name) if self.graph.config().show_recursion && !name.contains('@') => {
+                if associated {
+                    vec![format!("Self::{}", name), format!("self.{}", name)]
+                } else {
+                    vec![name.to_string()]
+                }
+            }
+            _ => Vec::new(),
+        };
+
+        // 分析函数体，所有正常结束的路径都连接到结束节点；unsafe fn 的整个函数体都是 unsafe 代码
+        self.unsafe_depth = usize::from(func.sig.unsafety.is_some());
+        self.max_nesting = 0;
+        let exits = self.analyze_stmts(&func.block, vec![PendingEdge::new(start_node)], true);
+        self.connect(exits, end_node, EdgeKind::Return(None));
+        self.unsafe_depth = 0;
+        info.max_nesting = self.max_nesting;
+
+        // 函数分析完成后一次性转换到 petgraph 表示
+        self.graph.append_arena(&mut self.arena, info);
+        self.fn_start_node = None;
+        self.fn_end_node = None;
+        self.self_calls.clear();
+    }
+
+    fn is_recursive(&self, nested: &NestedFlow) -> bool {
+        self.self_calls.iter().any(|call| nested.calls.contains(call))
+    }
+
+    // 调用自身的语句用虚线连回函数开始节点
+    fn link_recursion(&mut self, node: NodeIndex) {
+        if let Some(start_node) = self.fn_start_node {
+            self.arena.add_edge(node, start_node, EdgeKind::Recursion);
+        }
+    }
+
+    // 进入 if/match 的分支或循环体，离开时调用 leave_nested
+    fn enter_nested(&mut self) {
+        self.nesting += 1;
+        self.max_nesting = self.max_nesting.max(self.nesting);
+    }
+
+    fn leave_nested(&mut self) {
+        self.nesting -= 1;
+    }
+
+    // if 的分支或循环体，嵌套层数加一。分支或循环体为空时生成显式的 "(empty)" 节点，
+    // 避免条件节点的两条出边指向同一个合并点、循环节点直接连回自身
+    fn analyze_nested(&mut self, block: &Block, pending: Vec<PendingEdge>, tail: bool) -> Vec<PendingEdge> {
+        if block.stmts.is_empty() {
+            let outer = self.arena.set_span(SourceSpan::from_span(block.span()));
+            let exits = self.analyze_basic("(empty)".to_string(), pending);
+            self.arena.set_span(outer);
+            return exits;
+        }
+        self.enter_nested();
+        let exits = self.analyze_stmts(block, pending, tail);
+        self.leave_nested();
+        exits
+    }
+
+    fn analyze_block(&mut self, block: &Block, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        self.analyze_stmts(block, pending, false)
+    }
+
+    // tail 为 true 时代码块的值就是函数的返回值，末尾表达式按返回路径处理
+    fn analyze_stmts(&mut self, block: &Block, mut pending: Vec<PendingEdge>, tail: bool) -> Vec<PendingEdge> {
+        let mut last_unreachable: Option<NodeIndex> = None;
+
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            let outer = self.arena.set_span(SourceSpan::from_span(stmt.span()));
+            // 前面的语句已经离开当前路径，后续语句不可达，不从发散节点连边
+            if pending.is_empty() {
+                let node = self.arena.add_node(NodeType::Unreachable(self.text(stmt)));
+                if let Some(previous) = last_unreachable {
+                    self.arena.add_edge(previous, node, EdgeKind::Next);
+                }
+                last_unreachable = Some(node);
+                self.arena.set_span(outer);
+                continue;
+            }
+
+            pending = match stmt {
+                Stmt::Expr(expr, None) if tail && i + 1 == block.stmts.len() => self.analyze_tail(expr, pending),
+                Stmt::Local(local) if let Some(init) = Self::branching_init(local) => {
+                    self.analyze_binding(local, init, pending)
+                }
+                // 连续的声明与上一个声明节点合并，不经过基本块合并，因此不会吞掉有副作用的语句
+                Stmt::Local(_) if self.graph.config().group_declarations && NestedFlow::stmt(stmt).is_empty() => {
+                    let content = self.text(stmt);
+                    self.analyze_declaration(content, pending)
+                }
+                Stmt::Local(local)
+                    if local.init.as_ref().is_some_and(|init| init.diverge.is_none() && self.diverges(&init.expr)) =>
+                {
+                    let content = self.text(stmt);
+                    self.analyze_diverging(content, NestedFlow::stmt(stmt), pending)
+                }
+                Stmt::Expr(expr, _) => self.analyze_expr(expr, pending),
+                // 嵌套函数单独成图，这里只保留签名
+                Stmt::Item(Item::Fn(item)) => {
+                    let content = self.text(&item.sig);
+                    self.analyze_basic(content, pending)
+                }
+                Stmt::Macro(stmt_macro) if self.is_exit_macro(&stmt_macro.mac) => {
+                    let content = self.text(stmt);
+                    self.analyze_abort(content, pending)
                 }
                 _ => {
                     // 其他语句类型作为基本块处理
-                    let basic_block = self.graph.add_node(NodeType::BasicBlock(
-                        format!("{}", quote!(#stmt))
-                    ));
-                    self.graph.add_edge(last_node, basic_block, "next".to_string());
-                    last_node = basic_block;
+                    let content = self.text(stmt);
+                    self.analyze_statement(content, NestedFlow::stmt(stmt), pending)
                 }
+            };
+            self.arena.set_span(outer);
+        }
+
+        pending
+    }
+
+    // 返回循环体正常结束的出边和收集了 break 出边的循环上下文
+    fn analyze_loop_body(
+        &mut self,
+        loop_node: NodeIndex,
+        label: Option<&Label>,
+        block: &Block,
+        entry: Vec<PendingEdge>,
+    ) -> (Vec<PendingEdge>, LoopContext) {
+        let label = label.map(|label| label.name.ident.to_string());
+        self.loops.push(LoopContext { label, entry: loop_node, breaks: Vec::new(), has_value: false });
+        let exits = self.analyze_nested(block, entry, false);
+        let context = self.loops.pop().expect("loop context pushed above");
+        (exits, context)
+    }
+
+    // break/continue 的目标循环：带标签时按标签由内向外查找，否则是最内层循环
+    fn target_loop(&self, label: Option<&Lifetime>) -> Option<usize> {
+        match label {
+            Some(label) => {
+                let name = label.ident.to_string();
+                self.loops.iter().rposition(|context| context.label.as_ref() == Some(&name))
             }
+            None => self.loops.len().checked_sub(1),
         }
-        
-        last_node
     }
 
-    fn analyze_if(&mut self, expr_if: &ExprIf, parent: NodeIndex) -> NodeIndex {
-        // 创建条件节点
-        let cond_text = format!("{}", quote!(#expr_if.cond));
-        let cond_node = self.graph.add_node(NodeType::Condition(cond_text));
-        self.graph.add_edge(parent, cond_node, "进入判断".to_string());
+    // break 跳到目标循环的结束节点，之后的语句不可达
+    fn analyze_break(&mut self, expr_break: &ExprBreak, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let content = self.text(expr_break);
+        let Some(target) = self.target_loop(expr_break.label.as_ref()) else {
+            return self.analyze_basic(content, pending);
+        };
+        let node = self.arena.add_node(self.code_block(content));
+        self.connect(pending, node, EdgeKind::Next);
+        // 带值的 break 在边上显示值，循环结束后直接流向使用这个值的语句
+        let mut detail: Vec<String> = expr_break.label.iter().map(ToString::to_string).collect();
+        if let Some(value) = &expr_break.expr {
+            detail.push(LabelSanitizer::truncate(&self.text(&**value), MAX_EDGE_LABEL_WIDTH));
+            self.loops[target].has_value = true;
+        }
+        let detail = (!detail.is_empty()).then(|| detail.join(" "));
+        self.loops[target].breaks.push(PendingEdge::labeled(node, EdgeKind::Break(detail)));
+        Vec::new()
+    }
+
+    // continue 回到目标循环的入口，之后的语句不可达
+    fn analyze_continue(&mut self, expr_continue: &ExprContinue, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let content = self.text(expr_continue);
+        let Some(target) = self.target_loop(expr_continue.label.as_ref()) else {
+            return self.analyze_basic(content, pending);
+        };
+        let node = self.arena.add_node(self.code_block(content));
+        self.connect(pending, node, EdgeKind::Next);
+        let detail = expr_continue.label.as_ref().map(ToString::to_string);
+        self.arena.add_edge(node, self.loops[target].entry, EdgeKind::Continue(detail));
+        Vec::new()
+    }
+
+    fn analyze_expr(&mut self, expr: &Expr, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        // 分析期间创建的节点对应这个表达式的源码范围，之后恢复为外层的范围
+        let outer = self.arena.set_span(SourceSpan::from_span(expr.span()));
+        let exits = self.analyze_expr_kind(expr, pending);
+        self.arena.set_span(outer);
+        exits
+    }
+
+    fn analyze_expr_kind(&mut self, expr: &Expr, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        match expr {
+            Expr::If(expr_if) => self.analyze_if(expr_if, pending, false),
+            Expr::While(expr_while) => self.analyze_while(expr_while, pending),
+            Expr::Loop(expr_loop) => self.analyze_loop(expr_loop, pending),
+            Expr::ForLoop(expr_for) => self.analyze_for(expr_for, pending),
+            Expr::Match(expr_match) => self.analyze_match(expr_match, pending, false),
+            Expr::Break(expr_break) => self.analyze_break(expr_break, pending),
+            Expr::Continue(expr_continue) => self.analyze_continue(expr_continue, pending),
+            Expr::Unsafe(expr_unsafe) => {
+                self.unsafe_depth += 1;
+                let exits = self.analyze_block(&expr_unsafe.block, pending);
+                self.unsafe_depth -= 1;
+                exits
+            }
+            Expr::Return(_) => {
+                let content = self.text(expr);
+                let recursive = self.is_recursive(&NestedFlow::expr(expr));
+                self.analyze_exit(content, EdgeKind::Return(None), recursive, pending)
+            }
+            Expr::Macro(expr_macro) if self.is_exit_macro(&expr_macro.mac) => {
+                let content = self.text(expr);
+                self.analyze_abort(content, pending)
+            }
+            expr if Self::is_process_exit(expr) => {
+                let content = self.text(expr);
+                self.analyze_abort(content, pending)
+            }
+            expr if self.diverges(expr) => {
+                let content = self.text(expr);
+                self.analyze_diverging(content, NestedFlow::expr(expr), pending)
+            }
+            _ => {
+                let content = self.text(expr);
+                self.analyze_statement(content, NestedFlow::expr(expr), pending)
+            }
+        }
+    }
+
+    // 代码节点，位于 unsafe 代码中时单独标记
+    fn code_block(&self, content: String) -> NodeType {
+        if self.unsafe_depth > 0 {
+            NodeType::Unsafe(content)
+        } else {
+            NodeType::BasicBlock(content)
+        }
+    }
+
+    fn analyze_basic(&mut self, content: String, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        // 创建基本块节点
+        let basic_block = self.arena.add_node(self.code_block(content));
+        self.connect(pending, basic_block, EdgeKind::Next);
+        vec![PendingEdge::new(basic_block)]
+    }
+
+    // 内部嵌套了 return 或 `?` 的语句（例如 `let v = match x { None => return, .. }`）
+    // 除了继续执行外，还可能直接离开函数。每个 `?` 和 `.await` 先于语句本身求值
+    fn analyze_statement(&mut self, content: String, nested: NestedFlow, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let recursive = self.is_recursive(&nested);
+        let expressions = self.graph.config().block_granularity == BlockGranularity::Expression;
+        let mut pending = pending;
+        for point in nested.points {
+            pending = match point {
+                NestedPoint::Call(call) if expressions => self.analyze_call(call, &content, pending),
+                NestedPoint::MethodCall(call) if expressions => self.analyze_call(call, &content, pending),
+                NestedPoint::Call(_) | NestedPoint::MethodCall(_) => pending,
+                point => self.analyze_point(&point, pending),
+            };
+        }
+
+        // 语句中嵌套的 unsafe 块（如 `let v = unsafe { ptr.read() };`）不再展开，整条语句标记为 unsafe
+        let has_unsafe = usize::from(nested.has_unsafe);
+        self.unsafe_depth += has_unsafe;
+        let exits = self.analyze_basic(content, pending);
+        self.unsafe_depth -= has_unsafe;
+        if let [edge] = exits.as_slice() {
+            let node = edge.from;
+            if nested.returns && let Some(end_node) = self.fn_end_node {
+                self.arena.add_edge(node, end_node, EdgeKind::Return(None));
+            }
+            if recursive {
+                self.link_recursion(node);
+            }
+        }
+        exits
+    }
+
+    // 语句或分支、循环头部中的 `?` 和 `.await`：`?` 作为一个条件节点，Ok 继续执行，
+    // Err 连接到函数结束节点；`.await` 作为挂起点
+    fn analyze_point(&mut self, point: &NestedPoint, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let outer = self.arena.set_span(SourceSpan::from_span(point.span()));
+        let exits = match point {
+            NestedPoint::Try(try_expr) => {
+                let condition = self.arena.add_node(NodeType::Condition(self.text(*try_expr)));
+                self.connect(pending, condition, EdgeKind::Next);
+                if let Some(end_node) = self.fn_end_node {
+                    self.arena.add_edge(condition, end_node, EdgeKind::Err);
+                }
+                vec![PendingEdge::labeled(condition, EdgeKind::Ok)]
+            }
+            NestedPoint::Await(await_expr) => {
+                let suspend = self.arena.add_node(NodeType::Await(self.text(&*await_expr.base)));
+                self.connect(pending, suspend, EdgeKind::Next);
+                vec![PendingEdge::labeled(suspend, EdgeKind::Resume)]
+            }
+            NestedPoint::Call(_) | NestedPoint::MethodCall(_) => pending,
+        };
+        self.arena.set_span(outer);
+        exits
+    }
+
+    // if/while 条件、match 的被匹配值和 for 的迭代器中的 `?` 和 `.await` 先于判断节点求值，
+    // 返回第一个新建的节点（while 的回边和 continue 回到这里重新求值条件）和出边
+    fn analyze_head(&mut self, head: &Expr, mut pending: Vec<PendingEdge>) -> (Option<NodeIndex>, Vec<PendingEdge>) {
+        let mut first = None;
+        for point in NestedFlow::expr(head).points {
+            if !matches!(point, NestedPoint::Try(_) | NestedPoint::Await(_)) {
+                continue;
+            }
+            pending = self.analyze_point(&point, pending);
+            first = first.or(pending.first().map(|edge| edge.from));
+        }
+        (first, pending)
+    }
+
+    // expression 粒度下语句中的调用按求值顺序各占一个节点，语句本身就是这个调用时不重复
+    fn analyze_call<T: ToTokens + Spanned>(&mut self, call: &T, content: &str, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let text = self.text(call);
+        if text == content {
+            return pending;
+        }
+        self.analyze_basic(text, pending)
+    }
+
+    // 类型为 `!` 的语句：`?` 等嵌套的出口照常展开，语句本身是死路，不会继续执行到下一条语句
+    fn analyze_diverging(&mut self, content: String, nested: NestedFlow, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        for edge in self.analyze_statement(content, nested, pending) {
+            if let Some(end_node) = self.fn_end_node {
+                self.arena.add_edge(edge.from, end_node, EdgeKind::Diverge);
+            }
+        }
+        Vec::new()
+    }
+
+    // 初始化表达式本身是 if/match/循环的 let 语句（不含 let-else）
+    fn branching_init(local: &Local) -> Option<&Expr> {
+        let init = local.init.as_ref().filter(|init| init.diverge.is_none())?;
+        matches!(
+            &*init.expr,
+            Expr::If(_) | Expr::Match(_) | Expr::Loop(_) | Expr::While(_) | Expr::ForLoop(_)
+        )
+        .then_some(&*init.expr)
+    }
+
+    // 先展开初始化表达式中的分支，分支合并后再绑定变量
+    fn analyze_binding(&mut self, local: &Local, init: &Expr, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let exits = self.analyze_expr(init, pending);
+        if exits.is_empty() {
+            return exits;
+        }
+        let content = format!("let {} = …", self.text(&local.pat));
+        self.analyze_basic(content, exits)
+    }
+
+    fn analyze_declaration(&mut self, content: String, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        if let [edge] = pending.as_slice()
+            && edge.kind.is_none()
+            && let Some(NodeType::Declarations(decls)) = self.arena.node_mut(edge.from)
+        {
+            decls.push(content);
+            self.arena.extend_span(edge.from);
+            return pending;
+        }
+        let node = self.arena.add_node(NodeType::Declarations(vec![content]));
+        self.connect(pending, node, EdgeKind::Next);
+        vec![PendingEdge::new(node)]
+    }
+
+    // 直接离开函数的语句：连接到函数结束节点，不再有后继
+    fn analyze_exit(&mut self, content: String, kind: EdgeKind, recursive: bool, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let exit_node = self.arena.add_node(self.code_block(content));
+        self.connect(pending, exit_node, EdgeKind::Next);
+        if let Some(end_node) = self.fn_end_node {
+            self.arena.add_edge(exit_node, end_node, kind);
+        }
+        if recursive {
+            self.link_recursion(exit_node);
+        }
+        Vec::new()
+    }
+
+    // panic!、std::process::exit 等终止执行的语句：终止节点没有任何出边，也不连接到函数结束节点
+    fn analyze_abort(&mut self, content: String, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        let abort_node = self.arena.add_node(NodeType::Abort(content));
+        self.connect(pending, abort_node, EdgeKind::Next);
+        Vec::new()
+    }
+
+    // `std::process::exit(..)`、`process::abort()` 等
+    fn is_process_exit(expr: &Expr) -> bool {
+        let Expr::Call(call) = expr else {
+            return false;
+        };
+        let Expr::Path(func) = &*call.func else {
+            return false;
+        };
+        let mut segments = func.path.segments.iter().rev().map(|segment| segment.ident.to_string());
+        matches!(
+            (segments.next().as_deref(), segments.next().as_deref()),
+            (Some("exit" | "abort"), Some("process"))
+        )
+    }
+
+    // 能匹配任意值的模式：`_`、变量绑定（小写开头，区别于 `None` 等单元变体和常量）及其组合
+    fn is_catch_all(pat: &Pat) -> bool {
+        match pat {
+            Pat::Wild(_) => true,
+            Pat::Ident(ident) => match &ident.subpat {
+                Some((_, subpat)) => Self::is_catch_all(subpat),
+                None => ident.ident.to_string().starts_with(|c: char| c.is_lowercase() || c == '_'),
+            },
+            Pat::Paren(paren) => Self::is_catch_all(&paren.pat),
+            Pat::Reference(reference) => Self::is_catch_all(&reference.pat),
+            Pat::Type(pat_type) => Self::is_catch_all(&pat_type.pat),
+            Pat::Or(or) => or.cases.iter().any(Self::is_catch_all),
+            _ => false,
+        }
+    }
+
+    fn is_exit_macro(&self, mac: &Macro) -> bool {
+        self.graph.config().panic_as_exit
+            && mac.path.segments.last().is_some_and(|segment| {
+                matches!(
+                    segment.ident.to_string().as_str(),
+                    "panic" | "unreachable" | "todo" | "unimplemented"
+                )
+            })
+    }
+
+    // 当前语言的辅助节点文本
+    fn labels(&self) -> &Labels {
+        &self.graph.config().labels
+    }
+
+    // `if let`（包括 let 链）按模式是否匹配分支，普通条件按真假分支
+    fn branch_kinds(cond: &Expr) -> (EdgeKind, EdgeKind) {
+        if Self::has_let(cond) {
+            (EdgeKind::Matched, EdgeKind::Unmatched)
+        } else {
+            (EdgeKind::True, EdgeKind::False)
+        }
+    }
+
+    fn has_let(expr: &Expr) -> bool {
+        match expr {
+            Expr::Let(_) => true,
+            Expr::Binary(binary) if matches!(binary.op, syn::BinOp::And(_)) => {
+                Self::has_let(&binary.left) || Self::has_let(&binary.right)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_short_circuit(expr: &Expr) -> bool {
+        match expr {
+            Expr::Binary(binary) => matches!(binary.op, syn::BinOp::And(_) | syn::BinOp::Or(_)),
+            Expr::Paren(paren) => Self::is_short_circuit(&paren.expr),
+            _ => false,
+        }
+    }
+
+    // 把 `a && b || c` 拆成逐个求值的条件节点，返回条件成立和不成立时的出边：
+    // `a && b` 中 a 不成立时不再求值 b，`a || b` 中 a 成立时不再求值 b
+    fn analyze_condition(&mut self, cond: &Expr, pending: Vec<PendingEdge>) -> (Vec<PendingEdge>, Vec<PendingEdge>) {
+        match cond {
+            Expr::Binary(binary) if matches!(binary.op, syn::BinOp::And(_)) => {
+                let (left_true, mut left_false) = self.analyze_condition(&binary.left, pending);
+                let (right_true, right_false) = self.analyze_condition(&binary.right, left_true);
+                left_false.extend(right_false);
+                (right_true, left_false)
+            }
+            Expr::Binary(binary) if matches!(binary.op, syn::BinOp::Or(_)) => {
+                let (mut left_true, left_false) = self.analyze_condition(&binary.left, pending);
+                let (right_true, right_false) = self.analyze_condition(&binary.right, left_false);
+                left_true.extend(right_true);
+                (left_true, right_false)
+            }
+            Expr::Paren(paren) => self.analyze_condition(&paren.expr, pending),
+            _ => {
+                let (_, pending) = self.analyze_head(cond, pending);
+                let cond_node = self.arena.add_node_at(NodeType::Condition(self.text(cond)), SourceSpan::from_span(cond.span()));
+                self.connect(pending, cond_node, EdgeKind::EnterCondition);
+                (vec![PendingEdge::labeled(cond_node, EdgeKind::True)], vec![PendingEdge::labeled(cond_node, EdgeKind::False)])
+            }
+        }
+    }
+
+    // 函数的末尾表达式：if/match 的每个分支直接连接到函数结束节点，不带标签的代码块继续按末尾处理；
+    // 循环的值是 `()`（带值的 break 已在边上显示值），出边是普通的返回；
+    // 其他表达式的出边标注为 `return <表达式>`
+    fn analyze_tail(&mut self, expr: &Expr, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        match expr {
+            Expr::If(expr_if) => self.analyze_if(expr_if, pending, true),
+            Expr::Match(expr_match) => self.analyze_match(expr_match, pending, true),
+            Expr::Block(expr_block) if expr_block.label.is_none() => self.analyze_stmts(&expr_block.block, pending, true),
+            Expr::Unsafe(expr_unsafe) => {
+                self.unsafe_depth += 1;
+                let exits = self.analyze_stmts(&expr_unsafe.block, pending, true);
+                self.unsafe_depth -= 1;
+                exits
+            }
+            Expr::ForLoop(_) | Expr::While(_) | Expr::Loop(_) => self.analyze_expr(expr, pending),
+            _ => {
+                let value = LabelSanitizer::truncate(&self.text(expr), MAX_EDGE_LABEL_WIDTH);
+                self.analyze_expr(expr, pending)
+                    .into_iter()
+                    .map(|edge| match edge.kind {
+                        Some(_) => edge,
+                        None => PendingEdge::labeled(edge.from, EdgeKind::Return(Some(value.clone()))),
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // tail 为 true 时 if 是函数的末尾表达式，分支不再合并
+    fn analyze_if(&mut self, expr_if: &ExprIf, pending: Vec<PendingEdge>, tail: bool) -> Vec<PendingEdge> {
+        if self.graph.config().flat_else_if
+            && matches!(&expr_if.else_branch, Some((_, else_branch)) if matches!(&**else_branch, Expr::If(_)))
+        {
+            return self.analyze_if_chain(expr_if, pending, tail);
+        }
+
+        let (then_entry, else_entry) = if self.graph.config().expand_boolean_ops
+            && Self::is_short_circuit(&expr_if.cond)
+            && !Self::has_let(&expr_if.cond)
+        {
+            self.analyze_condition(&expr_if.cond, pending)
+        } else {
+            // 创建条件节点
+            let (_, pending) = self.analyze_head(&expr_if.cond, pending);
+            let cond_text = self.text(&*expr_if.cond);
+            let cond_node = self.arena.add_node_at(NodeType::Condition(cond_text), SourceSpan::from_span(expr_if.cond.span()));
+            self.connect(pending, cond_node, EdgeKind::EnterCondition);
+            let (then_kind, else_kind) = Self::branch_kinds(&expr_if.cond);
+            (vec![PendingEdge::labeled(cond_node, then_kind)], vec![PendingEdge::labeled(cond_node, else_kind)])
+        };
 
         // 处理 then 分支
-        let then_node = self.analyze_block(&expr_if.then_branch, Some(cond_node));
-        self.graph.add_edge(cond_node, then_node, "是".to_string());
+        let mut then_exits = self.analyze_nested(&expr_if.then_branch, then_entry, tail);
 
         // 处理 else 分支
-        let merge_node = self.graph.add_node(NodeType::BasicBlock("分支合并点".to_string()));
-        if let Some((_, else_branch)) = &expr_if.else_branch {
-            let else_node = match &**else_branch {
-                Expr::Block(block) => self.analyze_block(&block.block, Some(cond_node)),
-                Expr::If(else_if) => self.analyze_if(else_if, cond_node),
+        let else_exits = match &expr_if.else_branch {
+            Some((_, else_branch)) => match &**else_branch {
+                Expr::Block(block) => self.analyze_nested(&block.block, else_entry, tail),
+                Expr::If(else_if) => self.analyze_if(else_if, else_entry, tail),
                 _ => unreachable!(),
-            };
-            self.graph.add_edge(cond_node, else_node, "否".to_string());
-            self.graph.add_edge(else_node, merge_node, "完成分支".to_string());
-        } else {
-            self.graph.add_edge(cond_node, merge_node, "否".to_string());
+            },
+            None => else_entry,
+        };
+
+        if tail {
+            then_exits.extend(else_exits);
+            return then_exits;
+        }
+
+        // 两个分支都已离开当前路径时不需要合并点
+        if then_exits.is_empty() && else_exits.is_empty() {
+            return Vec::new();
         }
 
-        self.graph.add_edge(then_node, merge_node, "完成分支".to_string());
-        merge_node
+        let merge_node = self.arena.add_node_at(NodeType::BasicBlock(self.labels().merge_point.clone()), None);
+        self.connect(then_exits, merge_node, EdgeKind::BranchDone);
+        self.connect(else_exits, merge_node, EdgeKind::BranchDone);
+        vec![PendingEdge::new(merge_node)]
     }
 
-    fn analyze_while(&mut self, expr_while: &ExprWhile, parent: NodeIndex) -> NodeIndex {
-        // 创建循环入口节点
-        let cond_text = format!("{}", quote!(#expr_while.cond));
-        let loop_node = self.graph.add_node(NodeType::Loop(LoopKind::While(cond_text)));
-        self.graph.add_edge(parent, loop_node, "进入循环".to_string());
+    // `if .. else if .. else` 链画成一个多路判断节点，与 match 相同，
+    // 每个条件一条出边，边标签为条件本身
+    fn analyze_if_chain(&mut self, expr_if: &ExprIf, pending: Vec<PendingEdge>, tail: bool) -> Vec<PendingEdge> {
+        let mut branches = Vec::new();
+        let mut else_block = None;
+        let mut current = expr_if;
+        loop {
+            branches.push((&*current.cond, &current.then_branch));
+            match current.else_branch.as_ref().map(|(_, else_branch)| &**else_branch) {
+                Some(Expr::If(else_if)) => current = else_if,
+                Some(Expr::Block(block)) => {
+                    else_block = Some(&block.block);
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        // 只有第一个条件一定会求值
+        let (_, pending) = self.analyze_head(branches[0].0, pending);
+        let decision_text = self.labels().else_if_chain.replace("{}", &branches.len().to_string());
+        let decision_node = self.arena.add_node(NodeType::Condition(decision_text));
+        self.connect(pending, decision_node, EdgeKind::EnterCondition);
+
+        let mut exits = Vec::new();
+        for (cond, block) in branches {
+            let kind = EdgeKind::Case(Some(LabelSanitizer::truncate(&self.text(cond), MAX_EDGE_LABEL_WIDTH)));
+            exits.extend(self.analyze_nested(block, vec![PendingEdge::labeled(decision_node, kind)], tail));
+        }
+        let else_entry = vec![PendingEdge::labeled(decision_node, EdgeKind::Else)];
+        exits.extend(match else_block {
+            Some(block) => self.analyze_nested(block, else_entry, tail),
+            None => else_entry,
+        });
+
+        if tail || exits.is_empty() {
+            return exits;
+        }
+        let merge_node = self.arena.add_node_at(NodeType::BasicBlock(self.labels().merge_point.clone()), None);
+        self.connect(exits, merge_node, EdgeKind::BranchDone);
+        vec![PendingEdge::new(merge_node)]
+    }
+
+    fn analyze_while(&mut self, expr_while: &ExprWhile, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
+        // 创建循环入口节点，条件中的 `?` 和 `.await` 每次迭代都重新求值
+        let (head, pending) = self.analyze_head(&expr_while.cond, pending);
+        let cond_text = self.text(&*expr_while.cond);
+        let loop_node = self.arena.add_node(NodeType::Loop(LoopKind::While(cond_text)));
+        self.connect(pending, loop_node, EdgeKind::EnterLoop);
+        let entry = head.unwrap_or(loop_node);
+        // `while let` 与 `if let` 相同，按模式是否匹配决定进入循环体还是结束循环
+        let (body_kind, exit_kind) = Self::branch_kinds(&expr_while.cond);
 
         // 处理循环体
-        let body_node = self.analyze_block(&expr_while.body, Some(loop_node));
-        self.graph.add_edge(loop_node, body_node, "是".to_string());
-        
+        let (body_exits, context) = self.analyze_loop_body(
+            entry,
+            expr_while.label.as_ref(),
+            &expr_while.body,
+            vec![PendingEdge::labeled(loop_node, body_kind)],
+        );
+
         // 创建循环回边
-        self.graph.add_edge(body_node, loop_node, "继续循环".to_string());
+        self.connect(body_exits, entry, EdgeKind::LoopBack);
 
-        // 创建循环出口
-        let exit_node = self.graph.add_node(NodeType::BasicBlock("循环结束".to_string()));
-        self.graph.add_edge(loop_node, exit_node, "否".to_string());
-        
-        exit_node
+        // 创建循环出口，条件不成立或 break 时到达
+        let exit_node = self.arena.add_node_at(NodeType::BasicBlock(self.labels().loop_end.clone()), None);
+        self.arena.add_edge(loop_node, exit_node, exit_kind);
+        self.connect(context.breaks, exit_node, EdgeKind::Break(None));
+
+        vec![PendingEdge::new(exit_node)]
     }
 
-    fn analyze_loop(&mut self, expr_loop: &ExprLoop, parent: NodeIndex) -> NodeIndex {
+    fn analyze_loop(&mut self, expr_loop: &ExprLoop, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
         // 创建循环入口节点
-        let loop_node = self.graph.add_node(NodeType::Loop(LoopKind::Loop));
-        self.graph.add_edge(parent, loop_node, "进入循环".to_string());
+        let loop_node = self.arena.add_node(NodeType::Loop(LoopKind::Loop));
+        self.connect(pending, loop_node, EdgeKind::EnterLoop);
 
         // 处理循环体
-        let body_node = self.analyze_block(&expr_loop.body, Some(loop_node));
-        
+        let (body_exits, context) = self.analyze_loop_body(loop_node, expr_loop.label.as_ref(), &expr_loop.body, vec![PendingEdge::new(loop_node)]);
+
         // 创建循环回边
-        self.graph.add_edge(body_node, loop_node, "继续循环".to_string());
+        self.connect(body_exits, loop_node, EdgeKind::LoopBack);
+
+        // 无条件循环只能通过 break 离开，没有 break 时之后的语句不可达；
+        // 带值的 break 直接连接到使用循环值的语句
+        if context.breaks.is_empty() || context.has_value {
+            return context.breaks;
+        }
+        let exit_node = self.arena.add_node_at(NodeType::BasicBlock(self.labels().loop_end.clone()), None);
+        self.connect(context.breaks, exit_node, EdgeKind::Break(None));
 
-        // 创建循环出口（用于break语句）
-        let exit_node = self.graph.add_node(NodeType::BasicBlock("循环结束".to_string()));
-        self.graph.add_edge(loop_node, exit_node, "break".to_string());
-        
-        exit_node
+        vec![PendingEdge::new(exit_node)]
     }
 
-    fn analyze_match(&mut self, expr_match: &ExprMatch, parent: NodeIndex) -> NodeIndex {
-        let match_node = self.graph.add_node(NodeType::Condition(
-            format!("match {}", quote!(#expr_match.expr))
+    // tail 为 true 时 match 是函数的末尾表达式，每个分支直接返回
+    fn analyze_match(&mut self, expr_match: &ExprMatch, pending: Vec<PendingEdge>, tail: bool) -> Vec<PendingEdge> {
+        // 有不带守卫的通配分支（`_` 或变量绑定）时在判断节点上标注，这个分支会兜住所有剩余的情况
+        let has_wildcard = expr_match.arms.iter().any(|arm| arm.guard.is_none() && Self::is_catch_all(&arm.pat));
+        let annotation = if has_wildcard { format!(" {}", self.labels().wildcard) } else { String::new() };
+        let (_, pending) = self.analyze_head(&expr_match.expr, pending);
+        let match_node = self.arena.add_node(NodeType::Condition(
+            format!("match {}{}", self.text(&*expr_match.expr), annotation)
         ));
-        self.graph.add_edge(parent, match_node, "next".to_string());
-
-        let merge_node = self.graph.add_node(NodeType::BasicBlock("after_match".to_string()));
+        self.connect(pending, match_node, EdgeKind::Next);
 
+        let mut arm_exits = Vec::new();
+        // 上一个分支的守卫不成立时继续尝试下一个分支
+        let mut guard_failed: Option<NodeIndex> = None;
         for arm in &expr_match.arms {
-            let arm_node = self.graph.add_node(NodeType::BasicBlock(
-                format!("case: {}", quote!(#arm.pat))
+            let outer = self.arena.set_span(SourceSpan::from_span(arm.span()));
+            let arm_node = self.arena.add_node(NodeType::BasicBlock(
+                format!("case: {}", self.text(&arm.pat))
             ));
-            self.graph.add_edge(match_node, arm_node, "case".to_string());
-
-            let body_node = match &*arm.body {
-                Expr::Block(block) => self.analyze_block(&block.block, Some(arm_node)),
-                expr => {
-                    let node = self.graph.add_node(NodeType::BasicBlock(
-                        format!("{}", quote!(#expr))
-                    ));
-                    self.graph.add_edge(arm_node, node, "next".to_string());
-                    node
+            let kind = if arm.guard.is_none() && Self::is_catch_all(&arm.pat) { EdgeKind::Default } else { EdgeKind::Case(None) };
+            self.arena.add_edge(match_node, arm_node, kind);
+            if let Some(guard_node) = guard_failed.take() {
+                self.arena.add_edge(guard_node, arm_node, EdgeKind::False);
+            }
+
+            let arm_entry = match &arm.guard {
+                Some((_, guard)) => {
+                    let guard_node = self.arena.add_node(NodeType::Condition(format!("if {}", self.text(&**guard))));
+                    self.arena.add_edge(arm_node, guard_node, EdgeKind::Next);
+                    guard_failed = Some(guard_node);
+                    vec![PendingEdge::labeled(guard_node, EdgeKind::True)]
                 }
+                None => vec![PendingEdge::new(arm_node)],
             };
-            self.graph.add_edge(body_node, merge_node, "next".to_string());
+            self.enter_nested();
+            arm_exits.extend(match &*arm.body {
+                Expr::Block(block) => self.analyze_stmts(&block.block, arm_entry, tail),
+                expr if tail => self.analyze_tail(expr, arm_entry),
+                expr => self.analyze_expr(expr, arm_entry),
+            });
+            self.leave_nested();
+            self.arena.set_span(outer);
+        }
+
+        // 所有分支都已离开当前路径时不需要合并点
+        if tail || arm_exits.is_empty() {
+            return arm_exits;
         }
 
-        merge_node
+        let merge_node = self.arena.add_node_at(NodeType::BasicBlock(self.labels().merge_point.clone()), None);
+        self.connect(arm_exits, merge_node, EdgeKind::Next);
+        vec![PendingEdge::new(merge_node)]
     }
 
-    fn analyze_for(&mut self, expr_for: &ExprForLoop, parent: NodeIndex) -> NodeIndex {
+    fn analyze_for(&mut self, expr_for: &ExprForLoop, pending: Vec<PendingEdge>) -> Vec<PendingEdge> {
         // 创建for循环节点，显示迭代器表达式
-        let loop_text = format!("for {} in {}", quote!(#expr_for.pat), quote!(#expr_for.expr));
-        let loop_node = self.graph.add_node(NodeType::Loop(LoopKind::For(loop_text)));
-        self.graph.add_edge(parent, loop_node, "进入循环".to_string());
+        // 迭代器表达式只在进入循环前求值一次
+        let (_, pending) = self.analyze_head(&expr_for.expr, pending);
+        let loop_text = format!("for {} in {}", self.text(&*expr_for.pat), self.text(&*expr_for.expr));
+        let loop_node = self.arena.add_node(NodeType::Loop(LoopKind::For(loop_text)));
+        self.connect(pending, loop_node, EdgeKind::EnterLoop);
 
         // 分析循环体
-        let body_node = self.analyze_block(&expr_for.body, Some(loop_node));
-        
+        let (body_exits, context) = self.analyze_loop_body(loop_node, expr_for.label.as_ref(), &expr_for.body, vec![PendingEdge::new(loop_node)]);
+
         // 添加循环返回边
-        self.graph.add_edge(body_node, loop_node, "继续循环".to_string());
+        self.connect(body_exits, loop_node, EdgeKind::LoopBack);
+
+        // 创建循环出口节点，迭代结束或 break 时到达
+        let exit_node = self.arena.add_node_at(NodeType::BasicBlock(self.labels().loop_end.clone()), None);
+        self.arena.add_edge(loop_node, exit_node, EdgeKind::LoopExit);
+        self.connect(context.breaks, exit_node, EdgeKind::Break(None));
+
+        vec![PendingEdge::new(exit_node)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::{EdgeKind, GraphConfig, NodeType};
+    use petgraph::visit::EdgeRef;
+
+    // 分析源码，返回所有边的（起点标签，终点标签，边种类）
+    fn edges(source: &str) -> Vec<(String, String, EdgeKind)> {
+        edges_with(source, &GraphConfig::default())
+    }
+
+    fn edges_with(source: &str, config: &GraphConfig) -> Vec<(String, String, EdgeKind)> {
+        let graph = crate::analyze_source(source, config).expect("source parses");
+        let graph = graph.graph();
+        graph
+            .edge_references()
+            .map(|edge| (graph[edge.source()].label(), graph[edge.target()].label(), edge.weight().clone()))
+            .collect()
+    }
+
+    fn count(source: &str, node: &NodeType) -> usize {
+        let graph = crate::analyze_source(source, &GraphConfig::default()).expect("source parses");
+        graph.graph().node_weights().filter(|weight| *weight == node).count()
+    }
+
+    fn has_edge(edges: &[(String, String, EdgeKind)], from: &str, to: &str, kind: EdgeKind) -> bool {
+        edges.iter().any(|edge| edge.0 == from && edge.1 == to && edge.2 == kind)
+    }
+
+    #[test]
+    fn empty_function_connects_start_to_end() {
+        let edges = edges("fn noop() {}");
+        assert_eq!(edges, vec![("Start: crate::noop".to_string(), "End: crate::noop".to_string(), EdgeKind::Return(None))]);
+    }
 
-        // 创建循环出口节点
-        let exit_node = self.graph.add_node(NodeType::BasicBlock("循环结束".to_string()));
-        self.graph.add_edge(loop_node, exit_node, "退出循环".to_string());
+    #[test]
+    fn empty_loop_bodies_get_an_explicit_node() {
+        for body in ["loop {}", "while ready() {}", "for _ in 0..3 {}"] {
+            let source = format!("fn f() {{ {body} }}");
+            assert_eq!(count(&source, &NodeType::BasicBlock("(empty)".to_string())), 1, "{body}");
+            let edges = edges(&source);
+            assert!(edges.iter().any(|edge| edge.1 == "(empty)"), "{body}");
+            assert!(edges.iter().any(|edge| edge.0 == "(empty)" && edge.2 == EdgeKind::LoopBack), "{body}");
+            assert!(!edges.iter().any(|edge| edge.0 == edge.1), "{body}: loop node wired to itself");
+        }
+    }
+
+    #[test]
+    fn empty_branches_get_an_explicit_node() {
+        let source = "fn f() { if ready() {} else {} done(); }";
+        assert_eq!(count(source, &NodeType::BasicBlock("(empty)".to_string())), 2);
+        let edges = edges(source);
+        assert!(has_edge(&edges, "Condition: ready()", "(empty)", EdgeKind::True));
+        assert!(has_edge(&edges, "Condition: ready()", "(empty)", EdgeKind::False));
+        assert_eq!(edges.iter().filter(|edge| edge.0 == "(empty)" && edge.2 == EdgeKind::BranchDone).count(), 2);
+    }
+
+    #[test]
+    fn missing_else_is_not_an_empty_branch() {
+        let source = "fn f() { if ready() { go(); } }";
+        assert_eq!(count(source, &NodeType::BasicBlock("(empty)".to_string())), 0);
+    }
+
+    #[test]
+    fn empty_tail_branch_returns() {
+        let edges = edges("fn f() { if ready() {} }");
+        assert!(has_edge(&edges, "(empty)", "End: crate::f", EdgeKind::Return(None)));
+    }
 
-        exit_node
+    fn recursion_edges(source: &str) -> Vec<String> {
+        let config = GraphConfig { show_recursion: true, ..GraphConfig::default() };
+        edges_with(source, &config)
+            .into_iter()
+            .filter(|edge| edge.2 == EdgeKind::Recursion)
+            .map(|edge| edge.0)
+            .collect()
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn recursion_links_self_calls_to_start() {
+        assert_eq!(recursion_edges("fn fact(n: u64) -> u64 { let m = fact(n - 1); n * m }"), vec!["let m = fact(n - 1);"]);
+        assert_eq!(recursion_edges("impl Foo { fn new() -> Self { let v = Self::new(); v } }"), vec!["let v = Self::new();"]);
+        assert_eq!(recursion_edges("impl Foo { fn walk(&self) { self.walk(); } }"), vec!["self.walk()"]);
+    }
+
+    #[test]
+    fn recursion_ignores_calls_through_other_paths() {
+        assert!(recursion_edges("impl Foo { fn new() -> Self { let v = Vec::new(); Foo { v } } }").is_empty());
+        assert!(recursion_edges("fn parse() { other::parse(); }").is_empty());
+        assert!(recursion_edges("impl Default for Foo { fn default() -> Self { Foo(T::default()) } }").is_empty());
+        // 关联函数中不带路径的同名调用是另一个自由函数
+        assert!(recursion_edges("impl Foo { fn new() -> Self { new(); Foo } }").is_empty());
+        assert!(recursion_edges("fn walk(node: &Node) { node.walk(); }").is_empty());
+    }
+
+    #[test]
+    fn recursion_is_opt_in() {
+        assert!(!edges("fn fact(n: u64) -> u64 { fact(n - 1) }").iter().any(|edge| edge.2 == EdgeKind::Recursion));
+    }
+
+    #[test]
+    fn failed_guard_falls_through_to_next_arm() {
+        let edges = edges("fn f(x: u8) { match x { n if n > 10 => big(n), 0 => zero(), _ => other() } done(); }");
+        assert!(has_edge(&edges, "case: n", "Condition: if n > 10", EdgeKind::Next));
+        assert!(has_edge(&edges, "Condition: if n > 10", "big(n)", EdgeKind::True));
+        assert!(has_edge(&edges, "Condition: if n > 10", "case: 0", EdgeKind::False));
+        // 守卫不成立时只转到紧接着的下一个分支
+        assert!(!edges.iter().any(|edge| edge.0 == "Condition: if n > 10" && edge.1 == "case: _"));
+    }
+
+    #[test]
+    fn flat_else_if_fans_out_from_one_decision() {
+        let config = GraphConfig { flat_else_if: true, ..GraphConfig::default() };
+        let source = "fn f(x: i32) { if x < 0 { neg(); } else if x == 0 { zero(); } else { pos(); } done(); }";
+        let edges = edges_with(source, &config);
+        let decision = "Condition: if / else if 链（2 个条件）";
+        assert!(has_edge(&edges, "Start: crate::f", decision, EdgeKind::EnterCondition));
+        assert!(has_edge(&edges, decision, "neg()", EdgeKind::Case(Some("x < 0".to_string()))));
+        assert!(has_edge(&edges, decision, "zero()", EdgeKind::Case(Some("x == 0".to_string()))));
+        assert!(has_edge(&edges, decision, "pos()", EdgeKind::Else));
+        assert_eq!(edges.iter().filter(|edge| edge.1 == "分支合并点" && edge.2 == EdgeKind::BranchDone).count(), 3);
+        assert!(!edges.iter().any(|edge| edge.0.starts_with("Condition: x")));
+    }
+
+    #[test]
+    fn wildcard_arm_gets_default_edge() {
+        let source = "fn f(x: u8) { match x { 0 => zero(), _ => other() } done(); }";
+        let edges = edges(source);
+        assert!(has_edge(&edges, "Condition: match x [兜底分支]", "case: 0", EdgeKind::Case(None)));
+        assert!(has_edge(&edges, "Condition: match x [兜底分支]", "case: _", EdgeKind::Default));
+
+        let config = GraphConfig { labels: crate::graph::Labels::en(), ..GraphConfig::default() };
+        let edges = edges_with(source, &config);
+        assert!(has_edge(&edges, "Condition: match x [wildcard]", "case: _", EdgeKind::Default));
+    }
+
+    #[test]
+    fn guarded_catch_all_is_not_a_wildcard() {
+        let edges = edges("fn f(x: u8) { match x { 0 => zero(), n if n > 1 => many(n), 1 => one() } done(); }");
+        assert!(edges.iter().any(|edge| edge.0 == "Condition: match x"));
+        assert!(!edges.iter().any(|edge| edge.2 == EdgeKind::Default));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::graph::{FlowGraph, GraphConfig};
-use crate::passes::{ControlFlowAnalyzerPass, ParserPass};
-use syn::ItemFn;
+use crate::passes::{CollectedFunction, ControlFlowAnalyzerPass};
+use syn::{ReturnType, Type};
 
 pub struct GraphBuilderPass {
     graph: FlowGraph,
@@ -25,16 +25,51 @@ impl GraphBuilderPass {
         }
     }
     
-    pub fn build(functions: Vec<ItemFn>) -> FlowGraph {
+    pub fn build(functions: Vec<CollectedFunction>) -> FlowGraph {
         Self::build_with_config(functions, GraphConfig::default())
     }
 
-    pub fn build_with_config(functions: Vec<ItemFn>, config: GraphConfig) -> FlowGraph {
+    pub fn build_with_config(functions: Vec<CollectedFunction>, config: GraphConfig) -> FlowGraph {
+        Self::build_with_source(functions, config, None)
+    }
+
+    pub fn build_with_source(
+        functions: Vec<CollectedFunction>,
+        config: GraphConfig,
+        source: Option<&str>,
+    ) -> FlowGraph {
         let mut builder = Self::with_config(config);
-        let mut analyzer = ControlFlowAnalyzerPass::new(&mut builder.graph);
+        let filter = builder.graph.config().clone();
+        let mut analyzer = ControlFlowAnalyzerPass::with_source(&mut builder.graph, source);
+        analyzer.set_diverging_functions(
+            functions.iter()
+                .filter(|func| matches!(&func.item.sig.output, ReturnType::Type(_, ty) if matches!(**ty, Type::Never(_))))
+                .map(|func| func.item.sig.ident.to_string())
+                .collect(),
+        );
         
+        // 上一个函数是否使用了覆盖的选项，是则需要恢复全局选项
+        let mut overridden = false;
         for func in functions {
-            analyzer.analyze_function(&func);
+            // 过滤在构建前进行，不匹配的函数不会被分析
+            if !filter.matches_function(&func.path) || filter.skips_function(&func.path) {
+                continue;
+            }
+            match filter.for_function(&func.path) {
+                Some(config) => {
+                    analyzer.set_config(config);
+                    overridden = true;
+                }
+                None if overridden => {
+                    analyzer.set_config(filter.clone());
+                    overridden = false;
+                }
+                None => {}
+            }
+            analyzer.analyze_collected(&func);
+        }
+        if overridden {
+            analyzer.set_config(filter);
         }
         
         builder.graph
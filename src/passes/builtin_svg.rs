@@ -0,0 +1,22 @@
+use anyhow::{anyhow, Result};
+use layout::backends::svg::SVGWriter;
+use layout::gv::{DotParser, GraphBuilder};
+
+// 不依赖外部 dot 命令，用 layout-rs 完成布局并输出 SVG。
+// 只支持 Graphviz 的一部分功能：子图（分组框）、HTML 标签和正交连线等会被忽略或按普通标签处理
+pub struct BuiltinSvgPass;
+
+impl BuiltinSvgPass {
+    pub fn render(dot: &str) -> Result<String> {
+        let mut parser = DotParser::new(dot);
+        let ast = parser.process().map_err(|e| anyhow!("Failed to parse DOT: {}", e))?;
+
+        let mut builder = GraphBuilder::new();
+        builder.visit_graph(&ast);
+        let mut graph = builder.get();
+
+        let mut svg = SVGWriter::new();
+        graph.do_it(false, false, false, &mut svg);
+        Ok(svg.finalize())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::graph::{FlowGraph, GraphConfig, StoredGraph};
+use crate::passes::ParserPass;
+use serde::{Deserialize, Serialize};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+// 一个源文件的缓存：文件内容、分析选项或 cargo-graph 版本变化后 key 随之变化，缓存失效
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    graph: StoredGraph,
+}
+
+// 按文件缓存的分析结果，默认位于 target/cargo-graph/cache。每个源文件只保留最新的一份，
+// 缓存文件名由源文件路径决定，内容变化时覆盖；读写失败时当作没有缓存
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+impl AnalysisCache {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    pub fn load(&self, path: &Path, source: &str, config: &GraphConfig) -> Option<FlowGraph> {
+        let data = std::fs::read(self.entry_path(path)).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&data).ok()?;
+        (entry.key == Self::key(path, source, config)).then(|| FlowGraph::from_stored(entry.graph, config.clone()))
+    }
+
+    pub fn store(&self, path: &Path, source: &str, config: &GraphConfig, graph: &FlowGraph) {
+        let entry = CacheEntry { key: Self::key(path, source, config), graph: graph.to_stored() };
+        let Ok(data) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        // 先写临时文件再重命名，多个进程同时运行时不会读到写了一半的缓存
+        let entry_path = self.entry_path(path);
+        let temp_path = entry_path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&temp_path, data))
+            .and_then(|_| std::fs::rename(&temp_path, &entry_path));
+        if written.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        self.dir.join(format!("{:016x}.json", Self::hash(&absolute)))
+    }
+
+    // 显示路径和模块路径会写进函数信息，同一个文件从不同目录运行时结果也不同
+    fn key(path: &Path, source: &str, config: &GraphConfig) -> String {
+        let inputs = (
+            env!("CARGO_PKG_VERSION"),
+            ParserPass::display_path(path),
+            ParserPass::module_path(path),
+            source,
+            Self::options(config),
+        );
+        format!("{:016x}", Self::hash(&inputs))
+    }
+
+    // 影响分析结果的选项；主题、链接模板等只影响渲染，修改后不需要重新分析
+    fn options(config: &GraphConfig) -> String {
+        format!(
+            "{:?}",
+            (
+                config.include_tests,
+                &config.function_filter,
+                &config.item_paths,
+                config.panic_as_exit,
+                config.group_declarations,
+                config.expand_boolean_ops,
+                config.show_recursion,
+                config.flat_else_if,
+                config.block_granularity,
+                &config.labels,
+                &config.function_overrides,
+            )
+        )
+    }
+
+    fn hash(value: &impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
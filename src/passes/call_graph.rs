@@ -0,0 +1,309 @@
+use crate::passes::{CallSite, CodeOwners, CollectedFunction, ImportTable, ReachabilityPass, ScipIndex, StyledEdge, StyledGraph, StyledNode};
+use crate::graph::EdgeKind;
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use crate::style::{DotEscape, EdgeStyle, Theme};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+// 一个 crate 的名称及其收集到的函数和 use 语句
+pub struct CrateFunctions {
+    pub name: String,
+    // crate 根目录（Cargo.toml 所在目录）
+    pub root: PathBuf,
+    pub functions: Vec<CollectedFunction>,
+    pub imports: ImportTable,
+}
+
+// 调用图中的节点以 (crate 序号, 函数完整路径) 标识
+pub type CallNode = (usize, String);
+
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    pub nodes: BTreeSet<CallNode>,
+    // 调用边及调用点数量
+    pub edges: BTreeMap<(CallNode, CallNode), usize>,
+    // 无法解析到任何已收集函数的调用，按调用目标名称记录
+    pub external_edges: BTreeMap<(CallNode, String), usize>,
+}
+
+impl CallGraph {
+    // 只保留从根函数出发沿调用边可以到达的部分；根函数可以写完整路径或最后一段名称
+    pub fn reachable_from(&self, roots: &[String]) -> CallGraph {
+        let mut queue: VecDeque<&CallNode> = self.nodes.iter()
+            .filter(|node| roots.iter().any(|root| Self::matches(&node.1, root)))
+            .collect();
+        let mut reachable: BTreeSet<CallNode> = BTreeSet::new();
+        while let Some(node) = queue.pop_front() {
+            if !reachable.insert(node.clone()) {
+                continue;
+            }
+            queue.extend(self.edges.keys().filter(|(caller, _)| caller == node).map(|(_, callee)| callee));
+        }
+
+        CallGraph {
+            edges: self.edges.iter()
+                .filter(|((caller, _), _)| reachable.contains(caller))
+                .map(|(edge, count)| (edge.clone(), *count))
+                .collect(),
+            external_edges: self.external_edges.iter()
+                .filter(|((caller, _), _)| reachable.contains(caller))
+                .map(|(edge, count)| (edge.clone(), *count))
+                .collect(),
+            nodes: reachable,
+        }
+    }
+
+    fn matches(path: &str, root: &str) -> bool {
+        path == root || path.strip_suffix(root).is_some_and(|prefix| prefix.ends_with("::"))
+    }
+}
+
+pub struct CallGraphPass;
+
+impl CallGraphPass {
+    pub fn build(crates: &[CrateFunctions], index: Option<&ScipIndex>) -> CallGraph {
+        let mut graph = CallGraph::default();
+
+        for (crate_id, krate) in crates.iter().enumerate() {
+            for func in krate.functions.iter().filter(|func| !func.is_test) {
+                let caller = (crate_id, func.path.clone());
+                graph.nodes.insert(caller.clone());
+
+                for call in ReachabilityPass::calls(func) {
+                    let targets = Self::resolve(&call, func, crate_id, crates, index);
+                    if targets.is_empty() && call.is_external_candidate() {
+                        let target = krate.imports.expand(&func.path, &call.target)
+                            .unwrap_or_else(|| call.target.clone());
+                        *graph.external_edges.entry((caller.clone(), target)).or_default() += 1;
+                    }
+                    for target in targets {
+                        graph.nodes.insert(target.clone());
+                        *graph.edges.entry((caller.clone(), target)).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    // 先在本 crate 内解析；以其他 crate 名开头的路径（包括 use 展开后）到对应 crate 中解析
+    fn resolve(
+        call: &CallSite,
+        caller: &CollectedFunction,
+        crate_id: usize,
+        crates: &[CrateFunctions],
+        index: Option<&ScipIndex>,
+    ) -> Vec<CallNode> {
+        // 索引中的定义位置可能在任何成员 crate 中
+        if let (Some(index), Some(file)) = (index, &caller.file)
+            && let Some(locations) = index.definitions_of(file, call)
+        {
+            return crates.iter().enumerate()
+                .flat_map(|(id, krate)| {
+                    krate.functions.iter()
+                        .filter(|func| !func.is_test && ScipIndex::is_defined_at(func, &locations))
+                        .map(move |func| (id, func.path.clone()))
+                })
+                .collect();
+        }
+
+        let krate = &crates[crate_id];
+        let local: Vec<CallNode> = ReachabilityPass::resolve_call(call, caller, &krate.functions, &krate.imports, None)
+            .into_iter()
+            .filter(|func| !func.is_test)
+            .map(|func| (crate_id, func.path.clone()))
+            .collect();
+        if !local.is_empty() || call.is_method {
+            return local;
+        }
+
+        let target = krate.imports.expand(&caller.path, &call.target)
+            .unwrap_or_else(|| call.target.clone());
+        let Some((first, rest)) = target.split_once("::") else {
+            return Vec::new();
+        };
+        crates.iter().enumerate()
+            .filter(|(id, krate)| *id != crate_id && Self::crate_ident(&krate.name) == first)
+            .flat_map(|(id, krate)| {
+                let path = format!("crate::{}", rest);
+                krate.functions.iter()
+                    .filter(move |func| !func.is_test && func.path == path)
+                    .map(move |func| (id, func.path.clone()))
+            })
+            .collect()
+    }
+
+    // Cargo 包名中的 `-` 在代码中写作 `_`
+    fn crate_ident(name: &str) -> String {
+        name.replace('-', "_")
+    }
+
+    // `crate::module::f` 显示为 `my_crate::module::f`
+    pub fn display_name(crates: &[CrateFunctions], node: &CallNode) -> String {
+        let crate_name = Self::crate_ident(&crates[node.0].name);
+        match node.1.strip_prefix("crate") {
+            Some(rest) => format!("{}{}", crate_name, rest),
+            None => format!("{}::{}", crate_name, node.1),
+        }
+    }
+
+    // 转换为 DotRendererPass 使用的样式图：函数为圆角矩形，根函数加粗显示，
+    // 未解析的外部调用为虚线节点；同一调用边有多个调用点时在边上标注数量。
+    // 颜色取自主题的 function、root_function、external 节点和 external_call 边
+    pub fn styled(crates: &[CrateFunctions], graph: &CallGraph, roots: &[String], theme: &Theme) -> StyledGraph {
+        let mut styled = StyledGraph::new();
+        styled.theme = theme.clone();
+        let mut ids: HashMap<&CallNode, NodeIndex> = HashMap::new();
+        for node in &graph.nodes {
+            let id = NodeIndex::new(styled.nodes.len());
+            ids.insert(node, id);
+            let is_root = roots.iter().any(|root| CallGraph::matches(&node.1, root));
+            let node_theme = theme.node(if is_root { "root_function" } else { "function" });
+            styled.nodes.push(StyledNode {
+                id,
+                function: None,
+                kind: "function".to_string(),
+                shape: node_theme.shape,
+                style: node_theme.style,
+                fillcolor: node_theme.fillcolor,
+                color: node_theme.color,
+                label: Self::display_name(crates, node),
+                tooltip: None,
+                url: None,
+                span: None,
+            });
+        }
+
+        let externals: BTreeSet<&String> = graph.external_edges.keys().map(|(_, target)| target).collect();
+        let mut external_ids: HashMap<&String, NodeIndex> = HashMap::new();
+        for external in externals {
+            let id = NodeIndex::new(styled.nodes.len());
+            external_ids.insert(external, id);
+            let node_theme = theme.node("external");
+            styled.nodes.push(StyledNode {
+                id,
+                function: None,
+                kind: "external".to_string(),
+                shape: node_theme.shape,
+                style: node_theme.style,
+                fillcolor: node_theme.fillcolor,
+                color: node_theme.color,
+                label: external.clone(),
+                tooltip: Some("未解析的外部调用".to_string()),
+                url: None,
+                span: None,
+            });
+        }
+
+        let count_label = |count: usize| if count > 1 { count.to_string() } else { String::new() };
+        for ((caller, callee), count) in &graph.edges {
+            styled.edges.push(StyledEdge {
+                from: ids[caller],
+                to: ids[callee],
+                color: theme.edge.color.clone(),
+                style: theme.edge.style.clone(),
+                kind: EdgeKind::Call,
+                label: count_label(*count),
+            });
+        }
+        let external_theme = EdgeStyle::get(&EdgeKind::ExternalCall, theme);
+        for ((caller, external), count) in &graph.external_edges {
+            styled.edges.push(StyledEdge {
+                from: ids[caller],
+                to: external_ids[external],
+                color: external_theme.color.clone(),
+                style: external_theme.style.clone(),
+                kind: EdgeKind::ExternalCall,
+                label: count_label(*count),
+            });
+        }
+
+        styled
+    }
+
+    // 提供负责人规则时，分组按 crate 的负责团队着色，函数节点按所在文件的负责团队着色
+    pub fn render_workspace_dot(crates: &[CrateFunctions], graph: &CallGraph, owners: Option<&CodeOwners>) -> String {
+        let mut dot = String::from("digraph Workspace {\n");
+        dot.push_str("    graph [rankdir=LR; compound=true; nodesep=0.4; ranksep=1.2];\n");
+        dot.push_str("    node [fontname=\"Arial\"; fontsize=10; shape=box; style=\"rounded,filled\"; fillcolor=\"lightblue\"];\n");
+        dot.push_str("    edge [fontname=\"Arial\"; fontsize=9; color=\"gray40\"];\n\n");
+
+        // 每个成员 crate 作为一个分组
+        let mut teams = BTreeSet::new();
+        for (crate_id, krate) in crates.iter().enumerate() {
+            let _ = writeln!(dot, "    subgraph cluster_crate_{} {{", crate_id);
+            match owners {
+                Some(owners) => {
+                    let team = owners.team_of(&krate.root.join("Cargo.toml"));
+                    teams.extend(team);
+                    dot.push_str(&CodeOwners::cluster_attributes(team, &krate.name));
+                }
+                None => {
+                    let _ = writeln!(dot, "        label=\"{}\";", Self::escape(&krate.name));
+                    dot.push_str("        style=rounded;\n        color=gray;\n        bgcolor=aliceblue;\n");
+                }
+            }
+            for node in graph.nodes.iter().filter(|node| node.0 == crate_id) {
+                let name = Self::display_name(crates, node);
+                let fill = owners
+                    .map(|owners| {
+                        let team = krate.functions.iter()
+                            .find(|func| func.path == node.1)
+                            .and_then(|func| func.file.as_deref())
+                            .and_then(|file| owners.team_of(Path::new(file)));
+                        teams.extend(team);
+                        format!(", fillcolor=\"{}\"", CodeOwners::color(team))
+                    })
+                    .unwrap_or_default();
+                let _ = writeln!(dot, "        \"{}\" [label=\"{}\"{}];", Self::escape(&name), Self::escape(&name), fill);
+            }
+            dot.push_str("    }\n\n");
+        }
+        if owners.is_some() {
+            dot.push_str(&CodeOwners::legend_dot(&teams));
+        }
+
+        // 未解析的外部调用以灰色虚线节点显示
+        let externals: BTreeSet<&String> = graph.external_edges.keys().map(|(_, target)| target).collect();
+        for external in externals {
+            let _ = writeln!(
+                dot,
+                "    \"extern:{}\" [label=\"{}\"; style=\"dashed\"; color=\"gray50\"; fontcolor=\"gray40\"];",
+                Self::escape(external), Self::escape(external)
+            );
+        }
+
+        // 跨 crate 的调用以加粗的边表示，并标注调用点数量
+        for ((caller, callee), count) in &graph.edges {
+            let style = if caller.0 == callee.0 {
+                String::new()
+            } else {
+                format!(" [penwidth=2.5; color=\"firebrick\"; label=\"{}\"]", count)
+            };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\"{};",
+                Self::escape(&Self::display_name(crates, caller)),
+                Self::escape(&Self::display_name(crates, callee)),
+                style
+            );
+        }
+        for (caller, external) in graph.external_edges.keys() {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"extern:{}\" [style=\"dashed\"; color=\"gray60\"];",
+                Self::escape(&Self::display_name(crates, caller)),
+                Self::escape(external)
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn escape(text: &str) -> String {
+        DotEscape::quoted(text)
+    }
+}
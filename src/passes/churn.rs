@@ -0,0 +1,129 @@
+use crate::passes::CollectedFunction;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use syn::spanned::Spanned;
+
+// `git diff -U0` 中的一个修改块：旧文件 [old_start, old_start + old_len) 被替换为
+// 新文件 [new_start, new_start + new_len)；长度为 0 时 start 是插入/删除位置之前的行
+#[derive(Debug, Clone, Copy)]
+struct Hunk {
+    old_start: usize,
+    old_len: usize,
+    new_start: usize,
+    new_len: usize,
+}
+
+// 一个文件从新到旧的修改历史，每个元素是一次提交（或未提交的修改）的所有修改块
+pub struct FileHistory {
+    changes: Vec<Vec<Hunk>>,
+}
+
+pub struct ChurnPass;
+
+impl ChurnPass {
+    pub fn history(file: &Path, since: &str) -> Result<FileHistory> {
+        let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let name = file.file_name().context("Invalid file path")?;
+        let git = |args: &[&str]| -> Result<String> {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .arg("--")
+                .arg(name)
+                .output()
+                .context("Failed to run git")?;
+            if !output.status.success() {
+                bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+
+        // 未提交的修改算作最新的一次
+        let mut changes = Vec::new();
+        let uncommitted = Self::parse_hunks(&git(&["diff", "-U0", "HEAD"])?);
+        if !uncommitted.is_empty() {
+            changes.push(uncommitted);
+        }
+        let log = git(&["log", &format!("--since={}", since), "--follow", "-p", "-U0", "--format=%x00%H"])?;
+        for commit in log.split('\0').skip(1) {
+            changes.push(Self::parse_hunks(commit));
+        }
+
+        Ok(FileHistory { changes })
+    }
+
+    // `@@ -a,b +c,d @@`，省略长度时为 1
+    fn parse_hunks(diff: &str) -> Vec<Hunk> {
+        let range = |range: &str| -> Option<(usize, usize)> {
+            let (start, len) = range.split_once(',').unwrap_or((range, "1"));
+            Some((start.parse().ok()?, len.parse().ok()?))
+        };
+        diff.lines()
+            .filter_map(|line| {
+                let mut parts = line.strip_prefix("@@ ")?.split_whitespace();
+                let (old_start, old_len) = range(parts.next()?.strip_prefix('-')?)?;
+                let (new_start, new_len) = range(parts.next()?.strip_prefix('+')?)?;
+                Some(Hunk { old_start, old_len, new_start, new_len })
+            })
+            .collect()
+    }
+
+    // 每个函数在这段历史中被多少次提交修改过。从当前文件的行号开始，
+    // 逐个提交把函数的行范围映射回修改之前的位置，直到函数在某次提交中被新增
+    pub fn function_churn(functions: &[CollectedFunction], history: &FileHistory) -> HashMap<String, usize> {
+        functions.iter()
+            .filter_map(|func| {
+                let (start, end) = (func.item.sig.span().start().line, func.item.block.span().end().line);
+                if start == 0 || end < start {
+                    return None;
+                }
+
+                let (mut start, mut end) = (start, end);
+                let mut commits = 0;
+                for hunks in &history.changes {
+                    if hunks.iter().any(|hunk| Self::touches(hunk, start, end)) {
+                        commits += 1;
+                    }
+                    (start, end) = (Self::old_line(hunks, start, true), Self::old_line(hunks, end, false));
+                    if end < start {
+                        break;
+                    }
+                }
+                Some((func.path.clone(), commits))
+            })
+            .collect()
+    }
+
+    fn touches(hunk: &Hunk, start: usize, end: usize) -> bool {
+        if hunk.new_len == 0 {
+            // 纯删除发生在 new_start 与下一行之间
+            hunk.new_start >= start && hunk.new_start < end
+        } else {
+            hunk.new_start <= end && hunk.new_start + hunk.new_len > start
+        }
+    }
+
+    // 新文件中的行在修改之前的行号；落在修改块内时取修改块旧范围的起点或终点
+    fn old_line(hunks: &[Hunk], line: usize, is_start: bool) -> usize {
+        let mut delta = 0isize;
+        for hunk in hunks {
+            if line < hunk.new_start || (hunk.new_len == 0 && line == hunk.new_start) {
+                break;
+            }
+            if line < hunk.new_start + hunk.new_len {
+                let old_end = hunk.old_start + hunk.old_len;
+                return match (is_start, hunk.old_len) {
+                    (true, 0) => hunk.old_start + 1,
+                    (true, _) => hunk.old_start,
+                    (false, 0) => hunk.old_start,
+                    (false, _) => old_end - 1,
+                };
+            }
+            delta += hunk.new_len as isize - hunk.old_len as isize;
+        }
+        (line as isize - delta).max(0) as usize
+    }
+}
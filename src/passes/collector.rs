@@ -1,7 +1,40 @@
-use syn::{File, ItemFn, visit::{self, Visit}};
+use crate::passes::ParserPass;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Block, Expr, ExprCall, ExprClosure, ExprMethodCall, File, ImplItemFn, ItemConst,
+    ItemFn, ItemImpl, ItemMod, ItemStatic, ItemTrait, Local, Pat, Stmt, TraitItemFn, Type, Visibility,
+    parse_quote,
+    visit::{self, Visit},
+};
+
+#[derive(Debug, Clone)]
+pub struct CollectedFunction {
+    // 完整路径，例如 crate::module::Type::new
+    pub path: String,
+    // 所在源文件，用于闭包命名和索引查找
+    pub file: Option<String>,
+    pub item: ItemFn,
+    pub is_test: bool,
+    pub is_entry: bool,
+    // 闭包或嵌套函数所在函数的完整路径，渲染时它的图嵌套在该函数中
+    pub parent: Option<String>,
+    // 闭包、静态初始化等没有函数签名的伪函数，item 中的签名是占位的
+    pub pseudo: bool,
+    // impl 或 trait 中的关联函数，只能通过 `Self::name`/`self.name` 调用自身
+    pub associated: bool,
+}
 
 pub struct FunctionCollectorPass {
-    functions: Vec<ItemFn>,
+    functions: Vec<CollectedFunction>,
+    scope: Vec<String>,
+    // 由外到内正在访问的函数完整路径
+    enclosing: Vec<String>,
+    // 源文件路径
+    file: Option<String>,
+    test_depth: usize,
+    // 文件本身对应的模块层级，main 只有在这一层才是入口
+    root_depth: usize,
 }
 
 impl Default for FunctionCollectorPass {
@@ -12,19 +45,331 @@ impl Default for FunctionCollectorPass {
 
 impl FunctionCollectorPass {
     pub fn new() -> Self {
-        Self { functions: Vec::new() }
+        Self::in_module("crate")
+    }
+
+    pub fn in_module(module_path: &str) -> Self {
+        let scope: Vec<String> = module_path.split("::").map(str::to_string).collect();
+        Self {
+            functions: Vec::new(),
+            root_depth: scope.len(),
+            scope,
+            enclosing: Vec::new(),
+            file: None,
+            test_depth: 0,
+        }
+    }
+
+    pub fn with_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    pub fn collect(file: &File) -> Vec<CollectedFunction> {
+        Self::collect_in_module(file, "crate")
     }
-    
-    pub fn collect(file: &File) -> Vec<ItemFn> {
-        let mut collector = Self::new();
-        collector.visit_file(file);
-        collector.functions
+
+    pub fn collect_in_module(file: &File, module_path: &str) -> Vec<CollectedFunction> {
+        Self::in_module(module_path).run(file)
+    }
+
+    pub fn run(mut self, file: &File) -> Vec<CollectedFunction> {
+        self.visit_file(file);
+        self.functions
+    }
+
+    fn qualified(&self, name: &str) -> String {
+        let mut path = self.scope.join("::");
+        path.push_str("::");
+        path.push_str(name);
+        path
+    }
+
+    fn push_function(&mut self, item: ItemFn, associated: bool) {
+        let path = self.qualified(&item.sig.ident.to_string());
+        let is_test = self.test_depth > 0 || ParserPass::is_test_fn(&item.attrs);
+        // 只有文件顶层的 main 才是程序入口
+        let is_entry = ParserPass::is_entry_fn(&item)
+            && (item.sig.ident != "main" || self.scope.len() == self.root_depth);
+        let file = self.file.clone();
+        // 定义在函数体内的函数嵌套在外层函数中显示
+        let parent = self.enclosing.last().cloned();
+        self.functions.push(CollectedFunction { path, file, item, is_test, is_entry, parent, pseudo: false, associated });
+    }
+
+    // 只有包含代码块或控制流的表达式才值得单独成图
+    fn expr_block(expr: &Expr) -> Option<Block> {
+        match expr {
+            Expr::Block(expr_block) => Some(expr_block.block.clone()),
+            Expr::If(_) | Expr::Match(_) | Expr::Loop(_) | Expr::While(_) | Expr::ForLoop(_) => {
+                Some(Block {
+                    brace_token: Default::default(),
+                    stmts: vec![Stmt::Expr(expr.clone(), None)],
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // 没有函数签名的代码（闭包、静态初始化）作为伪函数收集
+    fn push_pseudo_function(&mut self, path: String, attrs: &[Attribute], block: Block, parent: Option<String>) {
+        let mut item: ItemFn = parse_quote!(fn pseudo() {});
+        item.attrs = attrs.to_vec();
+        item.block = Box::new(block);
+        self.functions.push(CollectedFunction {
+            path,
+            file: self.file.clone(),
+            item,
+            is_test: self.test_depth > 0,
+            is_entry: false,
+            parent,
+            pseudo: true,
+            associated: false,
+        });
+    }
+
+    // 绑定到变量或作为参数传递的闭包按 `名称@文件:行号` 命名；
+    // 只有单个表达式的简单闭包不单独成图
+    fn push_closure(&mut self, name: &str, closure: &ExprClosure) {
+        let Some(block) = Self::expr_block(&closure.body) else {
+            return;
+        };
+
+        let location = self.file.clone().unwrap_or_else(|| self.scope.join("::"));
+        let path = format!("{}@{}:{}", name, location, closure.span().start().line);
+        let parent = self.enclosing.last().cloned();
+        self.push_pseudo_function(path, &closure.attrs, block, parent);
+    }
+
+    // const/static 初始化表达式：`Lazy::new(|| { ... })` 取闭包体，
+    // 其他包含控制流的初始化表达式直接作为函数体。返回已经被收集的闭包
+    fn push_initializer<'ast>(
+        &mut self,
+        name: &str,
+        attrs: &[Attribute],
+        init: &'ast Expr,
+    ) -> Option<&'ast ExprClosure> {
+        let lazy_closure = match init {
+            Expr::Call(call) => call.args.iter().find_map(|arg| match arg {
+                Expr::Closure(closure) => Some(closure),
+                _ => None,
+            }),
+            _ => None,
+        };
+
+        let block = match lazy_closure {
+            Some(closure) => Self::expr_block(&closure.body).unwrap_or_else(|| Block {
+                brace_token: Default::default(),
+                stmts: vec![Stmt::Expr((*closure.body).clone(), None)],
+            }),
+            None => Self::expr_block(init)?,
+        };
+
+        let path = self.qualified(name);
+        self.push_pseudo_function(path, attrs, block, None);
+        lazy_closure
+    }
+
+    // 只在测试构建中编译的模块：`#[cfg(test)]`，`all(..)` 中有一项只在测试时成立，
+    // 或 `any(..)` 中每一项都只在测试时成立；`not(test)`、`any(test, unix)` 在普通构建中也会编译
+    pub(crate) fn is_test_module(node: &ItemMod) -> bool {
+        node.attrs.iter().any(|attr| {
+            let mut test_only = false;
+            attr.path().is_ident("cfg")
+                && attr.parse_nested_meta(|meta| {
+                    test_only |= Self::cfg_test_only(meta)?;
+                    Ok(())
+                }).is_ok()
+                && test_only
+        })
+    }
+
+    fn cfg_test_only(meta: syn::meta::ParseNestedMeta) -> syn::Result<bool> {
+        if meta.path.is_ident("test") {
+            return Ok(true);
+        }
+        if meta.path.is_ident("all") || meta.path.is_ident("any") {
+            let mut predicates = Vec::new();
+            meta.parse_nested_meta(|nested| {
+                predicates.push(Self::cfg_test_only(nested)?);
+                Ok(())
+            })?;
+            return Ok(if meta.path.is_ident("all") {
+                predicates.iter().any(|test_only| *test_only)
+            } else {
+                !predicates.is_empty() && predicates.iter().all(|test_only| *test_only)
+            });
+        }
+        if meta.input.peek(syn::Token![=]) {
+            // `feature = "..."` 等键值条件
+            meta.value()?.parse::<syn::Lit>()?;
+        } else if meta.input.peek(syn::token::Paren) {
+            // `not(...)` 等其他谓词，跳过括号中的内容
+            let predicates;
+            syn::parenthesized!(predicates in meta.input);
+            predicates.parse::<proc_macro2::TokenStream>()?;
+        }
+        Ok(false)
+    }
+
+    fn type_name(ty: &Type) -> String {
+        match ty {
+            Type::Path(type_path) => type_path.path.segments.last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default(),
+            _ => ty.to_token_stream().to_string().replace(' ', ""),
+        }
     }
 }
 
 impl<'ast> Visit<'ast> for FunctionCollectorPass {
     fn visit_item_fn(&mut self, node: &'ast ItemFn) {
-        self.functions.push(node.clone());
+        self.push_function(node.clone(), false);
+        // 嵌套在函数体内的定义以外层函数作为作用域
+        let is_test = ParserPass::is_test_fn(&node.attrs);
+        self.test_depth += usize::from(is_test);
+        self.enclosing.push(self.qualified(&node.sig.ident.to_string()));
+        self.scope.push(node.sig.ident.to_string());
         visit::visit_item_fn(self, node);
+        self.scope.pop();
+        self.enclosing.pop();
+        self.test_depth -= usize::from(is_test);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        let is_test = Self::is_test_module(node);
+        self.test_depth += usize::from(is_test);
+        self.scope.push(node.ident.to_string());
+        visit::visit_item_mod(self, node);
+        self.scope.pop();
+        self.test_depth -= usize::from(is_test);
+    }
+
+    fn visit_item_static(&mut self, node: &'ast ItemStatic) {
+        match self.push_initializer(&node.ident.to_string(), &node.attrs, &node.expr) {
+            // 闭包已经以静态项命名收集，只继续访问其内部
+            Some(closure) => self.visit_expr(&closure.body),
+            None => visit::visit_item_static(self, node),
+        }
+    }
+
+    fn visit_item_const(&mut self, node: &'ast ItemConst) {
+        match self.push_initializer(&node.ident.to_string(), &node.attrs, &node.expr) {
+            Some(closure) => self.visit_expr(&closure.body),
+            None => visit::visit_item_const(self, node),
+        }
+    }
+
+    fn visit_local(&mut self, node: &'ast Local) {
+        let ident = match &node.pat {
+            Pat::Ident(pat) => Some(&pat.ident),
+            Pat::Type(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat) => Some(&pat.ident),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let (Some(ident), Some(init)) = (ident, &node.init)
+            && let Expr::Closure(closure) = &*init.expr
+        {
+            self.push_closure(&ident.to_string(), closure);
+        }
+        visit::visit_local(self, node);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast ExprCall) {
+        if let Expr::Path(func) = &*node.func
+            && let Some(segment) = func.path.segments.last()
+        {
+            for arg in &node.args {
+                if let Expr::Closure(closure) = arg {
+                    self.push_closure(&segment.ident.to_string(), closure);
+                }
+            }
+        }
+        visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast ExprMethodCall) {
+        for arg in &node.args {
+            if let Expr::Closure(closure) = arg {
+                self.push_closure(&node.method.to_string(), closure);
+            }
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast ItemImpl) {
+        self.scope.push(Self::type_name(&node.self_ty));
+        visit::visit_item_impl(self, node);
+        self.scope.pop();
     }
-} 
\ No newline at end of file
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.push_function(ItemFn {
+            attrs: node.attrs.clone(),
+            vis: node.vis.clone(),
+            sig: node.sig.clone(),
+            block: Box::new(node.block.clone()),
+        }, true);
+        self.enclosing.push(self.qualified(&node.sig.ident.to_string()));
+        self.scope.push(node.sig.ident.to_string());
+        visit::visit_impl_item_fn(self, node);
+        self.scope.pop();
+        self.enclosing.pop();
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast ItemTrait) {
+        self.scope.push(node.ident.to_string());
+        visit::visit_item_trait(self, node);
+        self.scope.pop();
+    }
+
+    // 只有带默认实现的 trait 方法才有函数体
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        let Some(block) = &node.default else {
+            return visit::visit_trait_item_fn(self, node);
+        };
+        self.push_function(ItemFn {
+            attrs: node.attrs.clone(),
+            vis: Visibility::Inherited,
+            sig: node.sig.clone(),
+            block: Box::new(block.clone()),
+        }, true);
+        self.enclosing.push(self.qualified(&node.sig.ident.to_string()));
+        self.scope.push(node.sig.ident.to_string());
+        visit::visit_trait_item_fn(self, node);
+        self.scope.pop();
+        self.enclosing.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_test(source: &str) -> bool {
+        FunctionCollectorPass::is_test_module(&syn::parse_str(source).expect("module parses"))
+    }
+
+    #[test]
+    fn cfg_test_modules() {
+        assert!(is_test("#[cfg(test)] mod tests {}"));
+        assert!(is_test("#[cfg(all(test, feature = \"slow\"))] mod tests {}"));
+        assert!(is_test("#[cfg(all(not(miri), test))] mod tests {}"));
+        assert!(is_test("#[cfg(any(test, all(test, unix)))] mod tests {}"));
+    }
+
+    #[test]
+    fn cfg_without_test_ident() {
+        assert!(!is_test("mod tests {}"));
+        assert!(!is_test("#[cfg(not(test))] mod real {}"));
+        assert!(!is_test("#[cfg(any(unix, all(test, not(miri))))] mod real {}"));
+        assert!(!is_test("#[cfg(any(test, feature = \"x\"))] mod real {}"));
+        assert!(!is_test("#[cfg(any())] mod never {}"));
+        assert!(!is_test("#[cfg(feature = \"test\")] mod gated {}"));
+        assert!(!is_test("#[cfg(feature = \"testing\")] mod gated {}"));
+        assert!(!is_test("#[cfg(target_os = \"linux\")] mod contest {}"));
+        assert!(!is_test("#[cfg_attr(test, derive(Debug))] mod attrs {}"));
+    }
+}
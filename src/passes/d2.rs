@@ -0,0 +1,152 @@
+use crate::passes::{StyledGraph, StyledNode};
+use crate::style::{D2Escape, DotEscape, LabelSanitizer, NodeStyle, MAX_LINE_WIDTH};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+// 输出 D2 图：每个函数一个容器，闭包和嵌套函数的容器嵌套在所在函数中；
+// 节点按类型（NodeType::kind）引用 classes 中定义的形状，颜色直接写在节点上
+pub struct D2RendererPass;
+
+impl D2RendererPass {
+    pub fn render(graph: &StyledGraph) -> String {
+        let mut out = String::from("direction: down\n");
+
+        let classes: BTreeMap<&str, &str> = graph.nodes.iter()
+            .map(|node| (node.kind.as_str(), node.shape.as_str()))
+            .collect();
+        if !classes.is_empty() {
+            out.push_str("classes: {\n");
+            for (kind, shape) in &classes {
+                let _ = writeln!(out, "  {}: {{{}}}", kind, Self::shape(shape));
+            }
+            out.push_str("}\n");
+        }
+
+        let mut function_nodes: BTreeMap<Option<usize>, Vec<&StyledNode>> = BTreeMap::new();
+        for node in &graph.nodes {
+            function_nodes.entry(node.function).or_default().push(node);
+        }
+        let parent_of = |function: usize| graph.parents.get(&function).copied()
+            .filter(|parent| function_nodes.contains_key(&Some(*parent)));
+        let mut children: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+        for function in function_nodes.keys().flatten() {
+            children.entry(parent_of(*function)).or_default().push(*function);
+        }
+
+        // 节点的完整路径：从最外层函数的容器开始，以 `.` 连接
+        let mut containers: HashMap<usize, String> = HashMap::new();
+        for function in function_nodes.keys().flatten() {
+            let mut path = vec![Self::container(graph, *function)];
+            let mut current = *function;
+            while let Some(parent) = parent_of(current) {
+                path.push(Self::container(graph, parent));
+                current = parent;
+            }
+            path.reverse();
+            containers.insert(*function, path.join("."));
+        }
+        let node_path = |node: &StyledNode| match node.function.and_then(|function| containers.get(&function)) {
+            Some(container) => format!("{}.n{}", container, node.id.index()),
+            None => format!("n{}", node.id.index()),
+        };
+
+        if let Some(nodes) = function_nodes.get(&None) {
+            for node in nodes {
+                Self::render_node(&mut out, node, &graph.theme.font.color, 0);
+            }
+        }
+        for function in children.get(&None).into_iter().flatten() {
+            Self::render_container(&mut out, graph, *function, &function_nodes, &children, 0);
+        }
+
+        let paths: HashMap<_, _> = graph.nodes.iter().map(|node| (node.id, node_path(node))).collect();
+        for edge in &graph.edges {
+            let (Some(from), Some(to)) = (paths.get(&edge.from), paths.get(&edge.to)) else {
+                continue;
+            };
+            let _ = write!(out, "{} -> {}", from, to);
+            if !edge.label.is_empty() {
+                let _ = write!(out, ": \"{}\"", D2Escape::text(&LabelSanitizer::truncate(&edge.label, MAX_LINE_WIDTH)));
+            }
+            let mut style = vec![format!("style.stroke: \"{}\"", NodeStyle::css_color(&edge.color))];
+            match edge.style.as_str() {
+                "dashed" | "dotted" => style.push("style.stroke-dash: 3".to_string()),
+                "bold" => style.push("style.stroke-width: 3".to_string()),
+                _ => {}
+            }
+            let _ = writeln!(out, " {{{}}}", style.join("; "));
+        }
+        out
+    }
+
+    fn render_container(
+        out: &mut String,
+        graph: &StyledGraph,
+        function: usize,
+        function_nodes: &BTreeMap<Option<usize>, Vec<&StyledNode>>,
+        children: &BTreeMap<Option<usize>, Vec<usize>>,
+        depth: usize,
+    ) {
+        let indent = "  ".repeat(depth);
+        let name = graph.names.get(&function).cloned().unwrap_or_else(|| format!("fn {}", function));
+        let _ = writeln!(out, "{}{}: \"{}\" {{", indent, Self::container(graph, function), D2Escape::text(&name));
+        for node in &function_nodes[&Some(function)] {
+            Self::render_node(out, node, &graph.theme.font.color, depth + 1);
+        }
+        for child in children.get(&Some(function)).into_iter().flatten() {
+            Self::render_container(out, graph, *child, function_nodes, children, depth + 1);
+        }
+        let _ = writeln!(out, "{}}}", indent);
+    }
+
+    fn render_node(out: &mut String, node: &StyledNode, font_color: &str, depth: usize) {
+        let label = LabelSanitizer::wrap(&node.label, MAX_LINE_WIDTH).join("\n");
+        let mut attributes = vec![
+            format!("class: {}", node.kind),
+            format!("style.fill: \"{}\"", NodeStyle::css_color(&node.fillcolor)),
+            format!("style.stroke: \"{}\"", NodeStyle::css_color(&node.color)),
+            format!("style.font-color: \"{}\"", NodeStyle::css_color(font_color)),
+        ];
+        if node.style.contains("dashed") {
+            attributes.push("style.stroke-dash: 3".to_string());
+        }
+        if node.style.contains("bold") {
+            attributes.push("style.stroke-width: 3".to_string());
+        }
+        if let Some(tooltip) = &node.tooltip {
+            attributes.push(format!("tooltip: \"{}\"", D2Escape::text(tooltip)));
+        }
+        let _ = writeln!(
+            out,
+            "{}n{}: \"{}\" {{{}}}",
+            "  ".repeat(depth),
+            node.id.index(),
+            D2Escape::text(&label),
+            attributes.join("; ")
+        );
+    }
+
+    // 函数容器的键：完整路径中的非字母数字字符替换为下划线
+    fn container(graph: &StyledGraph, function: usize) -> String {
+        match graph.names.get(&function) {
+            Some(name) => DotEscape::identifier(name),
+            None => format!("fn{}", function),
+        }
+    }
+
+    // DOT 形状对应的 D2 形状；D2 没有八边形，终止节点用双边框矩形表示
+    fn shape(shape: &str) -> &'static str {
+        match shape {
+            "oval" | "ellipse" => "shape: oval",
+            "diamond" => "shape: diamond",
+            "hexagon" => "shape: hexagon",
+            "parallelogram" => "shape: parallelogram",
+            "octagon" => "shape: rectangle; style.double-border: true",
+            "note" => "shape: page",
+            "folder" => "shape: package",
+            "component" => "shape: stored_data",
+            _ => "shape: rectangle",
+        }
+    }
+
+}
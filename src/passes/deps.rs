@@ -0,0 +1,253 @@
+use crate::graph::{EdgeKind, FlowGraph, NodeType};
+use crate::passes::{DotRendererPass, StylerPass};
+use crate::style::NodeTheme;
+use anyhow::{Context, Result, bail};
+use petgraph::graph::NodeIndex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::process::Command;
+
+// `cargo metadata --format-version 1` 输出中用到的部分
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    resolve: Option<Resolve>,
+    workspace_members: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    id: String,
+    name: String,
+    version: String,
+    // SPDX 许可证表达式，没有声明时为 null
+    license: Option<String>,
+    dependencies: Vec<Dependency>,
+}
+
+// Cargo.toml 中声明的依赖
+#[derive(Deserialize)]
+struct Dependency {
+    name: String,
+    kind: Option<String>,
+    optional: bool,
+}
+
+#[derive(Deserialize)]
+struct Resolve {
+    nodes: Vec<ResolveNode>,
+}
+
+// 依赖解析后的包及其实际使用的依赖
+#[derive(Deserialize)]
+struct ResolveNode {
+    id: String,
+    deps: Vec<NodeDep>,
+}
+
+#[derive(Deserialize)]
+struct NodeDep {
+    pkg: String,
+    dep_kinds: Vec<DepKind>,
+}
+
+#[derive(Deserialize)]
+struct DepKind {
+    // null 为普通依赖，否则为 "dev" 或 "build"
+    kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DependencyOptions {
+    // 从 workspace 成员出发最多展开的层数，None 表示不限制
+    pub depth: Option<usize>,
+    // 同一个包的多个版本合并为一个节点
+    pub dedupe_versions: bool,
+    // 解析依赖时启用的 feature
+    pub features: Vec<String>,
+    // 需要标出的许可证（SPDX 标识符，如 GPL-3.0），许可证表达式中出现其中之一即标出
+    pub deny_licenses: Vec<String>,
+}
+
+// crate 依赖图以及需要标出的包
+pub struct DependencyGraph {
+    pub graph: FlowGraph,
+    // 同一个包出现了多个不兼容的主版本（0.x 按次版本区分）
+    pub duplicates: HashSet<NodeIndex>,
+    // 许可证命中 --deny-license 的包，值为包的标签
+    pub denied: BTreeMap<NodeIndex, String>,
+}
+
+// 图中的一个包节点，合并版本时包含同名包的所有版本
+#[derive(Default)]
+struct PackageNode {
+    name: String,
+    versions: BTreeSet<String>,
+    licenses: BTreeSet<String>,
+    is_member: bool,
+}
+
+// 一条依赖边：依赖种类（dev/build，普通依赖不记录）以及是否为可选依赖
+#[derive(Default)]
+struct DependencyEdge {
+    kinds: BTreeSet<String>,
+    optional: bool,
+}
+
+impl DependencyEdge {
+    fn kind(&self) -> EdgeKind {
+        EdgeKind::Dependency { kinds: self.kinds.iter().cloned().collect(), optional: self.optional }
+    }
+}
+
+// 通过 `cargo metadata` 构建 crate 依赖图，节点为包，workspace 成员加粗显示
+pub struct DependencyGraphPass;
+
+impl DependencyGraphPass {
+    pub fn build(crate_root: &Path, options: &DependencyOptions) -> Result<DependencyGraph> {
+        let metadata = Self::metadata(crate_root, &options.features)?;
+        Ok(Self::from_metadata(&metadata, options))
+    }
+
+    fn metadata(crate_root: &Path, features: &[String]) -> Result<Metadata> {
+        let mut command = Command::new("cargo");
+        command.current_dir(crate_root).args(["metadata", "--format-version", "1"]);
+        if !features.is_empty() {
+            command.args(["--features", &features.join(",")]);
+        }
+        let output = command.output().context("Failed to run cargo metadata")?;
+        if !output.status.success() {
+            bail!("cargo metadata failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        serde_json::from_slice(&output.stdout).context("Failed to parse cargo metadata output")
+    }
+
+    fn from_metadata(metadata: &Metadata, options: &DependencyOptions) -> DependencyGraph {
+        let packages: HashMap<&str, &Package> = metadata.packages.iter().map(|package| (package.id.as_str(), package)).collect();
+        let resolved: HashMap<&str, &ResolveNode> = metadata.resolve.iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .map(|node| (node.id.as_str(), node))
+            .collect();
+        let members: BTreeSet<&str> = metadata.workspace_members.iter().map(String::as_str).collect();
+        let key = |package: &Package| {
+            if options.dedupe_versions { package.name.clone() } else { package.id.clone() }
+        };
+
+        // 从 workspace 成员开始按层展开，超过深度限制的包不再展开其依赖
+        let mut package_nodes: BTreeMap<String, PackageNode> = BTreeMap::new();
+        let mut edges: BTreeMap<(String, String), DependencyEdge> = BTreeMap::new();
+        let mut visited: BTreeSet<&str> = BTreeSet::new();
+        let mut queue: VecDeque<(&str, usize)> = members.iter().map(|id| (*id, 0)).collect();
+        while let Some((id, depth)) = queue.pop_front() {
+            let Some(package) = packages.get(id) else {
+                continue;
+            };
+            if !visited.insert(id) {
+                continue;
+            }
+            let node = package_nodes.entry(key(package)).or_default();
+            node.name = package.name.clone();
+            node.versions.insert(package.version.clone());
+            node.licenses.extend(package.license.clone());
+            node.is_member |= members.contains(id);
+
+            if options.depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            for dep in resolved.get(id).map(|node| node.deps.as_slice()).unwrap_or_default() {
+                let Some(target) = packages.get(dep.pkg.as_str()) else {
+                    continue;
+                };
+                let edge = edges.entry((key(package), key(target))).or_default();
+                for dep_kind in &dep.dep_kinds {
+                    let kind = dep_kind.kind.as_deref();
+                    edge.kinds.extend(kind.map(str::to_string));
+                    edge.optional |= package.dependencies.iter()
+                        .any(|declared| declared.name == target.name && declared.kind.as_deref() == kind && declared.optional);
+                }
+                queue.push_back((dep.pkg.as_str(), depth + 1));
+            }
+        }
+
+        // 同名包的版本按 semver 兼容性分组，出现多组时为重复的主版本
+        let mut majors: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        for node in package_nodes.values() {
+            majors.entry(&node.name).or_default().extend(node.versions.iter().map(|version| Self::major(version)));
+        }
+
+        let mut deps = DependencyGraph { graph: FlowGraph::new(), duplicates: HashSet::new(), denied: BTreeMap::new() };
+        let mut nodes: HashMap<&String, NodeIndex> = HashMap::new();
+        for (key, node) in &package_nodes {
+            let versions: Vec<String> = node.versions.iter().map(|version| format!("v{}", version)).collect();
+            let license = if node.licenses.is_empty() {
+                "未声明许可证".to_string()
+            } else {
+                node.licenses.iter().cloned().collect::<Vec<_>>().join(" / ")
+            };
+            let duplicate = majors.get(node.name.as_str()).is_some_and(|majors| majors.len() > 1);
+            let mut label = format!("{} {}\n{}", node.name, versions.join(", "), license);
+            if duplicate {
+                label.push_str("\n重复的主版本");
+            }
+            let id = deps.graph.add_node(NodeType::Package(label.clone(), node.is_member));
+            if duplicate {
+                deps.duplicates.insert(id);
+            }
+            if node.licenses.iter().any(|license| Self::denies(license, &options.deny_licenses)) {
+                deps.denied.insert(id, label.lines().next().unwrap_or_default().to_string());
+            }
+            nodes.insert(key, id);
+        }
+        for ((from, to), edge) in &edges {
+            // 合并版本后，一个版本依赖同名包的另一个版本（semver trick）会成为自环，不显示
+            if from == to {
+                continue;
+            }
+            if let (Some(&from), Some(&to)) = (nodes.get(from), nodes.get(to)) {
+                deps.graph.add_edge(from, to, edge.kind());
+            }
+        }
+        deps
+    }
+
+    // semver 兼容的版本范围：1.2.3 -> 1，0.3.1 -> 0.3，0.0.4 -> 0.0.4
+    fn major(version: &str) -> String {
+        let core = version.split(['-', '+']).next().unwrap_or(version);
+        let parts: Vec<&str> = core.split('.').collect();
+        let significant = parts.iter().position(|part| *part != "0").map_or(parts.len(), |i| i + 1);
+        parts[..significant.min(parts.len())].join(".")
+    }
+
+    // 许可证表达式中的某个标识符与拒绝列表中的一项相同，或是它的 -only/-or-later/+ 变体（不区分大小写）
+    fn denies(license: &str, denied: &[String]) -> bool {
+        license
+            .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '/'))
+            .filter(|id| !id.is_empty() && !matches!(*id, "OR" | "AND" | "WITH"))
+            .any(|id| {
+                let id = id.to_ascii_lowercase();
+                denied.iter().any(|denied| {
+                    let denied = denied.to_ascii_lowercase();
+                    id == denied || id.strip_prefix(&denied).is_some_and(|rest| matches!(rest, "+" | "-only" | "-or-later"))
+                })
+            })
+    }
+
+    // 重复主版本和被拒绝许可证的包按主题中的 duplicate_package、denied_package 着色，后者优先
+    pub fn to_dot(deps: &DependencyGraph) -> String {
+        let theme = &deps.graph.config().theme;
+        let mut styled = StylerPass::apply_style(&deps.graph);
+        for node in &mut styled.nodes {
+            let key = if deps.denied.contains_key(&node.id) {
+                "denied_package"
+            } else if deps.duplicates.contains(&node.id) {
+                "duplicate_package"
+            } else {
+                continue;
+            };
+            let NodeTheme { shape, style, fillcolor, color } = theme.node(key);
+            (node.shape, node.style, node.fillcolor, node.color) = (shape, style, fillcolor, color);
+        }
+        DotRendererPass::render(&styled)
+    }
+}
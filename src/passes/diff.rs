@@ -0,0 +1,157 @@
+use crate::graph::{BlockGranularity, EdgeKind, FlowGraph, FunctionInfo, GraphArena, GraphConfig, NodeType};
+use crate::passes::{DotRendererPass, FunctionSnapshot, StylerPass};
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Removed,
+    // 两个版本中都有、但进出的边发生了变化
+    Changed,
+}
+
+// 两个版本合并后的控制流图，只包含发生变化的函数；没有状态的节点和边两个版本相同
+pub struct GraphDiff {
+    pub graph: FlowGraph,
+    pub nodes: HashMap<NodeIndex, DiffStatus>,
+    pub edges: HashMap<(NodeIndex, NodeIndex), DiffStatus>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+}
+
+pub struct DiffPass;
+
+impl DiffPass {
+    // 同名函数的两个版本画在一个分组中：节点按标签配对（同名标签按出现顺序），
+    // 只在 head 中的节点为新增，只在 base 中的节点为删除，删除的边同样保留在图中
+    pub fn compare(base: &[FunctionSnapshot], head: &[FunctionSnapshot], config: &GraphConfig) -> GraphDiff {
+        // 节点编号不能因为合并基本块而改变
+        let config = GraphConfig { block_granularity: BlockGranularity::Statement, include_tests: true, ..config.clone() };
+        let mut diff = GraphDiff { graph: FlowGraph::with_config(config), nodes: HashMap::new(), edges: HashMap::new() };
+
+        let mut functions: BTreeMap<&str, (Option<&FunctionSnapshot>, Option<&FunctionSnapshot>)> = BTreeMap::new();
+        for func in base {
+            functions.entry(&func.name).or_default().0 = Some(func);
+        }
+        for func in head {
+            functions.entry(&func.name).or_default().1 = Some(func);
+        }
+        for (name, (before, after)) in functions {
+            Self::append_function(&mut diff, name, before, after);
+        }
+        diff
+    }
+
+    fn append_function(diff: &mut GraphDiff, name: &str, before: Option<&FunctionSnapshot>, after: Option<&FunctionSnapshot>) {
+        let empty = Vec::new();
+        let before_nodes = before.map_or(&empty, |func| &func.nodes);
+        let after_nodes = after.map_or(&empty, |func| &func.nodes);
+
+        // 合并后的节点：head 的全部节点，之后是 base 中没有配对的节点
+        let mut unpaired: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, node) in after_nodes.iter().enumerate().rev() {
+            unpaired.entry(Self::key(node)).or_default().push(i);
+        }
+        let mut nodes = after_nodes.clone();
+        let mut statuses: Vec<Option<DiffStatus>> = vec![None; nodes.len()];
+        let mut paired = vec![false; nodes.len()];
+        let before_ids: Vec<usize> = before_nodes.iter()
+            .map(|node| match unpaired.get_mut(&Self::key(node)).and_then(Vec::pop) {
+                Some(id) => {
+                    paired[id] = true;
+                    id
+                }
+                None => {
+                    nodes.push(node.clone());
+                    statuses.push(Some(DiffStatus::Removed));
+                    nodes.len() - 1
+                }
+            })
+            .collect();
+        for (status, paired) in statuses.iter_mut().zip(&paired) {
+            if !paired {
+                *status = Some(DiffStatus::Added);
+            }
+        }
+
+        // 边的多重集合：正数只在 head 中，负数只在 base 中
+        let mut edges: BTreeMap<(usize, usize, EdgeKind), isize> = BTreeMap::new();
+        for (from, to, kind) in after.map_or(&[][..], |func| &func.edges) {
+            *edges.entry((*from, *to, kind.clone())).or_insert(0) += 1;
+        }
+        for (from, to, kind) in before.map_or(&[][..], |func| &func.edges) {
+            *edges.entry((before_ids[*from], before_ids[*to], kind.clone())).or_insert(0) -= 1;
+        }
+
+        let mut edge_statuses = Vec::new();
+        for ((from, to, kind), count) in &edges {
+            let status = match count {
+                0 => None,
+                count if *count > 0 => Some(DiffStatus::Added),
+                _ => Some(DiffStatus::Removed),
+            };
+            if status.is_some() {
+                for id in [*from, *to] {
+                    statuses[id].get_or_insert(DiffStatus::Changed);
+                }
+            }
+            edge_statuses.push((*from, *to, kind.clone(), status));
+        }
+        if statuses.iter().all(Option::is_none) {
+            return;
+        }
+
+        let mut arena = GraphArena::new();
+        for node in nodes {
+            arena.add_node(node);
+        }
+        for (from, to, kind, _) in &edge_statuses {
+            arena.add_edge(NodeIndex::new(*from), NodeIndex::new(*to), kind.clone());
+        }
+        let info = FunctionInfo { name: name.to_string(), ..FunctionInfo::default() };
+        let offset = diff.graph.append_arena(&mut arena, info);
+
+        let index = |id: usize| NodeIndex::new(id + offset);
+        diff.nodes.extend(statuses.into_iter().enumerate()
+            .filter_map(|(id, status)| Some((index(id), status?))));
+        diff.edges.extend(edge_statuses.into_iter()
+            .filter_map(|(from, to, _, status)| Some(((index(from), index(to)), status?))));
+    }
+
+    // 与 verify 相同，节点按去掉多余空白的标签比较，与代码格式无关
+    fn key(node: &NodeType) -> String {
+        node.label().split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    // 新增为绿色、删除为红色（虚线）、变化为黄色，颜色取自主题
+    pub fn to_dot(diff: &GraphDiff) -> String {
+        let theme = &diff.graph.config().theme;
+        let color = |status: DiffStatus| match status {
+            DiffStatus::Added => theme.added_color.clone(),
+            DiffStatus::Removed => theme.removed_color.clone(),
+            DiffStatus::Changed => theme.changed_color.clone(),
+        };
+
+        let mut styled = StylerPass::apply_style(&diff.graph);
+        for node in &mut styled.nodes {
+            if let Some(&status) = diff.nodes.get(&node.id) {
+                node.fillcolor = color(status);
+                if status == DiffStatus::Removed {
+                    node.style = format!("{},dashed", node.style);
+                }
+            }
+        }
+        for edge in &mut styled.edges {
+            if let Some(&status) = diff.edges.get(&(edge.from, edge.to)) {
+                edge.color = color(status);
+                edge.style = if status == DiffStatus::Removed { "dashed,bold" } else { "bold" }.to_string();
+            }
+        }
+        DotRendererPass::render(&styled)
+    }
+}
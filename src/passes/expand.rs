@@ -0,0 +1,68 @@
+use crate::passes::ParserPass;
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+use syn::{File, Item};
+
+// 通过 `cargo expand` 展开宏之后再分析，展开结果不带原始位置信息，
+// 节点文本和闭包行号都来自展开后的代码
+pub struct MacroExpansionPass;
+
+impl MacroExpansionPass {
+    // 展开单个源文件对应的模块，返回展开后的语法树和源码（节点文本按语法树中的位置从源码中截取）；
+    // 文件中 `mod name;` 声明的子模块由各自的文件单独展开，这里去掉
+    pub fn expand_file(path: &Path, original: &File) -> Result<(File, String)> {
+        let path = path.canonicalize().with_context(|| format!("Failed to resolve {}", path.display()))?;
+        let crate_root = path.ancestors()
+            .skip(1)
+            .find(|dir| dir.join("Cargo.toml").is_file())
+            .with_context(|| format!("No Cargo.toml found above {}", path.display()))?;
+        let relative = path.strip_prefix(crate_root)?.to_string_lossy().replace('\\', "/");
+
+        let mut command = Command::new("cargo");
+        command.current_dir(crate_root).args(["expand", "--color", "never"]);
+        // 选择文件所属的编译目标：src/bin 下的文件是同名二进制，有 lib.rs 时 main.rs 以外的文件属于库
+        if let Some(bin) = relative.strip_prefix("src/bin/") {
+            let name = bin.split('/').next().unwrap_or(bin).trim_end_matches(".rs");
+            command.args(["--bin", name]);
+        } else if relative != "src/main.rs" && crate_root.join("src/lib.rs").is_file() {
+            command.arg("--lib");
+        }
+        let module = ParserPass::module_path(&path);
+        if let Some(module) = module.strip_prefix("crate::") {
+            command.arg(module);
+        }
+
+        let output = command.output().context("Failed to run cargo expand (install it with `cargo install cargo-expand`)")?;
+        if !output.status.success() {
+            bail!("cargo expand failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let expanded = String::from_utf8(output.stdout)?;
+
+        let ast = Self::strip_external_modules(ParserPass::parse(&expanded)?, original, &module);
+        Ok((ast, expanded))
+    }
+
+    // 只展开子模块时输出的是 `mod name { ... }`，取出其中的内容；
+    // 再去掉原文件中以 `mod name;` 声明、位于其他文件中的子模块
+    fn strip_external_modules(mut ast: File, original: &File, module: &str) -> File {
+        let name = module.rsplit("::").next().unwrap_or(module);
+        if let [Item::Mod(item_mod)] = ast.items.as_slice()
+            && module != "crate"
+            && item_mod.ident == name
+            && let Some((_, items)) = &item_mod.content
+        {
+            ast.items = items.clone();
+        }
+
+        let external: HashSet<String> = original.items.iter()
+            .filter_map(|item| match item {
+                Item::Mod(item_mod) if item_mod.content.is_none() => Some(item_mod.ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        ast.items.retain(|item| !matches!(item, Item::Mod(item_mod) if external.contains(&item_mod.ident.to_string())));
+        ast
+    }
+}
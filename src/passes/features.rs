@@ -0,0 +1,232 @@
+use crate::style::DotEscape;
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Arm, Attribute, Expr, File, ImplItemFn, Item, ItemFn, ItemImpl, ItemMod, LitStr, Stmt};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FeatureGate {
+    pub feature: String,
+    // cfg(not(feature = ...))：关闭该 feature 时才编译
+    pub negated: bool,
+    pub target: String,
+    pub kind: String,
+}
+
+pub struct FeatureMapPass {
+    gates: Vec<FeatureGate>,
+    scope: Vec<String>,
+}
+
+impl FeatureMapPass {
+    pub fn collect(file: &File, module_path: &str) -> Vec<FeatureGate> {
+        let mut pass = Self {
+            gates: Vec::new(),
+            scope: module_path.split("::").map(str::to_string).collect(),
+        };
+        pass.visit_file(file);
+        pass.gates
+    }
+
+    // 解析 cfg 属性中出现的所有 feature，返回 (feature, 是否在 not 中)
+    pub fn cfg_features(attrs: &[Attribute]) -> Vec<(String, bool)> {
+        let mut features = Vec::new();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("cfg")) {
+            let _ = attr.parse_nested_meta(|meta| Self::parse_cfg(meta, false, &mut features));
+        }
+        features
+    }
+
+    fn parse_cfg(meta: ParseNestedMeta, negated: bool, features: &mut Vec<(String, bool)>) -> syn::Result<()> {
+        if meta.path.is_ident("feature") {
+            let feature: LitStr = meta.value()?.parse()?;
+            features.push((feature.value(), negated));
+        } else if meta.input.peek(syn::Token![=]) {
+            // target_os = "linux" 之类的其他条件
+            let _: syn::Lit = meta.value()?.parse()?;
+        } else if meta.input.peek(syn::token::Paren) {
+            let negated = negated ^ meta.path.is_ident("not");
+            meta.parse_nested_meta(|nested| Self::parse_cfg(nested, negated, features))?;
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, attrs: &[Attribute], target: String, kind: &str) {
+        for (feature, negated) in Self::cfg_features(attrs) {
+            self.gates.push(FeatureGate {
+                feature,
+                negated,
+                target: target.clone(),
+                kind: kind.to_string(),
+            });
+        }
+    }
+
+    fn qualified(&self, name: &str) -> String {
+        format!("{}::{}", self.scope.join("::"), name)
+    }
+
+    fn block_target(&self, line: usize) -> String {
+        format!("{} (line {})", self.scope.join("::"), line)
+    }
+
+    fn expr_attrs(expr: &Expr) -> &[Attribute] {
+        match expr {
+            Expr::Block(e) => &e.attrs,
+            Expr::Call(e) => &e.attrs,
+            Expr::MethodCall(e) => &e.attrs,
+            Expr::Macro(e) => &e.attrs,
+            Expr::If(e) => &e.attrs,
+            Expr::Match(e) => &e.attrs,
+            Expr::ForLoop(e) => &e.attrs,
+            Expr::While(e) => &e.attrs,
+            Expr::Loop(e) => &e.attrs,
+            Expr::Unsafe(e) => &e.attrs,
+            Expr::Assign(e) => &e.attrs,
+            _ => &[],
+        }
+    }
+
+    pub fn render_dot(gates: &[FeatureGate], declared: &[String]) -> String {
+        let features: BTreeSet<&str> = declared.iter().map(String::as_str)
+            .chain(gates.iter().map(|gate| gate.feature.as_str()))
+            .collect();
+        let targets: BTreeSet<(&str, &str)> = gates.iter()
+            .map(|gate| (gate.target.as_str(), gate.kind.as_str()))
+            .collect();
+
+        let mut dot = String::from("digraph Features {\n");
+        dot.push_str("    graph [rankdir=LR; nodesep=0.3; ranksep=2.0];\n");
+        dot.push_str("    node [fontname=\"Arial\"; fontsize=10; style=filled];\n");
+        dot.push_str("    edge [fontname=\"Arial\"; fontsize=9];\n\n");
+
+        // 左侧为 feature，右侧为被 feature 控制的代码
+        dot.push_str("    subgraph features {\n        rank=same;\n");
+        for feature in &features {
+            let _ = writeln!(
+                dot,
+                "        \"feature:{}\" [label=\"{}\", shape=\"component\", fillcolor=\"lightyellow\"];",
+                Self::escape(feature), Self::escape(feature)
+            );
+        }
+        dot.push_str("    }\n\n    subgraph targets {\n        rank=same;\n");
+        for (target, kind) in &targets {
+            let _ = writeln!(
+                dot,
+                "        \"item:{}\" [label=\"{}\\n({})\", shape=\"box\", fillcolor=\"lightblue\"];",
+                Self::escape(target), Self::escape(target), kind
+            );
+        }
+        dot.push_str("    }\n\n");
+
+        let mut seen = BTreeSet::new();
+        for gate in gates {
+            if !seen.insert((&gate.feature, &gate.target, gate.negated)) {
+                continue;
+            }
+            let style = if gate.negated {
+                "style=\"dashed\", color=\"red\", label=\"not\""
+            } else {
+                "color=\"black\""
+            };
+            let _ = writeln!(
+                dot,
+                "    \"feature:{}\" -> \"item:{}\" [{}];",
+                Self::escape(&gate.feature), Self::escape(&gate.target), style
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    // Cargo.toml 中声明的 feature
+    pub fn declared_features(manifest: &str) -> Result<Vec<String>> {
+        let table: toml::Table = manifest.parse()?;
+        Ok(table.get("features")
+            .and_then(|features| features.as_table())
+            .map(|features| features.keys().filter(|key| *key != "default").cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn escape(text: &str) -> String {
+        DotEscape::quoted(text)
+    }
+}
+
+impl<'ast> Visit<'ast> for FeatureMapPass {
+    fn visit_item(&mut self, node: &'ast Item) {
+        let (attrs, name, kind): (&[Attribute], Option<String>, &str) = match node {
+            Item::Struct(item) => (&item.attrs, Some(item.ident.to_string()), "struct"),
+            Item::Enum(item) => (&item.attrs, Some(item.ident.to_string()), "enum"),
+            Item::Trait(item) => (&item.attrs, Some(item.ident.to_string()), "trait"),
+            Item::Const(item) => (&item.attrs, Some(item.ident.to_string()), "const"),
+            Item::Static(item) => (&item.attrs, Some(item.ident.to_string()), "static"),
+            Item::Use(item) => (&item.attrs, None, "use"),
+            _ => (&[], None, ""),
+        };
+        if !attrs.is_empty() {
+            let target = match name {
+                Some(name) => self.qualified(&name),
+                None => self.block_target(node.span().start().line),
+            };
+            self.record(attrs, target, kind);
+        }
+        visit::visit_item(self, node);
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        let name = node.sig.ident.to_string();
+        self.record(&node.attrs, self.qualified(&name), "fn");
+        self.scope.push(name);
+        visit::visit_item_fn(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        let name = node.ident.to_string();
+        self.record(&node.attrs, self.qualified(&name), "mod");
+        self.scope.push(name);
+        visit::visit_item_mod(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast ItemImpl) {
+        let ty = quote::ToTokens::to_token_stream(&*node.self_ty).to_string().replace(' ', "");
+        self.record(&node.attrs, format!("impl {}", self.qualified(&ty)), "impl");
+        self.scope.push(ty);
+        visit::visit_item_impl(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        let name = node.sig.ident.to_string();
+        self.record(&node.attrs, self.qualified(&name), "fn");
+        self.scope.push(name);
+        visit::visit_impl_item_fn(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_stmt(&mut self, node: &'ast Stmt) {
+        let attrs = match node {
+            Stmt::Local(local) => &local.attrs[..],
+            Stmt::Macro(mac) => &mac.attrs[..],
+            Stmt::Expr(expr, _) => Self::expr_attrs(expr),
+            Stmt::Item(_) => &[],
+        };
+        if !attrs.is_empty() {
+            let target = self.block_target(node.span().start().line);
+            self.record(attrs, target, "block");
+        }
+        visit::visit_stmt(self, node);
+    }
+
+    fn visit_arm(&mut self, node: &'ast Arm) {
+        let target = self.block_target(node.span().start().line);
+        self.record(&node.attrs, target, "match arm");
+        visit::visit_arm(self, node);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::graph::{BlockGranularity, FlowGraph};
+use crate::style::LabelSanitizer;
+use std::fmt::Write;
+
+// GraphML 导出，供 yEd、Gephi、Cytoscape 等工具交互浏览和自定义布局。
+// 节点带 label、kind、file、function、line、column 属性，边带 label、kind 属性
+pub struct GraphMlRendererPass;
+
+impl GraphMlRendererPass {
+    pub fn render(graph: &FlowGraph) -> String {
+        // 与其他渲染方式一致，merged 粒度下先合并连续的基本块
+        let merged;
+        let graph = if graph.config().block_granularity == BlockGranularity::Merged {
+            merged = graph.merged_blocks();
+            &merged
+        } else {
+            graph
+        };
+        let groups = graph.function_groups();
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\" ");
+        out.push_str("xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" ");
+        out.push_str("xsi:schemaLocation=\"http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd\">\n");
+        out.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+        out.push_str("  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n");
+        out.push_str("  <key id=\"file\" for=\"node\" attr.name=\"file\" attr.type=\"string\"/>\n");
+        out.push_str("  <key id=\"function\" for=\"node\" attr.name=\"function\" attr.type=\"string\"/>\n");
+        out.push_str("  <key id=\"line\" for=\"node\" attr.name=\"line\" attr.type=\"int\"/>\n");
+        out.push_str("  <key id=\"column\" for=\"node\" attr.name=\"column\" attr.type=\"int\"/>\n");
+        out.push_str("  <key id=\"edge_label\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n");
+        out.push_str("  <key id=\"edge_kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n");
+        out.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+
+        for (id, node) in graph.nodes() {
+            let info = groups.get(&id).and_then(|&function| graph.function_info(function));
+            let _ = writeln!(out, "    <node id=\"n{}\">", id.index());
+            Self::data(&mut out, "label", &node.label());
+            Self::data(&mut out, "kind", node.kind());
+            if let Some(file) = info.and_then(|info| info.file.as_deref()) {
+                Self::data(&mut out, "file", file);
+            }
+            if let Some(info) = info {
+                Self::data(&mut out, "function", &info.name);
+            }
+            if let Some(span) = graph.node_span(id) {
+                Self::data(&mut out, "line", &span.line.to_string());
+                Self::data(&mut out, "column", &span.column.to_string());
+            }
+            out.push_str("    </node>\n");
+        }
+
+        for (i, (from, to, kind)) in graph.edges().enumerate() {
+            let _ = writeln!(out, "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">", i, from.index(), to.index());
+            let label = kind.label(&graph.config().labels);
+            if !label.is_empty() {
+                Self::data(&mut out, "edge_label", &label);
+            }
+            Self::data(&mut out, "edge_kind", kind.kind());
+            out.push_str("    </edge>\n");
+        }
+
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    fn data(out: &mut String, key: &str, value: &str) {
+        let _ = writeln!(out, "      <data key=\"{}\">{}</data>", key, Self::escape(value));
+    }
+
+    // XML 文本转义；控制字符在 XML 1.0 中不合法，sanitize 已替换为 U+FFFD
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in LabelSanitizer::sanitize(text).chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
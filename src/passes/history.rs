@@ -0,0 +1,196 @@
+use crate::graph::{EdgeKind, FlowGraph, FunctionInfo, GraphArena, GraphConfig, Labels, NodeType};
+use crate::passes::{FunctionStats, StatsPass};
+use anyhow::{Context, Result};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 历史记录保存在 crate 根目录下的 .cargo-graph/history/<commit>.json
+const HISTORY_DIR: &str = ".cargo-graph/history";
+
+// 一个函数的控制流图，边使用函数内的局部节点编号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionSnapshot {
+    pub name: String,
+    pub stats: FunctionStats,
+    pub nodes: Vec<NodeType>,
+    #[serde(deserialize_with = "deserialize_edges")]
+    pub edges: Vec<(usize, usize, EdgeKind)>,
+}
+
+// 旧版本记录中的边权是中文显示文本，读取时转换为 EdgeKind
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEdgeKind {
+    Kind(EdgeKind),
+    Label(String),
+}
+
+fn deserialize_edges<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(usize, usize, EdgeKind)>, D::Error> {
+    let labels = Labels::zh();
+    let edges: Vec<(usize, usize, StoredEdgeKind)> = Vec::deserialize(deserializer)?;
+    Ok(edges.into_iter()
+        .map(|(from, to, kind)| match kind {
+            StoredEdgeKind::Kind(kind) => (from, to, kind),
+            StoredEdgeKind::Label(label) => (from, to, EdgeKind::parse(&label, &labels)),
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub commit: String,
+    // 提交时间（Unix 时间戳），用于排序
+    pub timestamp: i64,
+    // 提交日期（ISO 8601），只用于显示
+    pub date: String,
+    pub functions: Vec<FunctionSnapshot>,
+}
+
+impl Snapshot {
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+}
+
+pub struct HistoryPass;
+
+impl HistoryPass {
+    pub fn snapshot(graph: &FlowGraph) -> Vec<FunctionSnapshot> {
+        let stats: HashMap<String, FunctionStats> = StatsPass::collect(graph)
+            .into_iter()
+            .map(|stats| (stats.name.clone(), stats))
+            .collect();
+
+        let mut owned: HashMap<usize, Vec<NodeIndex>> = HashMap::new();
+        for node_id in graph.graph.node_indices() {
+            if let Some(function) = graph.function_of(node_id) {
+                owned.entry(function).or_default().push(node_id);
+            }
+        }
+
+        let mut functions = Vec::new();
+        let mut function = 0;
+        while let Some(info) = graph.function_info(function) {
+            let nodes = owned.remove(&function).unwrap_or_default();
+            function += 1;
+            // 被过滤掉的测试函数没有统计数据，不记录
+            let Some(stats) = stats.get(&info.name) else {
+                continue;
+            };
+            let local: HashMap<NodeIndex, usize> = nodes.iter()
+                .enumerate()
+                .map(|(i, &node_id)| (node_id, i))
+                .collect();
+            let edges = nodes.iter()
+                .flat_map(|&node_id| graph.graph.edges(node_id))
+                .filter_map(|edge| {
+                    Some((local[&edge.source()], *local.get(&edge.target())?, edge.weight().clone()))
+                })
+                .collect();
+            functions.push(FunctionSnapshot {
+                name: info.name.clone(),
+                stats: stats.clone(),
+                nodes: nodes.iter().map(|&node_id| graph.graph[node_id].clone()).collect(),
+                edges,
+            });
+        }
+
+        functions
+    }
+
+    pub fn save(root: &Path, snapshot: &Snapshot) -> Result<PathBuf> {
+        let dir = root.join(HISTORY_DIR);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", snapshot.commit));
+        fs::write(&path, serde_json::to_string(snapshot)?)?;
+        Ok(path)
+    }
+
+    // 读取所有记录，按提交时间排序
+    pub fn load(root: &Path) -> Result<Vec<Snapshot>> {
+        let dir = root.join(HISTORY_DIR);
+        let mut snapshots = Vec::new();
+        if !dir.exists() {
+            return Ok(snapshots);
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let content = fs::read_to_string(&path)?;
+            let snapshot: Snapshot = serde_json::from_str(&content)
+                .with_context(|| format!("Invalid history record: {}", path.display()))?;
+            snapshots.push(snapshot);
+        }
+        snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+        Ok(snapshots)
+    }
+
+    // 匹配到的函数在每次提交中的版本，过滤规则与 --function 相同
+    pub fn versions<'a>(snapshots: &'a [Snapshot], function: &str) -> Vec<(&'a Snapshot, &'a FunctionSnapshot)> {
+        let filter = GraphConfig {
+            function_filter: vec![function.to_string()],
+            ..GraphConfig::default()
+        };
+        snapshots.iter()
+            .flat_map(|snapshot| {
+                snapshot.functions.iter()
+                    .filter(|func| filter.matches_function(&func.name))
+                    .map(move |func| (snapshot, func))
+            })
+            .collect()
+    }
+
+    // 把各个版本放进同一张图，每个版本一个函数分组，开始节点标明提交和复杂度
+    pub fn evolution_graph(versions: &[(&Snapshot, &FunctionSnapshot)]) -> FlowGraph {
+        let mut graph = FlowGraph::with_config(GraphConfig { include_tests: true, ..GraphConfig::default() });
+        let mut arena = GraphArena::new();
+
+        for (snapshot, func) in versions {
+            let title = format!(
+                "{} @ {} (complexity {})",
+                func.name,
+                snapshot.short_commit(),
+                func.stats.complexity
+            );
+            let ids: Vec<NodeIndex> = func.nodes.iter()
+                .map(|node| match node {
+                    NodeType::Start(_, is_test) => arena.add_node(NodeType::Start(title.clone(), *is_test)),
+                    node => arena.add_node(node.clone()),
+                })
+                .collect();
+            for (from, to, kind) in &func.edges {
+                arena.add_edge(ids[*from], ids[*to], kind.clone());
+            }
+            let info = FunctionInfo { name: title, ..FunctionInfo::default() };
+            graph.append_arena(&mut arena, info);
+        }
+
+        graph
+    }
+
+    pub fn render_text(versions: &[(&Snapshot, &FunctionSnapshot)]) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<9} {:<25} {:>10} {:>6} {:>8} {:>6}  function", "commit", "date", "complexity", "nodes", "branches", "loops");
+        for (snapshot, func) in versions {
+            let _ = writeln!(
+                out,
+                "{:<9} {:<25} {:>10} {:>6} {:>8} {:>6}  {}",
+                snapshot.short_commit(),
+                snapshot.date,
+                func.stats.complexity,
+                func.stats.nodes,
+                func.stats.branches,
+                func.stats.loops,
+                func.name
+            );
+        }
+        out
+    }
+}
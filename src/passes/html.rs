@@ -0,0 +1,366 @@
+use crate::passes::{DotRendererPass, StyledGraph};
+use crate::style::SyntaxHighlighter;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+// 自包含的交互式 HTML 查看器：内嵌渲染好的 SVG 和 DOT 源码，不依赖网络。
+// 支持拖动平移、滚轮缩放、按节点文本搜索、折叠分组（函数或文件），以及点击节点查看源码
+pub struct HtmlViewerPass;
+
+// 源码面板的数据：每个函数的源码（已高亮）和每个节点对应的行
+#[derive(Serialize, Default)]
+pub struct SourcePanel {
+    functions: Vec<PanelFunction>,
+    // DOT 节点名称 -> (函数下标, 起始行, 结束行)
+    nodes: BTreeMap<String, (usize, usize, usize)>,
+}
+
+#[derive(Serialize)]
+struct PanelFunction {
+    name: String,
+    file: String,
+    // lines 中第一行的行号
+    first_line: usize,
+    lines: Vec<String>,
+}
+
+impl SourcePanel {
+    // 函数的源码范围取其中所有节点的源码范围之和；从磁盘读取源文件，读取失败的函数不显示源码。
+    // 节点名称与 DotRendererPass::render 对同一个图的输出一致
+    pub fn build(graph: &StyledGraph) -> Self {
+        let mut ranges: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+        for node in &graph.nodes {
+            if let (Some(function), Some(span)) = (node.function, node.span) {
+                let range = ranges.entry(function).or_insert((span.line, span.end_line));
+                range.0 = range.0.min(span.line);
+                range.1 = range.1.max(span.end_line);
+            }
+        }
+
+        let mut panel = Self::default();
+        let mut sources: HashMap<&str, Option<String>> = HashMap::new();
+        let mut indices = HashMap::new();
+        for (function, (first, last)) in ranges {
+            let Some(file) = graph.files.get(&function) else {
+                continue;
+            };
+            let Some(source) = sources.entry(file).or_insert_with(|| std::fs::read_to_string(file).ok()) else {
+                continue;
+            };
+            let text: Vec<&str> = source.lines().skip(first - 1).take(last + 1 - first).collect();
+            indices.insert(function, panel.functions.len());
+            panel.functions.push(PanelFunction {
+                name: graph.names.get(&function).cloned().unwrap_or_default(),
+                file: file.clone(),
+                first_line: first,
+                lines: SyntaxHighlighter::html_lines(&text.join("\n")),
+            });
+        }
+
+        let ids = DotRendererPass::node_ids(graph);
+        for node in &graph.nodes {
+            if let (Some(&index), Some(span)) = (node.function.and_then(|function| indices.get(&function)), node.span) {
+                panel.nodes.insert(ids[&node.id].clone(), (index, span.line, span.end_line));
+            }
+        }
+        panel
+    }
+}
+
+const STYLE: &str = r#"
+* { box-sizing: border-box; }
+body { margin: 0; display: flex; height: 100vh; font-family: Arial, sans-serif; font-size: 13px; }
+#sidebar { width: 280px; padding: 10px; border-right: 1px solid #ccc; overflow-y: auto; background: #fafafa; }
+#sidebar h1 { font-size: 15px; margin: 0 0 10px; }
+#search { width: 100%; padding: 4px; margin-bottom: 4px; }
+#matches { color: #666; margin-bottom: 10px; }
+#sidebar button { margin: 0 4px 10px 0; }
+#groups label { display: block; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; cursor: pointer; }
+#graph { flex: 1; overflow: hidden; cursor: grab; }
+#graph.dragging { cursor: grabbing; }
+#graph svg { width: 100%; height: 100%; }
+.match polygon, .match ellipse, .match path:not([fill="none"]) { stroke: #e6007e !important; stroke-width: 3px; }
+.dim { opacity: 0.25; }
+.hidden { display: none; }
+.cluster.collapsed polygon, .cluster.collapsed path { fill: #ddd; }
+#source-panel { width: 40%; border-left: 1px solid #ccc; display: flex; flex-direction: column; background: #fff; }
+#source-panel.hidden { display: none; }
+#source-header { display: flex; align-items: center; padding: 6px 10px; border-bottom: 1px solid #ccc; background: #fafafa; }
+#source-title { flex: 1; font-weight: bold; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+#source-code { flex: 1; margin: 0; overflow: auto; font-family: Consolas, Menlo, monospace; font-size: 12px; line-height: 1.5; }
+#source-code .line { padding-right: 10px; white-space: pre; }
+#source-code .line.current { background: #fff3b0; }
+#source-code .number { display: inline-block; width: 4em; padding-right: 1em; text-align: right; color: #999; user-select: none; }
+"#;
+
+const SCRIPT: &str = r#"
+(function () {
+  var svg = document.querySelector('#graph svg');
+  if (!svg) { return; }
+  var container = document.getElementById('graph');
+  var view = svg.viewBox.baseVal;
+  var box = { x: view.x, y: view.y, w: view.width, h: view.height };
+  svg.removeAttribute('width');
+  svg.removeAttribute('height');
+
+  function apply() { svg.setAttribute('viewBox', [box.x, box.y, box.w, box.h].join(' ')); }
+  function toSvg(x, y) {
+    var point = svg.createSVGPoint();
+    point.x = x; point.y = y;
+    return point.matrixTransform(svg.getScreenCTM().inverse());
+  }
+  // 元素在根坐标系中的包围盒
+  function bounds(element) {
+    var b = element.getBBox();
+    var m = svg.getScreenCTM().inverse().multiply(element.getScreenCTM());
+    var p1 = svg.createSVGPoint(), p2 = svg.createSVGPoint();
+    p1.x = b.x; p1.y = b.y; p2.x = b.x + b.width; p2.y = b.y + b.height;
+    p1 = p1.matrixTransform(m); p2 = p2.matrixTransform(m);
+    return { x1: Math.min(p1.x, p2.x), y1: Math.min(p1.y, p2.y), x2: Math.max(p1.x, p2.x), y2: Math.max(p1.y, p2.y) };
+  }
+  function inside(point, rect) {
+    return point.x >= rect.x1 && point.x <= rect.x2 && point.y >= rect.y1 && point.y <= rect.y2;
+  }
+
+  // 平移和缩放
+  container.addEventListener('wheel', function (event) {
+    event.preventDefault();
+    var scale = event.deltaY < 0 ? 0.85 : 1 / 0.85;
+    var p = toSvg(event.clientX, event.clientY);
+    box.x = p.x - (p.x - box.x) * scale;
+    box.y = p.y - (p.y - box.y) * scale;
+    box.w *= scale; box.h *= scale;
+    apply();
+  }, { passive: false });
+  var drag = null, moved = false;
+  container.addEventListener('mousedown', function (event) {
+    drag = toSvg(event.clientX, event.clientY);
+    moved = false;
+    container.classList.add('dragging');
+  });
+  window.addEventListener('mousemove', function (event) {
+    if (!drag) { return; }
+    moved = true;
+    var p = toSvg(event.clientX, event.clientY);
+    box.x -= p.x - drag.x; box.y -= p.y - drag.y;
+    apply();
+  });
+  window.addEventListener('mouseup', function () { drag = null; container.classList.remove('dragging'); });
+  document.getElementById('reset').addEventListener('click', function () {
+    box = { x: view.x, y: view.y, w: view.width, h: view.height };
+    apply();
+  });
+
+  // 搜索：Graphviz 输出的节点带 class="node"，其他渲染器的输出按文本元素搜索
+  var nodes = Array.prototype.slice.call(svg.querySelectorAll('g.node'));
+  if (nodes.length === 0) { nodes = Array.prototype.slice.call(svg.querySelectorAll('text')); }
+  var matches = [], current = -1;
+  var search = document.getElementById('search');
+  search.addEventListener('input', function () {
+    var query = search.value.trim().toLowerCase();
+    matches = [];
+    current = -1;
+    nodes.forEach(function (node) {
+      var hit = query !== '' && node.textContent.toLowerCase().indexOf(query) >= 0;
+      node.classList.toggle('match', hit);
+      node.classList.toggle('dim', query !== '' && !hit);
+      if (hit) { matches.push(node); }
+    });
+    document.getElementById('matches').textContent = query === '' ? '' : matches.length + ' 个匹配';
+  });
+  search.addEventListener('keydown', function (event) {
+    if (event.key !== 'Enter' || matches.length === 0) { return; }
+    current = (current + 1) % matches.length;
+    var rect = bounds(matches[current]);
+    box.x = (rect.x1 + rect.x2) / 2 - box.w / 2;
+    box.y = (rect.y1 + rect.y2) / 2 - box.h / 2;
+    apply();
+  });
+
+  // 折叠分组：隐藏分组范围内的节点、子分组和与之相连的边
+  var clusters = Array.prototype.slice.call(svg.querySelectorAll('g.cluster'));
+  var edges = Array.prototype.slice.call(svg.querySelectorAll('g.edge'));
+  var collapsed = [];
+  function refresh() {
+    // 隐藏的元素没有包围盒，先全部显示再重新计算
+    svg.querySelectorAll('.hidden').forEach(function (element) { element.classList.remove('hidden'); });
+    var rects = collapsed.map(bounds);
+    var hiddenBy = function (rect) {
+      return rects.some(function (r) {
+        return !(r.x1 === rect.x1 && r.y1 === rect.y1 && r.x2 === rect.x2 && r.y2 === rect.y2) && inside({ x: rect.x1, y: rect.y1 }, r) && inside({ x: rect.x2, y: rect.y2 }, r);
+      });
+    };
+    clusters.forEach(function (cluster) {
+      cluster.classList.toggle('hidden', hiddenBy(bounds(cluster)));
+      cluster.classList.toggle('collapsed', collapsed.indexOf(cluster) >= 0);
+    });
+    svg.querySelectorAll('g.node').forEach(function (node) {
+      var rect = bounds(node);
+      var center = { x: (rect.x1 + rect.x2) / 2, y: (rect.y1 + rect.y2) / 2 };
+      node.classList.toggle('hidden', rects.some(function (r) { return inside(center, r); }));
+    });
+    var root = svg.getScreenCTM().inverse();
+    edges.forEach(function (edge) {
+      var path = edge.querySelector('path');
+      if (!path) { return; }
+      var m = root.multiply(path.getScreenCTM());
+      var ends = [0, path.getTotalLength()].map(function (at) { return path.getPointAtLength(at).matrixTransform(m); });
+      edge.classList.toggle('hidden', rects.some(function (r) { return inside(ends[0], r) || inside(ends[1], r); }));
+    });
+  }
+  function toggle(cluster, checkbox) {
+    var index = collapsed.indexOf(cluster);
+    if (index >= 0) { collapsed.splice(index, 1); } else { collapsed.push(cluster); }
+    checkbox.checked = index < 0;
+    refresh();
+  }
+  var list = document.getElementById('groups');
+  clusters.forEach(function (cluster) {
+    var title = cluster.querySelector('title');
+    var name = title ? title.textContent : cluster.id;
+    var label = document.createElement('label');
+    var checkbox = document.createElement('input');
+    checkbox.type = 'checkbox';
+    label.appendChild(checkbox);
+    label.appendChild(document.createTextNode(' ' + name));
+    label.title = name;
+    checkbox.addEventListener('change', function () { checkbox.checked = !checkbox.checked; toggle(cluster, checkbox); });
+    cluster.addEventListener('dblclick', function () { toggle(cluster, checkbox); });
+    list.appendChild(label);
+  });
+  document.getElementById('expand').addEventListener('click', function () {
+    collapsed = [];
+    list.querySelectorAll('input').forEach(function (checkbox) { checkbox.checked = false; });
+    refresh();
+  });
+  if (clusters.length === 0) { document.getElementById('group-section').classList.add('hidden'); }
+
+  // 源码面板：点击节点显示所在函数的源码并高亮节点对应的行；按住 Ctrl/Cmd 点击仍打开节点的源码链接
+  var data = document.getElementById('source-data');
+  var sources = data ? JSON.parse(data.textContent) : { functions: [], nodes: {} };
+  var panel = document.getElementById('source-panel');
+  function showSource(entry) {
+    var func = sources.functions[entry[0]];
+    document.getElementById('source-title').textContent = func.name + ' — ' + func.file + ':' + entry[1];
+    var code = document.getElementById('source-code');
+    code.innerHTML = '';
+    var current = null;
+    func.lines.forEach(function (html, i) {
+      var number = func.first_line + i;
+      var line = document.createElement('div');
+      line.className = 'line';
+      if (number >= entry[1] && number <= entry[2]) {
+        line.classList.add('current');
+        current = current || line;
+      }
+      line.innerHTML = '<span class="number">' + number + '</span>' + html;
+      code.appendChild(line);
+    });
+    panel.classList.remove('hidden');
+    if (current) { current.scrollIntoView({ block: 'center' }); }
+  }
+  svg.querySelectorAll('g.node').forEach(function (node) {
+    var entry = sources.nodes[node.id];
+    if (!entry) { return; }
+    node.style.cursor = 'pointer';
+    node.addEventListener('click', function (event) {
+      if (moved || event.ctrlKey || event.metaKey) { return; }
+      event.preventDefault();
+      showSource(entry);
+    });
+  });
+  document.getElementById('source-close').addEventListener('click', function () { panel.classList.add('hidden'); });
+
+  // 下载内嵌的 DOT 源码
+  document.getElementById('download').addEventListener('click', function () {
+    var source = document.getElementById('dot-source').textContent.replace(/<\\\//g, '</');
+    var link = document.createElement('a');
+    link.href = URL.createObjectURL(new Blob([source], { type: 'text/vnd.graphviz' }));
+    link.download = 'graph.dot';
+    link.click();
+  });
+})();
+"#;
+
+// --watch 时附加的脚本：每秒重新读取页面本身，内容变化后刷新。
+// 需要通过 HTTP 打开（或使用允许 file:// 请求的浏览器）
+const LIVE_RELOAD: &str = r#"
+(function () {
+  var last = null;
+  setInterval(function () {
+    fetch(location.href, { cache: 'no-store' })
+      .then(function (response) { return response.text(); })
+      .then(function (text) {
+        if (last !== null && text !== last) { location.reload(); }
+        last = text;
+      })
+      .catch(function () {});
+  }, 1000);
+})();
+"#;
+
+impl HtmlViewerPass {
+    pub fn render(svg: &str, dot: &str, title: &str) -> String {
+        Self::render_with_sources(svg, dot, title, &SourcePanel::default())
+    }
+
+    // sources 为点击节点时显示的源码，节点按 SVG 元素的 id（即 DOT 节点名称）查找
+    pub fn render_with_sources(svg: &str, dot: &str, title: &str, sources: &SourcePanel) -> String {
+        let title = Self::escape(title);
+        // SVG 直接内嵌，去掉 XML 声明和 DOCTYPE
+        let svg = svg.find("<svg").map_or(svg, |start| &svg[start..]);
+        let sources = serde_json::to_string(sources).unwrap_or_else(|_| "null".to_string());
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"zh\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <div id=\"sidebar\">\n<h1>{title}</h1>\n\
+             <input id=\"search\" type=\"search\" placeholder=\"搜索节点（回车跳到下一个）\">\n<div id=\"matches\"></div>\n\
+             <button id=\"reset\">重置视图</button><button id=\"download\">下载 DOT</button>\n\
+             <div id=\"group-section\">\n<button id=\"expand\">全部展开</button>\n<div id=\"groups\"></div>\n</div>\n</div>\n\
+             <div id=\"graph\">\n{svg}\n</div>\n\
+             <div id=\"source-panel\" class=\"hidden\">\n<div id=\"source-header\"><span id=\"source-title\"></span><button id=\"source-close\">关闭</button></div>\n\
+             <pre id=\"source-code\"></pre>\n</div>\n\
+             <script type=\"text/vnd.graphviz\" id=\"dot-source\">\n{dot}</script>\n\
+             <script type=\"application/json\" id=\"source-data\">{sources}</script>\n\
+             <script>{SCRIPT}</script>\n</body>\n</html>\n",
+            title = title,
+            svg = svg,
+            // 避免 DOT 中的 `</script>` 提前结束脚本块，下载时再还原
+            dot = dot.replace("</", "<\\/"),
+            // JSON 中的 `<\/` 与 `</` 等价
+            sources = sources.replace("</", "<\\/"),
+        )
+    }
+
+    // 在 render 的结果中加入自动刷新脚本
+    pub fn live_reload(html: &str) -> String {
+        let script = format!("<script>{}</script>\n</body>", LIVE_RELOAD);
+        match html.rfind("</body>") {
+            Some(end) => format!("{}{}{}", &html[..end], script, &html[end + "</body>".len()..]),
+            None => format!("{}{}", html, script),
+        }
+    }
+
+    // --split-by 输出的索引页：每个模块一节，列出指向各张图的链接。
+    // sections 为 (模块, [(显示名称, 相对于索引页的路径)])
+    pub fn render_index(title: &str, sections: &[(String, Vec<(String, String)>)]) -> String {
+        let mut body = String::new();
+        for (module, links) in sections {
+            body.push_str(&format!("<h2>{}</h2>\n<ul>\n", Self::escape(module)));
+            for (name, href) in links {
+                body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", Self::escape(href), Self::escape(name)));
+            }
+            body.push_str("</ul>\n");
+        }
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"zh\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{title}</title>\n\
+             <style>body {{ font-family: Arial, sans-serif; font-size: 14px; margin: 20px; }} h2 {{ font-size: 15px; margin-bottom: 4px; }}</style>\n\
+             </head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+            title = Self::escape(title),
+            body = body,
+        )
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use syn::visit::{self, Visit};
+use syn::{File, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemTrait, ItemUse, TraitItemFn, Type, UseTree};
+
+// 每个作用域（模块或函数路径）中 `use` 引入的名称
+#[derive(Debug, Clone, Default)]
+pub struct ImportTable {
+    // 作用域 -> 本地名称 -> 完整路径
+    names: HashMap<String, HashMap<String, String>>,
+    // 作用域 -> `use path::*` 的路径
+    globs: HashMap<String, Vec<String>>,
+}
+
+impl ImportTable {
+    pub fn collect(file: &File, module_path: &str) -> Self {
+        let mut collector = ImportCollector {
+            table: Self::default(),
+            scope: module_path.split("::").map(str::to_string).collect(),
+        };
+        collector.visit_file(file);
+        collector.table
+    }
+
+    pub fn extend(&mut self, other: ImportTable) {
+        for (scope, names) in other.names {
+            self.names.entry(scope).or_default().extend(names);
+        }
+        for (scope, globs) in other.globs {
+            self.globs.entry(scope).or_default().extend(globs);
+        }
+    }
+
+    pub fn lookup(&self, scope: &str, name: &str) -> Option<&str> {
+        self.names.get(scope)?.get(name).map(String::as_str)
+    }
+
+    pub fn globs(&self, scope: &str) -> &[String] {
+        self.globs.get(scope).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // 所有 (作用域, 引入的完整路径)，包括 glob 引入的路径
+    pub fn paths(&self) -> impl Iterator<Item = (&str, &str)> {
+        let names = self.names.iter()
+            .flat_map(|(scope, names)| names.values().map(move |path| (scope.as_str(), path.as_str())));
+        let globs = self.globs.iter()
+            .flat_map(|(scope, globs)| globs.iter().map(move |path| (scope.as_str(), path.as_str())));
+        names.chain(globs)
+    }
+
+    // 用调用者所在作用域（由内向外）的 `use` 展开调用路径的首段
+    pub fn expand(&self, caller: &str, callee: &str) -> Option<String> {
+        let (first, rest) = match callee.split_once("::") {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (callee, None),
+        };
+        Self::scopes(caller).find_map(|scope| {
+            let full = self.lookup(scope, first)?;
+            Some(match rest {
+                Some(rest) => format!("{}::{}", full, rest),
+                None => full.to_string(),
+            })
+        })
+    }
+
+    // `crate::a::f` -> `crate::a::f`、`crate::a`、`crate`
+    pub fn scopes(path: &str) -> impl Iterator<Item = &str> {
+        std::iter::successors(Some(path), |scope| scope.rsplit_once("::").map(|(parent, _)| parent))
+    }
+}
+
+struct ImportCollector {
+    table: ImportTable,
+    scope: Vec<String>,
+}
+
+impl ImportCollector {
+    // `self::`、`super::` 相对于当前作用域展开为以 `crate` 开头的路径
+    fn absolute(&self, prefix: &[String]) -> Vec<String> {
+        let mut path: Vec<String> = Vec::new();
+        for segment in prefix {
+            match segment.as_str() {
+                "self" if path.is_empty() => path = self.scope.clone(),
+                "super" if path.is_empty() => {
+                    path = self.scope.clone();
+                    path.pop();
+                }
+                "super" => {
+                    path.pop();
+                }
+                _ => path.push(segment.clone()),
+            }
+        }
+        path
+    }
+
+    fn add_tree(&mut self, prefix: &mut Vec<String>, tree: &UseTree) {
+        let scope = self.scope.join("::");
+        match tree {
+            UseTree::Path(use_path) => {
+                prefix.push(use_path.ident.to_string());
+                self.add_tree(prefix, &use_path.tree);
+                prefix.pop();
+            }
+            UseTree::Name(use_name) => {
+                let name = use_name.ident.to_string();
+                // `use a::b::{self}` 引入模块 b 本身
+                let (alias, full) = if name == "self" {
+                    (prefix.last().cloned().unwrap_or_default(), self.absolute(prefix))
+                } else {
+                    prefix.push(name.clone());
+                    let full = self.absolute(prefix);
+                    prefix.pop();
+                    (name, full)
+                };
+                self.table.names.entry(scope).or_default().insert(alias, full.join("::"));
+            }
+            UseTree::Rename(use_rename) => {
+                prefix.push(use_rename.ident.to_string());
+                let full = self.absolute(prefix);
+                prefix.pop();
+                self.table.names.entry(scope).or_default()
+                    .insert(use_rename.rename.to_string(), full.join("::"));
+            }
+            UseTree::Glob(_) => {
+                let full = self.absolute(prefix);
+                self.table.globs.entry(scope).or_default().push(full.join("::"));
+            }
+            UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.add_tree(prefix, tree);
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ImportCollector {
+    fn visit_item_use(&mut self, node: &'ast ItemUse) {
+        self.add_tree(&mut Vec::new(), &node.tree);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        self.scope.push(node.ident.to_string());
+        visit::visit_item_mod(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        self.scope.push(node.sig.ident.to_string());
+        visit::visit_item_fn(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast ItemImpl) {
+        // 与函数收集保持一致，方法路径为 模块::类型::方法
+        let name = match &*node.self_ty {
+            Type::Path(type_path) => type_path.path.segments.last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default(),
+            ty => quote::ToTokens::to_token_stream(ty).to_string().replace(' ', ""),
+        };
+        self.scope.push(name);
+        visit::visit_item_impl(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        self.scope.push(node.sig.ident.to_string());
+        visit::visit_impl_item_fn(self, node);
+        self.scope.pop();
+    }
+
+    // trait 默认方法的路径为 模块::trait::方法
+    fn visit_item_trait(&mut self, node: &'ast ItemTrait) {
+        self.scope.push(node.ident.to_string());
+        visit::visit_item_trait(self, node);
+        self.scope.pop();
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast TraitItemFn) {
+        self.scope.push(node.sig.ident.to_string());
+        visit::visit_trait_item_fn(self, node);
+        self.scope.pop();
+    }
+}
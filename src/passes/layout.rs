@@ -0,0 +1,178 @@
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Graphviz 的 plain 输出以英寸为单位，这里统一换算为 point（1/72 英寸），
+// 坐标原点在左下角
+const POINTS_PER_INCH: f64 = 72.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutNode {
+    pub id: String,
+    pub label: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub shape: String,
+    pub style: String,
+    pub color: String,
+    pub fillcolor: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutLabel {
+    pub text: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutEdge {
+    pub tail: String,
+    pub head: String,
+    // B 样条控制点
+    pub points: Vec<(f64, f64)>,
+    pub label: Option<LayoutLabel>,
+    pub style: String,
+    pub color: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Layout {
+    pub width: f64,
+    pub height: f64,
+    pub nodes: Vec<LayoutNode>,
+    pub edges: Vec<LayoutEdge>,
+}
+
+pub struct LayoutPass;
+
+impl LayoutPass {
+    // 调用 `dot -Tplain` 完成布局
+    pub fn layout(dot_content: &str) -> Result<Layout> {
+        let mut child = Command::new("dot")
+            .arg("-Tplain")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to run Graphviz `dot`, which `--format layout` requires; install it from https://graphviz.org/download/")?;
+        child.stdin.take().context("Failed to open dot stdin")?.write_all(dot_content.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!("Failed to lay out graph with dot");
+        }
+        Self::parse_plain(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    pub fn parse_plain(plain: &str) -> Result<Layout> {
+        let mut layout = Layout::default();
+
+        for line in plain.lines() {
+            let tokens = Self::tokenize(line);
+            let Some(kind) = tokens.first() else {
+                continue;
+            };
+            let number = |index: usize| -> Result<f64> {
+                tokens.get(index)
+                    .and_then(|token| token.parse::<f64>().ok())
+                    .with_context(|| format!("Invalid plain layout line: {}", line))
+            };
+            let text = |index: usize| tokens.get(index).cloned().unwrap_or_default();
+
+            match kind.as_str() {
+                // graph scale width height
+                "graph" => {
+                    layout.width = number(2)? * POINTS_PER_INCH;
+                    layout.height = number(3)? * POINTS_PER_INCH;
+                }
+                // node name x y width height label style shape color fillcolor
+                "node" => layout.nodes.push(LayoutNode {
+                    id: text(1),
+                    x: number(2)? * POINTS_PER_INCH,
+                    y: number(3)? * POINTS_PER_INCH,
+                    width: number(4)? * POINTS_PER_INCH,
+                    height: number(5)? * POINTS_PER_INCH,
+                    label: text(6),
+                    style: text(7),
+                    shape: text(8),
+                    color: text(9),
+                    fillcolor: text(10),
+                }),
+                // edge tail head n x1 y1 ... xn yn [label xl yl] style color
+                "edge" => {
+                    let count = number(3)? as usize;
+                    let points = (0..count)
+                        .map(|i| Ok((number(4 + 2 * i)? * POINTS_PER_INCH, number(5 + 2 * i)? * POINTS_PER_INCH)))
+                        .collect::<Result<Vec<_>>>()?;
+                    let rest = 4 + 2 * count;
+                    let label = if tokens.len() >= rest + 5 {
+                        Some(LayoutLabel {
+                            text: text(rest),
+                            x: number(rest + 1)? * POINTS_PER_INCH,
+                            y: number(rest + 2)? * POINTS_PER_INCH,
+                        })
+                    } else {
+                        None
+                    };
+                    let style_index = if label.is_some() { rest + 3 } else { rest };
+                    layout.edges.push(LayoutEdge {
+                        tail: text(1),
+                        head: text(2),
+                        points,
+                        label,
+                        style: text(style_index),
+                        color: text(style_index + 1),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(layout)
+    }
+
+    pub fn render_json(layout: &Layout) -> Result<String> {
+        Ok(serde_json::to_string_pretty(layout)?)
+    }
+
+    // 按空白分割，双引号内的内容（支持 \" 转义）作为一个整体
+    fn tokenize(line: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut chars = line.chars().peekable();
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == '"' {
+                chars.next();
+                let mut token = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some('n') => token.push('\n'),
+                            Some(escaped) => token.push(escaped),
+                            None => {}
+                        },
+                        '"' => break,
+                        c => token.push(c),
+                    }
+                }
+                tokens.push(token);
+            } else {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+
+        tokens
+    }
+}
@@ -0,0 +1,179 @@
+use crate::passes::{CallGraph, CallGraphPass, CrateFunctions};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionMetrics {
+    pub name: String,
+    pub in_degree: usize,
+    pub out_degree: usize,
+    // (入度 + 出度) / (n - 1)
+    pub degree_centrality: f64,
+    // 经过该函数的最短调用路径比例（Brandes 算法，归一化到 0..1）
+    pub betweenness: f64,
+    // 到所有可达函数平均距离的倒数，按可达比例修正（Wasserman-Faust）
+    pub closeness: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CallMatrix {
+    pub functions: Vec<String>,
+    // matrix[i][j] 为 functions[i] 调用 functions[j] 的调用点数量
+    pub matrix: Vec<Vec<usize>>,
+    pub metrics: Vec<FunctionMetrics>,
+}
+
+pub struct MatrixPass;
+
+impl MatrixPass {
+    pub fn build(crates: &[CrateFunctions], graph: &CallGraph) -> CallMatrix {
+        let nodes: Vec<_> = graph.nodes.iter().collect();
+        let functions: Vec<String> = nodes.iter().map(|node| CallGraphPass::display_name(crates, node)).collect();
+        let n = nodes.len();
+
+        let mut matrix = vec![vec![0; n]; n];
+        for ((caller, callee), count) in &graph.edges {
+            if let (Ok(i), Ok(j)) = (nodes.binary_search(&caller), nodes.binary_search(&callee)) {
+                matrix[i][j] += count;
+            }
+        }
+
+        // 中心性只看是否存在调用，不考虑调用点数量
+        let successors: Vec<Vec<usize>> = (0..n)
+            .map(|i| (0..n).filter(|&j| i != j && matrix[i][j] > 0).collect())
+            .collect();
+        let betweenness = Self::betweenness(&successors);
+
+        let metrics = (0..n)
+            .map(|i| {
+                let out_degree = successors[i].len();
+                let in_degree = successors.iter().filter(|targets| targets.contains(&i)).count();
+                let scale = if n > 1 { (n - 1) as f64 } else { 1.0 };
+                FunctionMetrics {
+                    name: functions[i].clone(),
+                    in_degree,
+                    out_degree,
+                    degree_centrality: (in_degree + out_degree) as f64 / scale,
+                    betweenness: if n > 2 { betweenness[i] / ((n - 1) * (n - 2)) as f64 } else { 0.0 },
+                    closeness: Self::closeness(&successors, i),
+                }
+            })
+            .collect();
+
+        CallMatrix { functions, matrix, metrics }
+    }
+
+    fn distances(successors: &[Vec<usize>], source: usize) -> Vec<Option<usize>> {
+        let mut distance = vec![None; successors.len()];
+        let mut queue = VecDeque::from([source]);
+        distance[source] = Some(0);
+        while let Some(node) = queue.pop_front() {
+            for &next in &successors[node] {
+                if distance[next].is_none() {
+                    distance[next] = distance[node].map(|d| d + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distance
+    }
+
+    fn closeness(successors: &[Vec<usize>], source: usize) -> f64 {
+        let n = successors.len();
+        let reachable: Vec<usize> = Self::distances(successors, source).into_iter().flatten().filter(|&d| d > 0).collect();
+        let total: usize = reachable.iter().sum();
+        if total == 0 || n < 2 {
+            return 0.0;
+        }
+        let r = reachable.len() as f64;
+        (r / total as f64) * (r / (n - 1) as f64)
+    }
+
+    // Brandes 算法，边无权
+    fn betweenness(successors: &[Vec<usize>]) -> Vec<f64> {
+        let n = successors.len();
+        let mut centrality = vec![0.0; n];
+
+        for source in 0..n {
+            let mut stack = Vec::new();
+            let mut predecessors = vec![Vec::new(); n];
+            let mut paths = vec![0.0; n];
+            let mut distance: Vec<Option<usize>> = vec![None; n];
+            paths[source] = 1.0;
+            distance[source] = Some(0);
+
+            let mut queue = VecDeque::from([source]);
+            while let Some(node) = queue.pop_front() {
+                stack.push(node);
+                for &next in &successors[node] {
+                    if distance[next].is_none() {
+                        distance[next] = distance[node].map(|d| d + 1);
+                        queue.push_back(next);
+                    }
+                    if distance[next] == distance[node].map(|d| d + 1) {
+                        paths[next] += paths[node];
+                        predecessors[next].push(node);
+                    }
+                }
+            }
+
+            let mut dependency = vec![0.0; n];
+            while let Some(node) = stack.pop() {
+                for &prev in &predecessors[node] {
+                    dependency[prev] += paths[prev] / paths[node] * (1.0 + dependency[node]);
+                }
+                if node != source {
+                    centrality[node] += dependency[node];
+                }
+            }
+        }
+
+        centrality
+    }
+
+    pub fn render_csv(matrix: &CallMatrix) -> String {
+        let mut csv = String::from("function");
+        for function in &matrix.functions {
+            let _ = write!(csv, ",{}", Self::csv_field(function));
+        }
+        csv.push('\n');
+        for (function, row) in matrix.functions.iter().zip(&matrix.matrix) {
+            csv.push_str(&Self::csv_field(function));
+            for count in row {
+                let _ = write!(csv, ",{}", count);
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn render_metrics_csv(matrix: &CallMatrix) -> String {
+        let mut csv = String::from("function,in_degree,out_degree,degree_centrality,betweenness,closeness\n");
+        for metrics in &matrix.metrics {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.6},{:.6},{:.6}",
+                Self::csv_field(&metrics.name),
+                metrics.in_degree,
+                metrics.out_degree,
+                metrics.degree_centrality,
+                metrics.betweenness,
+                metrics.closeness
+            );
+        }
+        csv
+    }
+
+    pub fn render_json(matrix: &CallMatrix) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(matrix)?)
+    }
+
+    fn csv_field(text: &str) -> String {
+        if text.contains([',', '"', '\n']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::passes::{StyledGraph, StyledNode};
+use crate::style::{DotEscape, LabelSanitizer, MermaidEscape, NodeStyle, MAX_LINE_WIDTH};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+// 输出 Mermaid `flowchart TD`，可以直接粘贴到 GitHub/GitLab 的 Markdown 或 Notion 中。
+// 节点形状和颜色沿用 DOT 渲染的样式，每个函数一个子图，闭包和嵌套函数的子图嵌套在所在函数中
+pub struct MermaidRendererPass;
+
+impl MermaidRendererPass {
+    pub fn render(graph: &StyledGraph) -> String {
+        let mut out = String::from("flowchart TD\n");
+
+        let mut function_nodes: BTreeMap<Option<usize>, Vec<&StyledNode>> = BTreeMap::new();
+        for node in &graph.nodes {
+            function_nodes.entry(node.function).or_default().push(node);
+        }
+        let mut children: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+        for function in function_nodes.keys().flatten() {
+            let parent = graph.parents.get(function).copied().filter(|parent| function_nodes.contains_key(&Some(*parent)));
+            children.entry(parent).or_default().push(*function);
+        }
+
+        if let Some(nodes) = function_nodes.get(&None) {
+            for node in nodes {
+                Self::render_node(&mut out, graph, node, 1);
+            }
+        }
+        for function in children.get(&None).into_iter().flatten() {
+            Self::render_subgraph(&mut out, graph, *function, &function_nodes, &children, 1);
+        }
+
+        let valid_nodes: HashSet<_> = graph.nodes.iter().map(|node| node.id).collect();
+        let functions: BTreeMap<_, _> = graph.nodes.iter().map(|node| (node.id, node.function)).collect();
+        for edge in graph.edges.iter().filter(|edge| valid_nodes.contains(&edge.from) && valid_nodes.contains(&edge.to)) {
+            let arrow = match edge.style.as_str() {
+                "dashed" | "dotted" => "-.->",
+                "bold" => "==>",
+                _ => "-->",
+            };
+            let label = if edge.label.is_empty() {
+                String::new()
+            } else {
+                format!("|\"{}\"|", MermaidEscape::text(&LabelSanitizer::truncate(&edge.label, MAX_LINE_WIDTH)))
+            };
+            let _ = writeln!(
+                out,
+                "    {} {}{} {}",
+                Self::node_id(graph, functions[&edge.from], edge.from.index()),
+                arrow,
+                label,
+                Self::node_id(graph, functions[&edge.to], edge.to.index())
+            );
+        }
+
+        // 填充色和边框沿用 DOT 样式
+        for node in &graph.nodes {
+            let mut style = format!(
+                "fill:{},stroke:{},color:{}",
+                NodeStyle::css_color(&node.fillcolor),
+                NodeStyle::css_color(&node.color),
+                NodeStyle::css_color(&graph.theme.font.color)
+            );
+            if node.style.contains("dashed") {
+                style.push_str(",stroke-dasharray:5 5");
+            }
+            if node.style.contains("bold") {
+                style.push_str(",stroke-width:2px");
+            }
+            let _ = writeln!(out, "    style {} {}", Self::node_id(graph, node.function, node.id.index()), style);
+        }
+        out
+    }
+
+    fn render_subgraph(
+        out: &mut String,
+        graph: &StyledGraph,
+        function: usize,
+        function_nodes: &BTreeMap<Option<usize>, Vec<&StyledNode>>,
+        children: &BTreeMap<Option<usize>, Vec<usize>>,
+        depth: usize,
+    ) {
+        let indent = "    ".repeat(depth);
+        let name = graph.names.get(&function).cloned().unwrap_or_else(|| format!("fn {}", function));
+        let _ = writeln!(out, "{}subgraph {}[\"{}\"]", indent, Self::prefix(graph, Some(function)), MermaidEscape::text(&name));
+        for node in &function_nodes[&Some(function)] {
+            Self::render_node(out, graph, node, depth + 1);
+        }
+        for child in children.get(&Some(function)).into_iter().flatten() {
+            Self::render_subgraph(out, graph, *child, function_nodes, children, depth + 1);
+        }
+        let _ = writeln!(out, "{}end", indent);
+    }
+
+    // 开始/结束为体育场形，条件为菱形，循环为六边形，其余沿用 DOT 形状中最接近的一种
+    fn render_node(out: &mut String, graph: &StyledGraph, node: &StyledNode, depth: usize) {
+        let label = MermaidEscape::lines(&LabelSanitizer::wrap(&node.label, MAX_LINE_WIDTH));
+        let (open, close) = match node.shape.as_str() {
+            "oval" | "ellipse" => ("([", "])"),
+            "diamond" => ("{", "}"),
+            "hexagon" => ("{{", "}}"),
+            "parallelogram" => ("[/", "/]"),
+            "octagon" => ("[[", "]]"),
+            _ if node.style.contains("rounded") => ("(", ")"),
+            _ => ("[", "]"),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}{}\"{}\"{}",
+            "    ".repeat(depth),
+            Self::node_id(graph, node.function, node.id.index()),
+            open,
+            label,
+            close
+        );
+    }
+
+    // 节点 ID 以所属函数的完整路径为前缀，合并多个文件的输出时不会冲突
+    fn node_id(graph: &StyledGraph, function: Option<usize>, index: usize) -> String {
+        format!("{}_n{}", Self::prefix(graph, function), index)
+    }
+
+    fn prefix(graph: &StyledGraph, function: Option<usize>) -> String {
+        match function {
+            Some(function) => match graph.names.get(&function) {
+                Some(name) => DotEscape::identifier(name),
+                None => format!("fn{}", function),
+            },
+            None => "node".to_string(),
+        }
+    }
+}
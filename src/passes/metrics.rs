@@ -0,0 +1,118 @@
+use crate::graph::FlowGraph;
+use crate::passes::{CollectedFunction, StatsPass};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+use syn::visit::{self, Visit};
+use syn::{ExprUnsafe, ItemFn};
+
+// 按函数输出的指标：名称、说明、取值
+type FunctionMetric = (&'static str, &'static str, fn(&CodeMetrics) -> usize);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeMetrics {
+    pub name: String,
+    pub file: String,
+    pub nodes: usize,
+    pub branches: usize,
+    pub loops: usize,
+    pub complexity: usize,
+    pub unsafe_blocks: usize,
+    pub is_unsafe: bool,
+}
+
+// 统计函数体内的 unsafe 块，嵌套函数单独统计
+#[derive(Default)]
+struct UnsafeCounter {
+    blocks: usize,
+}
+
+impl<'ast> Visit<'ast> for UnsafeCounter {
+    fn visit_expr_unsafe(&mut self, node: &'ast ExprUnsafe) {
+        self.blocks += 1;
+        visit::visit_expr_unsafe(self, node);
+    }
+
+    fn visit_item_fn(&mut self, _node: &'ast ItemFn) {}
+}
+
+pub struct OpenMetricsPass;
+
+impl OpenMetricsPass {
+    pub fn collect(functions: &[CollectedFunction], graph: &FlowGraph) -> Vec<CodeMetrics> {
+        let by_path: HashMap<&str, &CollectedFunction> = functions.iter()
+            .map(|func| (func.path.as_str(), func))
+            .collect();
+
+        StatsPass::collect(graph)
+            .into_iter()
+            .map(|stats| {
+                let func = by_path.get(stats.name.as_str());
+                let mut counter = UnsafeCounter::default();
+                if let Some(func) = func {
+                    counter.visit_block(&func.item.block);
+                }
+                CodeMetrics {
+                    file: func.and_then(|func| func.file.clone()).unwrap_or_default(),
+                    is_unsafe: func.is_some_and(|func| func.item.sig.unsafety.is_some()),
+                    unsafe_blocks: counter.blocks,
+                    nodes: stats.nodes,
+                    branches: stats.branches,
+                    loops: stats.loops,
+                    complexity: stats.complexity,
+                    name: stats.name,
+                }
+            })
+            .collect()
+    }
+
+    // OpenMetrics 文本格式：crate 级别的汇总值加上按函数和文件打标签的指标
+    pub fn render(metrics: &[CodeMetrics]) -> String {
+        let mut out = String::new();
+
+        let totals = [
+            ("functions", "Number of analyzed functions.", metrics.len()),
+            ("unsafe_functions", "Number of functions declared unsafe.", metrics.iter().filter(|m| m.is_unsafe).count()),
+            ("unsafe_blocks", "Number of unsafe blocks.", metrics.iter().map(|m| m.unsafe_blocks).sum()),
+            ("nodes", "Number of control flow graph nodes.", metrics.iter().map(|m| m.nodes).sum()),
+        ];
+        for (name, help, value) in totals {
+            Self::family(&mut out, name, help);
+            let _ = writeln!(out, "cargo_graph_{} {}", name, value);
+        }
+
+        let per_function: [FunctionMetric; 5] = [
+            ("function_complexity", "Cyclomatic complexity of the function control flow graph.", |m| m.complexity),
+            ("function_nodes", "Control flow graph nodes per function.", |m| m.nodes),
+            ("function_branches", "Branch points per function.", |m| m.branches),
+            ("function_loops", "Loops per function.", |m| m.loops),
+            ("function_unsafe_blocks", "Unsafe blocks per function.", |m| m.unsafe_blocks),
+        ];
+        for (name, help, value) in per_function {
+            Self::family(&mut out, name, help);
+            for metric in metrics {
+                let _ = writeln!(
+                    out,
+                    "cargo_graph_{}{{function=\"{}\",file=\"{}\"}} {}",
+                    name,
+                    Self::escape(&metric.name),
+                    Self::escape(&metric.file),
+                    value(metric)
+                );
+            }
+        }
+
+        out.push_str("# EOF\n");
+        out
+    }
+
+    fn family(out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# TYPE cargo_graph_{} gauge", name);
+        let _ = writeln!(out, "# HELP cargo_graph_{} {}", name, help);
+    }
+
+    // 标签值中只有 `\`、`"` 和换行需要转义
+    fn escape(value: &str) -> String {
+        value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+    }
+}
@@ -4,10 +4,72 @@ mod analyzer;
 mod builder;
 mod styler;
 mod renderer;
+mod stats;
+mod reachability;
+mod features;
+mod call_graph;
+mod imports;
+mod scip;
+mod matrix;
+mod layout;
+mod accessibility;
+mod metrics;
+mod history;
+mod churn;
+mod ownership;
+mod verify;
+mod expand;
+mod modules;
+mod deps;
+mod types;
+mod mermaid;
+mod plantuml;
+mod graphml;
+mod builtin_svg;
+mod html;
+mod d2;
+mod terminal;
+mod report;
+mod unreachable;
+mod diff;
+mod workspace;
+mod path_filter;
+mod cache;
 
 pub use parser::ParserPass;
-pub use collector::FunctionCollectorPass;
+pub use collector::{CollectedFunction, FunctionCollectorPass};
 pub use analyzer::ControlFlowAnalyzerPass;
 pub use builder::GraphBuilderPass;
-pub use styler::StylerPass;
-pub use renderer::DotRendererPass; 
\ No newline at end of file
+pub use styler::{StyledEdge, StyledGraph, StyledNode, StylerPass};
+pub use renderer::DotRendererPass;
+pub use mermaid::MermaidRendererPass;
+pub use plantuml::PlantUmlRendererPass;
+pub use graphml::GraphMlRendererPass;
+pub use builtin_svg::BuiltinSvgPass;
+pub use html::{HtmlViewerPass, SourcePanel};
+pub use d2::D2RendererPass;
+pub use terminal::TerminalRendererPass;
+pub use report::{ModuleReport, ReportDiagrams, ReportPass};
+pub use reachability::{CallSite, ReachabilityPass};
+pub use imports::ImportTable;
+pub use scip::ScipIndex;
+pub use matrix::{CallMatrix, FunctionMetrics, MatrixPass};
+pub use accessibility::AccessibilityPass;
+pub use metrics::{CodeMetrics, OpenMetricsPass};
+pub use churn::{ChurnPass, FileHistory};
+pub use ownership::CodeOwners;
+pub use expand::MacroExpansionPass;
+pub use modules::ModuleGraphPass;
+pub use deps::{DependencyGraph, DependencyGraphPass, DependencyOptions};
+pub use types::{TypeGraphPass, TypeItems};
+pub use verify::{Baseline, FunctionDiff, VerifyPass, VerifyReport};
+pub use diff::{DiffPass, DiffStatus, GraphDiff};
+pub use workspace::{WorkspacePackage, WorkspacePass};
+pub use path_filter::PathFilter;
+pub use cache::AnalysisCache;
+pub use history::{FunctionSnapshot, HistoryPass, Snapshot};
+pub use layout::{Layout, LayoutEdge, LayoutLabel, LayoutNode, LayoutPass};
+pub use features::{FeatureGate, FeatureMapPass};
+pub use call_graph::{CallGraph, CallGraphPass, CallNode, CrateFunctions};
+pub use stats::{StatsPass, StatsReport, FunctionStats, Distribution};
+pub use unreachable::{UnreachableCode, UnreachableCodePass}; 
\ No newline at end of file
@@ -0,0 +1,114 @@
+use crate::graph::{EdgeKind, FlowGraph, NodeType};
+use crate::passes::{FunctionCollectorPass, ImportTable};
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use syn::visit::{self, Visit};
+use syn::{File, ItemMod};
+
+// 模块依赖图：`mod` 声明构成模块树，`use` 语句构成模块之间的依赖，
+// 相互依赖（强连通分量中）的 use 边标为循环依赖
+pub struct ModuleGraphPass {
+    modules: Vec<String>,
+    scope: Vec<String>,
+}
+
+impl ModuleGraphPass {
+    // 文件本身对应的模块以及其中声明的所有模块（内联的和 `mod x;`），测试模块除外
+    pub fn collect(file: &File, module_path: &str) -> Vec<String> {
+        let mut pass = Self {
+            modules: vec![module_path.to_string()],
+            scope: module_path.split("::").map(str::to_string).collect(),
+        };
+        pass.visit_file(file);
+        pass.modules
+    }
+
+    pub fn build(modules: &BTreeSet<String>, imports: &ImportTable) -> FlowGraph {
+        let mut graph = FlowGraph::new();
+        let nodes: BTreeMap<&str, NodeIndex> = modules.iter()
+            .map(|module| (module.as_str(), graph.add_node(NodeType::Module(module.clone()))))
+            .collect();
+
+        // 模块树：父模块指向它声明的子模块
+        for (module, &node) in &nodes {
+            if let Some((parent, _)) = module.rsplit_once("::")
+                && let Some(&parent_node) = nodes.get(parent)
+            {
+                graph.add_edge(parent_node, node, EdgeKind::Mod);
+            }
+        }
+
+        let mut uses: BTreeSet<(NodeIndex, NodeIndex)> = BTreeSet::new();
+        for (scope, path) in imports.paths() {
+            let Some(from) = Self::owning_module(&nodes, scope) else {
+                continue;
+            };
+            let Some(to) = Self::resolve(&nodes, from, path) else {
+                continue;
+            };
+            if from != to {
+                uses.insert((nodes[from], nodes[to]));
+            }
+        }
+
+        // 只按 use 边计算强连通分量，模块树的边不算依赖
+        let mut dependencies: DiGraph<(), ()> = DiGraph::new();
+        for _ in 0..nodes.len() {
+            dependencies.add_node(());
+        }
+        for &(from, to) in &uses {
+            dependencies.add_edge(from, to, ());
+        }
+        // 模块 -> 所在的强连通分量（只记录多于一个模块的分量）
+        let cycles: HashMap<NodeIndex, usize> = tarjan_scc(&dependencies)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .enumerate()
+            .flat_map(|(id, component)| component.into_iter().map(move |node| (node, id)))
+            .collect();
+
+        for (from, to) in uses {
+            let kind = if cycles.get(&from).is_some_and(|id| cycles.get(&to) == Some(id)) {
+                EdgeKind::Cycle
+            } else {
+                EdgeKind::Use
+            };
+            graph.add_edge(from, to, kind);
+        }
+
+        graph
+    }
+
+    // 作用域（模块、函数或 impl 路径）所在的最内层已知模块
+    fn owning_module<'a>(nodes: &BTreeMap<&'a str, NodeIndex>, scope: &str) -> Option<&'a str> {
+        ImportTable::scopes(scope).find_map(|scope| nodes.get_key_value(scope).map(|(module, _)| *module))
+    }
+
+    // `crate::` 开头的路径直接查找；不以 `crate` 开头时可能是当前模块的子模块（2018 版的相对路径），
+    // 否则是外部 crate，不在图中显示
+    fn resolve<'a>(nodes: &BTreeMap<&'a str, NodeIndex>, from: &str, path: &str) -> Option<&'a str> {
+        let path = if path == "crate" || path.starts_with("crate::") {
+            path.to_string()
+        } else {
+            let first = path.split("::").next().unwrap_or(path);
+            if !nodes.contains_key(format!("{}::{}", from, first).as_str()) {
+                return None;
+            }
+            format!("{}::{}", from, path)
+        };
+        Self::owning_module(nodes, &path)
+    }
+}
+
+impl<'ast> Visit<'ast> for ModuleGraphPass {
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        if FunctionCollectorPass::is_test_module(node) {
+            return;
+        }
+        self.scope.push(node.ident.to_string());
+        self.modules.push(self.scope.join("::"));
+        visit::visit_item_mod(self, node);
+        self.scope.pop();
+    }
+}
@@ -0,0 +1,158 @@
+use crate::style::DotEscape;
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// GitHub/GitLab 查找 CODEOWNERS 的位置
+const CODEOWNERS_LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+const TEAM_COLORS: [&str; 10] = [
+    "#a6cee3", "#b2df8a", "#fdbf6f", "#cab2d6", "#ffff99",
+    "#8dd3c7", "#fccde5", "#d9d9d9", "#bebada", "#ccebc5",
+];
+
+// 没有负责人的文件
+const UNOWNED_COLOR: &str = "white";
+
+struct OwnerRule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+// CODEOWNERS 格式的负责人规则：`<模式> <负责人>...`，后面的规则优先
+pub struct CodeOwners {
+    // 规则中的路径相对于这个目录
+    base: PathBuf,
+    rules: Vec<OwnerRule>,
+}
+
+impl CodeOwners {
+    pub fn load(path: &Path, base: &Path) -> Result<Self> {
+        let rules = fs::read_to_string(path)?
+            .lines()
+            .filter_map(|line| {
+                let line = line.split('#').next().unwrap_or("").trim();
+                let mut parts = line.split_whitespace();
+                let pattern = Self::normalize(parts.next()?);
+                Some(OwnerRule { pattern, owners: parts.map(str::to_string).collect() })
+            })
+            .collect();
+        Ok(Self { base: base.canonicalize()?, rules })
+    }
+
+    // 从 start 向上查找 CODEOWNERS，直到 git 仓库根目录
+    pub fn discover(start: &Path) -> Option<Result<Self>> {
+        let start = start.canonicalize().ok()?;
+        for dir in start.ancestors() {
+            if let Some(path) = CODEOWNERS_LOCATIONS.iter().map(|location| dir.join(location)).find(|path| path.is_file()) {
+                return Some(Self::load(&path, dir));
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        None
+    }
+
+    // 与 .gitignore 相同：以 `/` 开头或中间含有 `/` 的模式相对于根目录，否则匹配任意层级；
+    // 以 `/` 结尾的模式只匹配目录下的文件
+    fn normalize(pattern: &str) -> String {
+        let anchored = pattern.starts_with('/') || pattern.trim_end_matches('/').contains('/');
+        let mut pattern = pattern.trim_start_matches('/').to_string();
+        if !anchored {
+            pattern = format!("**/{}", pattern);
+        }
+        if pattern.ends_with('/') {
+            pattern.push_str("**");
+        }
+        pattern
+    }
+
+    pub fn owners_of(&self, path: &Path) -> Option<&[String]> {
+        let absolute = match path.is_absolute() {
+            true => path.to_path_buf(),
+            false => std::env::current_dir().ok()?.join(path),
+        };
+        let absolute = absolute.canonicalize().unwrap_or(absolute);
+        let relative = absolute.strip_prefix(&self.base).ok()?.to_string_lossy().replace('\\', "/");
+
+        self.rules.iter()
+            .rev()
+            .find(|rule| {
+                // 模式同时匹配目录本身下的所有文件
+                Self::glob(rule.pattern.as_bytes(), relative.as_bytes())
+                    || Self::glob(format!("{}/**", rule.pattern).as_bytes(), relative.as_bytes())
+            })
+            .map(|rule| rule.owners.as_slice())
+            .filter(|owners| !owners.is_empty())
+    }
+
+    // 第一个负责人作为团队，用于着色
+    pub fn team_of(&self, path: &Path) -> Option<&str> {
+        self.owners_of(path).and_then(|owners| owners.first()).map(String::as_str)
+    }
+
+    pub fn color(team: Option<&str>) -> &'static str {
+        match team {
+            // FNV-1a，保证同一团队在不同图中颜色相同
+            Some(team) => {
+                let hash = team.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+                    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+                });
+                TEAM_COLORS[(hash % TEAM_COLORS.len() as u64) as usize]
+            }
+            None => UNOWNED_COLOR,
+        }
+    }
+
+    // 分组的背景色和标签；没有负责人的分组用红色虚线边框标出
+    pub fn cluster_attributes(team: Option<&str>, label: &str) -> String {
+        match team {
+            Some(team) => format!(
+                "        label=\"{}\";\n        style=rounded;\n        color=gray;\n        bgcolor=\"{}\";\n",
+                DotEscape::quoted(&format!("{}\n{}", label, team)),
+                Self::color(Some(team))
+            ),
+            None => format!(
+                "        label=\"{}\";\n        style=\"rounded,dashed\";\n        color=red;\n        bgcolor=\"{}\";\n",
+                DotEscape::quoted(&format!("{}\n(无负责人)", label)),
+                UNOWNED_COLOR
+            ),
+        }
+    }
+
+    // 团队与颜色的图例
+    pub fn legend_dot(teams: &BTreeSet<&str>) -> String {
+        let mut dot = String::from("    subgraph cluster_owners_legend {\n        label=\"负责人\";\n        style=rounded;\n        color=gray;\n");
+        for team in teams {
+            let _ = writeln!(
+                dot,
+                "        \"owner:{}\" [label=\"{}\", shape=box, style=filled, fillcolor=\"{}\"];",
+                DotEscape::quoted(team), DotEscape::quoted(team), Self::color(Some(team))
+            );
+        }
+        dot.push_str("    }\n\n");
+        dot
+    }
+
+    // `*` 和 `?` 不匹配 `/`，`**` 匹配任意多层目录
+    fn glob(pattern: &[u8], path: &[u8]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            [b'*', b'*', b'/', rest @ ..] => {
+                Self::glob(rest, path) || path.iter().enumerate()
+                    .filter(|(_, c)| **c == b'/')
+                    .any(|(i, _)| Self::glob(rest, &path[i + 1..]))
+            }
+            [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| Self::glob(rest, &path[i..])),
+            [b'*', rest @ ..] => {
+                let segment = path.iter().position(|c| *c == b'/').unwrap_or(path.len());
+                (0..=segment).any(|i| Self::glob(rest, &path[i..]))
+            }
+            [b'?', rest @ ..] => path.first().is_some_and(|c| *c != b'/') && Self::glob(rest, &path[1..]),
+            [c, rest @ ..] => path.first() == Some(c) && Self::glob(rest, &path[1..]),
+        }
+    }
+}
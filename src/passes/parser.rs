@@ -1,4 +1,6 @@
 use anyhow::{Context, Result};
+use proc_macro2::Span;
+use std::path::{Component, Path, PathBuf};
 use syn::{File, Item, ItemFn, Attribute};
 
 pub struct ParserPass;
@@ -17,9 +19,150 @@ impl ParserPass {
         })
     }
 
+    // 根据文件路径推断模块路径，例如 src/graph/mod.rs -> crate::graph
+    pub fn module_path(path: &Path) -> String {
+        let components: Vec<String> = path.with_extension("")
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+
+        let relative = match components.iter().rposition(|c| c == "src") {
+            Some(pos) => &components[pos + 1..],
+            None => &components[components.len().saturating_sub(1)..],
+        };
+
+        let mut module = vec!["crate".to_string()];
+        module.extend(relative.iter().cloned());
+        if matches!(module.last().map(String::as_str), Some("mod")) {
+            module.pop();
+        }
+        if module.len() == 2 && matches!(module[1].as_str(), "lib" | "main") {
+            module.pop();
+        }
+        module.join("::")
+    }
+
+    // 用于展示的文件路径，尽量相对于当前目录
+    pub fn display_path(path: &Path) -> String {
+        std::env::current_dir()
+            .ok()
+            .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+            .unwrap_or_else(|| path.to_path_buf())
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    // 文件中 `mod name;` 声明对应的源文件（支持 #[path]），不存在的文件会被忽略。
+    // crate 根文件和 mod.rs 的子模块位于同一目录，其他文件的子模块位于同名目录
+    pub fn declared_modules(path: &Path, ast: &File, is_root: bool) -> Vec<PathBuf> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let child_dir = if is_root || path.file_stem().is_some_and(|stem| stem == "mod") {
+            dir.to_path_buf()
+        } else {
+            dir.join(path.file_stem().unwrap_or_default())
+        };
+
+        ast.items.iter()
+            .filter_map(|item| match item {
+                Item::Mod(item_mod) if item_mod.content.is_none() => Some(item_mod),
+                _ => None,
+            })
+            .filter_map(|item_mod| {
+                let custom_path = item_mod.attrs.iter()
+                    .filter(|attr| attr.path().is_ident("path"))
+                    .find_map(|attr| match &attr.meta {
+                        syn::Meta::NameValue(nv) => match &nv.value {
+                            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) => Some(lit.value()),
+                            _ => None,
+                        },
+                        _ => None,
+                    });
+                if let Some(custom_path) = custom_path {
+                    return Some(dir.join(custom_path)).filter(|file| file.exists());
+                }
+
+                let name = item_mod.ident.to_string();
+                [child_dir.join(format!("{}.rs", name)), child_dir.join(&name).join("mod.rs")]
+                    .into_iter()
+                    .find(|file| file.exists())
+            })
+            .collect()
+    }
+
+    // 根据 span 从原始源码中截取代码片段，保留用户的原始格式和注释
+    pub fn snippet(source: &str, span: Span) -> Option<String> {
+        let text = source.get(span.byte_range())?;
+        if text.is_empty() {
+            return None;
+        }
+
+        // 后续行去掉与首行起始列相同的缩进
+        let indent = span.start().column;
+        let mut lines = text.lines();
+        let mut result = lines.next().unwrap_or_default().to_string();
+        for line in lines {
+            let leading = line.bytes().take_while(|b| *b == b' ' || *b == b'\t').count();
+            result.push('\n');
+            result.push_str(&line[leading.min(indent)..]);
+        }
+        Some(result)
+    }
+
+    // 程序入口：main、#[tokio::main] 一类的运行时入口以及导出给外部调用的函数
+    pub fn is_entry_fn(item: &ItemFn) -> bool {
+        item.sig.ident == "main"
+            || item.sig.abi.is_some()
+            || item.attrs.iter().any(|attr| {
+                let path = &Self::attr_path(attr);
+                path.is_ident("no_mangle")
+                    || path.is_ident("export_name")
+                    || (path.segments.len() > 1
+                        && path.segments.last().is_some_and(|segment| segment.ident == "main"))
+            })
+    }
+
+    // 属性的路径，Rust 2024 的 `#[unsafe(no_mangle)]`、`#[unsafe(export_name = "..")]` 取括号中的属性
+    fn attr_path(attr: &Attribute) -> syn::Path {
+        if let syn::Meta::List(list) = &attr.meta
+            && list.path.is_ident("unsafe")
+            && let Ok(inner) = list.parse_args::<syn::Meta>()
+        {
+            return inner.path().clone();
+        }
+        attr.path().clone()
+    }
+
     pub fn get_function_info(item: &ItemFn) -> (String, bool) {
         let name = item.sig.ident.to_string();
         let is_test = Self::is_test_fn(&item.attrs);
         (name, is_test)
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_entry(source: &str) -> bool {
+        ParserPass::is_entry_fn(&syn::parse_str(source).expect("function parses"))
+    }
+
+    #[test]
+    fn exported_functions_are_entries() {
+        assert!(is_entry("#[no_mangle] fn exported() {}"));
+        assert!(is_entry("#[export_name = \"run\"] fn exported() {}"));
+        assert!(is_entry("#[unsafe(no_mangle)] fn exported() {}"));
+        assert!(is_entry("#[unsafe(export_name = \"run\")] fn exported() {}"));
+        assert!(is_entry("#[tokio::main] async fn start() {}"));
+        assert!(is_entry("extern \"C\" fn callback() {}"));
+    }
+
+    #[test]
+    fn ordinary_functions_are_not_entries() {
+        assert!(!is_entry("fn helper() {}"));
+        assert!(!is_entry("#[inline] fn helper() {}"));
+        assert!(!is_entry("#[unsafe(link_section = \".text\")] fn helper() {}"));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::graph::glob_match;
+use std::path::{Component, Path, PathBuf};
+
+// 遍历源文件时跳过哪些文件和目录。--include / --exclude 的模式匹配相对于 root（crate 或 workspace 根目录）的路径：
+// `**` 匹配任意多级目录，`*`、`?` 不跨越 `/`；不含 `/` 的模式匹配路径中的任意一级目录或文件名
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    pub root: PathBuf,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    // 是否遵循 .gitignore 等忽略规则，并跳过 cargo 的构建输出目录和 git 子模块；--no-ignore 时为 false
+    pub respect_ignore: bool,
+    // cargo metadata 给出的实际构建输出目录（可能被 CARGO_TARGET_DIR 或 .cargo/config.toml 改到别处）
+    pub target_dir: Option<PathBuf>,
+}
+
+impl PathFilter {
+    // 是否跳过整个目录：构建输出目录、git 子模块（或嵌套的其他仓库）以及匹配 --exclude 的目录。
+    // --include 只对文件生效，否则 `src/core/**` 会把 src 目录本身排除掉
+    pub fn skips_dir(&self, dir: &Path) -> bool {
+        let ignored = self.respect_ignore
+            && (self.target_dir.as_deref() == Some(dir) || dir.join(".git").exists());
+        ignored || self.exclude.iter().any(|pattern| self.matches(pattern, dir))
+    }
+
+    // 文件是否参与分析：指定了 --include 时必须匹配其中之一，且不能匹配任何 --exclude，
+    // 也不能位于被跳过的目录中（不遍历目录时，如读取 git 中某个版本的文件列表）
+    pub fn allows(&self, file: &Path) -> bool {
+        let relative = file.strip_prefix(&self.root).unwrap_or(file);
+        let in_skipped_dir = relative.ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .any(|dir| self.skips_dir(&self.root.join(dir)));
+        !in_skipped_dir
+            && (self.include.is_empty() || self.include.iter().any(|pattern| self.matches(pattern, file)))
+            && !self.exclude.iter().any(|pattern| self.matches(pattern, file))
+    }
+
+    fn matches(&self, pattern: &str, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let segments: Vec<String> = relative.components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        if !pattern.contains('/') {
+            return segments.iter().any(|segment| glob_match(pattern, segment));
+        }
+        let pattern: Vec<&str> = pattern.split('/').filter(|segment| !segment.is_empty()).collect();
+        Self::match_segments(&pattern, &segments)
+    }
+
+    fn match_segments(pattern: &[&str], segments: &[String]) -> bool {
+        match pattern.split_first() {
+            None => segments.is_empty(),
+            Some((&"**", rest)) => (0..=segments.len()).any(|skip| Self::match_segments(rest, &segments[skip..])),
+            Some((first, rest)) => segments.split_first()
+                .is_some_and(|(segment, remaining)| glob_match(first, segment) && Self::match_segments(rest, remaining)),
+        }
+    }
+}
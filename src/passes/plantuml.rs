@@ -0,0 +1,391 @@
+use crate::passes::{StyledEdge, StyledGraph, StyledNode};
+use crate::style::{LabelSanitizer, NodeStyle, PlantUmlEscape, Theme, MAX_LINE_WIDTH};
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+// 输出 PlantUML 活动图，每个函数一个 `@startuml` 块。
+// 控制流能还原成结构化形式时使用 if/switch/while/repeat，否则退回旧版活动图的箭头语法
+pub struct PlantUmlRendererPass;
+
+impl PlantUmlRendererPass {
+    pub fn render(graph: &StyledGraph) -> String {
+        let mut function_nodes: BTreeMap<Option<usize>, Vec<&StyledNode>> = BTreeMap::new();
+        for node in &graph.nodes {
+            function_nodes.entry(node.function).or_default().push(node);
+        }
+
+        let mut out = String::new();
+        for (function, nodes) in &function_nodes {
+            let title = match function {
+                Some(function) => graph.names.get(function).cloned().unwrap_or_else(|| format!("fn {}", function)),
+                None => "detached".to_string(),
+            };
+            let members: HashSet<_> = nodes.iter().map(|node| node.id).collect();
+            let edges: Vec<&StyledEdge> = graph.edges.iter()
+                .filter(|edge| members.contains(&edge.from) && members.contains(&edge.to))
+                .collect();
+
+            let body = Activity::new(nodes, &edges)
+                .structured()
+                .unwrap_or_else(|| Self::arrows(nodes, &edges));
+            let _ = writeln!(
+                out,
+                "@startuml\n{}title {}\n{}@enduml\n",
+                Self::skinparams(&graph.theme),
+                PlantUmlEscape::text(&title),
+                body
+            );
+        }
+        out
+    }
+
+    // 背景、字体和默认箭头颜色取自主题，节点颜色写在各个动作上
+    fn skinparams(theme: &Theme) -> String {
+        format!(
+            "skinparam backgroundColor {}\nskinparam defaultFontName \"{}\"\nskinparam defaultFontColor {}\nskinparam ArrowColor {}\n",
+            Self::color(&theme.background),
+            theme.font.name.replace('"', "'"),
+            Self::color(&theme.font.color),
+            Self::color(&theme.edge.color)
+        )
+    }
+
+    // PlantUML 的颜色写作 `#name` 或 `#rrggbb`
+    fn color(color: &str) -> String {
+        format!("#{}", NodeStyle::css_color(color).trim_start_matches('#'))
+    }
+
+    // 旧版活动图语法：开始和结束节点都写作 `(*)`，其他节点第一次出现时声明别名。
+    // 带引号的标签中无法转义双引号，改用单引号
+    fn arrows(nodes: &[&StyledNode], edges: &[&StyledEdge]) -> String {
+        let nodes: HashMap<_, _> = nodes.iter().map(|node| (node.id, *node)).collect();
+        // 开始节点的出边在最前面，其余按起点排列
+        let mut edges = edges.to_vec();
+        edges.sort_by_key(|edge| (nodes[&edge.from].shape != "oval", edge.from.index()));
+        let mut declared = HashSet::new();
+        let mut reference = |index: NodeIndex| {
+            let node = nodes[&index];
+            if node.shape == "oval" {
+                "(*)".to_string()
+            } else if declared.insert(index) {
+                let label = PlantUmlEscape::lines(&LabelSanitizer::wrap(&node.label, MAX_LINE_WIDTH)).replace('"', "'");
+                format!("\"{}\" as n{}", label, index.index())
+            } else {
+                format!("n{}", index.index())
+            }
+        };
+
+        let mut out = String::new();
+        for edge in edges {
+            let from = reference(edge.from);
+            let to = reference(edge.to);
+            let label = if edge.label.is_empty() {
+                String::new()
+            } else {
+                format!("[{}] ", PlantUmlEscape::text(&edge.label))
+            };
+            let _ = writeln!(out, "{} --> {}{}", from, label, to);
+        }
+        out
+    }
+}
+
+// 把一个函数的控制流图还原成结构化的活动图，遇到无法表示的跳转时放弃
+struct Activity<'a> {
+    nodes: HashMap<NodeIndex, &'a StyledNode>,
+    successors: HashMap<NodeIndex, Vec<&'a StyledEdge>>,
+    predecessors: HashMap<NodeIndex, Vec<NodeIndex>>,
+    emitted: HashSet<NodeIndex>,
+    // 当前所在的循环：循环节点和循环之后的第一个节点，由内到外排在末尾
+    loops: Vec<(NodeIndex, Option<NodeIndex>)>,
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl<'a> Activity<'a> {
+    fn new(nodes: &[&'a StyledNode], edges: &[&'a StyledEdge]) -> Self {
+        let mut successors: HashMap<NodeIndex, Vec<&StyledEdge>> = HashMap::new();
+        let mut predecessors: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+        for edge in edges {
+            successors.entry(edge.from).or_default().push(edge);
+            predecessors.entry(edge.to).or_default().push(edge.from);
+        }
+        Self {
+            nodes: nodes.iter().map(|node| (node.id, *node)).collect(),
+            successors,
+            predecessors,
+            emitted: HashSet::new(),
+            loops: Vec::new(),
+            lines: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    fn structured(mut self) -> Option<String> {
+        let start = self.nodes.values()
+            .filter(|node| node.shape == "oval" && !self.successors_of(node.id).is_empty())
+            .map(|node| node.id)
+            .min()?;
+        self.sequence(start, None, true)?;
+        // 不可达的代码无法放进结构化的活动图
+        if self.nodes.keys().any(|index| !self.emitted.contains(index) && !self.is_end(*index)) {
+            return None;
+        }
+        Some(self.lines.iter().map(|line| format!("{}\n", line)).collect())
+    }
+
+    fn successors_of(&self, index: NodeIndex) -> Vec<&'a StyledEdge> {
+        self.successors.get(&index).cloned().unwrap_or_default()
+    }
+
+    fn is_end(&self, index: NodeIndex) -> bool {
+        self.nodes[&index].shape == "oval" && self.successors_of(index).is_empty()
+    }
+
+    fn line(&mut self, line: String) {
+        self.lines.push(format!("{}{}", "  ".repeat(self.depth), line));
+    }
+
+    fn label(&self, index: NodeIndex) -> String {
+        PlantUmlEscape::lines(&LabelSanitizer::wrap(&self.nodes[&index].label, MAX_LINE_WIDTH))
+    }
+
+    fn action(&mut self, index: NodeIndex) {
+        let color = match self.nodes[&index].fillcolor.as_str() {
+            "" | "white" => String::new(),
+            color => PlantUmlRendererPass::color(color),
+        };
+        let label = self.label(index);
+        self.line(format!("{}:{};", color, label));
+    }
+
+    fn arrow(&mut self, edge: &StyledEdge) {
+        if !edge.label.is_empty() {
+            self.line(format!("-> {};", PlantUmlEscape::text(&edge.label)));
+        }
+    }
+
+    // 从 current 开始顺序输出，直到 stop；tail 表示这段之后所在循环体再没有其他语句，
+    // 只有这时回到循环节点才能表示为一次正常的迭代结束
+    fn sequence(&mut self, mut current: NodeIndex, stop: Option<NodeIndex>, tail: bool) -> Option<()> {
+        loop {
+            if Some(current) == stop {
+                return Some(());
+            }
+            if let Some(&(head, exit)) = self.loops.last() {
+                if current == head {
+                    return tail.then_some(());
+                }
+                if Some(current) == exit {
+                    self.line("break".to_string());
+                    return Some(());
+                }
+            }
+            // 跳到外层循环（带标签的 break/continue）无法表示
+            if self.loops.iter().any(|&(head, exit)| current == head || Some(current) == exit) {
+                return None;
+            }
+            if self.is_end(current) {
+                self.emitted.insert(current);
+                self.line("stop".to_string());
+                return Some(());
+            }
+            if !self.emitted.insert(current) {
+                return None;
+            }
+
+            let edges = self.successors_of(current);
+            let shape = self.nodes[&current].shape.clone();
+            let next = match (shape.as_str(), edges.len()) {
+                ("oval", 1) => {
+                    self.line("start".to_string());
+                    self.arrow(edges[0]);
+                    Some(edges[0].to)
+                }
+                ("diamond", n) if n >= 2 => self.branch(current, &edges, tail)?,
+                ("hexagon", _) => self.repeat(current, &edges)?,
+                (_, 0) => {
+                    self.action(current);
+                    self.line("end".to_string());
+                    None
+                }
+                (_, 1) => {
+                    self.action(current);
+                    self.arrow(edges[0]);
+                    Some(edges[0].to)
+                }
+                _ => return None,
+            };
+            match next {
+                Some(next) => current = next,
+                None => return Some(()),
+            }
+        }
+    }
+
+    // 函数结束节点以及当前所在循环的入口和出口，分支不会在这些节点汇合
+    fn exits(&self) -> HashSet<NodeIndex> {
+        self.nodes.keys()
+            .copied()
+            .filter(|&index| self.is_end(index))
+            .chain(self.loops.iter().flat_map(|&(head, exit)| std::iter::once(head).chain(exit)))
+            .collect()
+    }
+
+    // 从 from 出发、不经过 blocked 中节点能到达的节点（包括 from），按广度优先顺序排列
+    fn reach(&self, from: NodeIndex, blocked: &HashSet<NodeIndex>) -> Vec<NodeIndex> {
+        let mut seen = HashSet::new();
+        let mut order = Vec::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(index) = queue.pop_front() {
+            if blocked.contains(&index) || !seen.insert(index) {
+                continue;
+            }
+            order.push(index);
+            queue.extend(self.successors_of(index).iter().map(|edge| edge.to));
+        }
+        order
+    }
+
+    // 分支的汇合点：能从最多分支到达（至少两个）的节点中离判断节点最近的一个
+    fn join(&self, cond: NodeIndex, edges: &[&StyledEdge]) -> Option<NodeIndex> {
+        let mut blocked = self.exits();
+        blocked.insert(cond);
+        let reaches: Vec<HashSet<_>> = edges.iter()
+            .map(|edge| self.reach(edge.to, &blocked).into_iter().collect())
+            .collect();
+        let order: Vec<_> = edges.iter()
+            .flat_map(|edge| self.reach(edge.to, &blocked))
+            .collect();
+        order.iter()
+            .map(|index| (*index, reaches.iter().filter(|reach| reach.contains(index)).count()))
+            .filter(|&(_, count)| count >= 2)
+            .fold(None, |best: Option<(NodeIndex, usize)>, (index, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((index, count)),
+            })
+            .map(|(index, _)| index)
+    }
+
+    fn branch(&mut self, cond: NodeIndex, edges: &[&'a StyledEdge], tail: bool) -> Option<Option<NodeIndex>> {
+        let mut join = self.join(cond, edges);
+        // 提前返回的 if：then 分支不再汇合时，把 else 分支的目标当作汇合点，后续语句不必嵌套在 else 中
+        if join.is_none() && edges.len() == 2 && !self.exits().contains(&edges[1].to) && !self.continues(edges[0].to) {
+            join = Some(edges[1].to);
+        }
+        let branch_tail = tail && join.is_none();
+        let label = self.label(cond);
+
+        if edges.len() == 2 {
+            self.line(format!("if ({}) then ({})", label, PlantUmlEscape::text(&edges[0].label)));
+            self.depth += 1;
+            self.sequence(edges[0].to, join, branch_tail)?;
+            self.depth -= 1;
+            self.line(format!("else ({})", PlantUmlEscape::text(&edges[1].label)));
+            self.depth += 1;
+            self.sequence(edges[1].to, join, branch_tail)?;
+            self.depth -= 1;
+            self.line("endif".to_string());
+        } else {
+            self.line(format!("switch ({})", label));
+            for edge in edges {
+                self.line(format!("case ({})", PlantUmlEscape::text(&edge.label)));
+                self.depth += 1;
+                self.sequence(edge.to, join, branch_tail)?;
+                self.depth -= 1;
+            }
+            self.line("endswitch".to_string());
+        }
+        Some(join)
+    }
+
+    // 分支中是否有回到当前循环入口的边（continue 或循环体结束）
+    fn continues(&self, from: NodeIndex) -> bool {
+        let Some(&(head, _)) = self.loops.last() else {
+            return false;
+        };
+        let blocked = self.exits();
+        self.reach(from, &blocked).iter()
+            .any(|&index| self.successors_of(index).iter().any(|edge| edge.to == head))
+    }
+
+    // while/for 有进入循环体和退出循环两条出边，输出为 while；loop 只有一条出边，输出为 repeat
+    fn repeat(&mut self, head: NodeIndex, edges: &[&'a StyledEdge]) -> Option<Option<NodeIndex>> {
+        let body = self.loop_body(head);
+        let label = self.label(head);
+        // 合并基本块会重新添加边，不能依赖出边的顺序区分进入循环体和退出循环
+        let (inside, outside): (Vec<&StyledEdge>, Vec<&StyledEdge>) = edges.iter()
+            .partition(|edge| body.contains(&edge.to) || edge.to == head);
+        match (inside.as_slice(), outside.as_slice()) {
+            ([enter], [leave]) => {
+                self.loops.push((head, Some(leave.to)));
+                let is = if enter.label.is_empty() { String::new() } else { format!(" is ({})", PlantUmlEscape::text(&enter.label)) };
+                self.line(format!("while ({}){}", label, is));
+                self.depth += 1;
+                self.sequence(enter.to, None, true)?;
+                self.depth -= 1;
+                self.line(format!("endwhile ({})", PlantUmlEscape::text(&leave.label)));
+                self.loops.pop();
+                Some(Some(leave.to))
+            }
+            ([enter], []) => {
+                let exit = self.loop_exit(head, &body);
+                self.loops.push((head, exit));
+                self.line("repeat".to_string());
+                self.depth += 1;
+                self.sequence(enter.to, None, true)?;
+                self.depth -= 1;
+                self.line(format!("repeat while ({})", label));
+                self.loops.pop();
+                Some(exit)
+            }
+            _ => None,
+        }
+    }
+
+    // 循环体：从循环节点出发能到达、且能回到循环节点的节点
+    fn loop_body(&self, head: NodeIndex) -> HashSet<NodeIndex> {
+        let blocked = HashSet::from([head]);
+        let forward: HashSet<_> = self.successors_of(head).iter()
+            .flat_map(|edge| self.reach(edge.to, &blocked))
+            .collect();
+        let mut backward = HashSet::new();
+        let mut queue: VecDeque<_> = self.predecessors.get(&head).cloned().unwrap_or_default().into();
+        while let Some(index) = queue.pop_front() {
+            if index == head || !backward.insert(index) {
+                continue;
+            }
+            queue.extend(self.predecessors.get(&index).into_iter().flatten());
+        }
+        forward.intersection(&backward).copied().collect()
+    }
+
+    // loop 只能通过 break 离开：离开循环体的边指向的节点中（不算函数结束和终止执行的节点）
+    // 只有一个时就是循环之后的第一个节点；有多个时取它们共同到达的最近节点
+    fn loop_exit(&self, head: NodeIndex, body: &HashSet<NodeIndex>) -> Option<NodeIndex> {
+        let mut targets: Vec<NodeIndex> = Vec::new();
+        for index in body.iter().copied().chain(std::iter::once(head)) {
+            for edge in self.successors_of(index) {
+                let leaves = !body.contains(&edge.to) && edge.to != head;
+                if leaves && !self.successors_of(edge.to).is_empty() && !targets.contains(&edge.to) {
+                    targets.push(edge.to);
+                }
+            }
+        }
+        targets.sort();
+        match targets.as_slice() {
+            [] => None,
+            [target] => Some(*target),
+            _ => {
+                let mut blocked = self.exits();
+                blocked.insert(head);
+                let reaches: Vec<HashSet<_>> = targets.iter()
+                    .map(|&target| self.reach(target, &blocked).into_iter().collect())
+                    .collect();
+                self.reach(targets[0], &blocked).into_iter()
+                    .find(|index| reaches.iter().all(|reach| reach.contains(index)))
+            }
+        }
+    }
+}
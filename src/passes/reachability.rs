@@ -0,0 +1,158 @@
+use crate::passes::{CollectedFunction, ImportTable, ScipIndex};
+use std::collections::{HashMap, VecDeque};
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprCall, ExprMethodCall};
+
+// 函数体内的一次调用，例如 `helper`、`Type::new`，或方法调用 `push`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    pub target: String,
+    pub is_method: bool,
+    // 被调用名称（路径末段或方法名）的位置，用于在 SCIP 索引中查找
+    pub line: usize,
+    pub column: usize,
+}
+
+impl CallSite {
+    // 无法解析时是否作为外部函数显示；`Some(..)`、元组结构体构造等单段大写名称不算调用
+    pub fn is_external_candidate(&self) -> bool {
+        !self.is_method
+            && (self.target.contains("::")
+                || !self.target.starts_with(|c: char| c.is_uppercase()))
+    }
+}
+
+#[derive(Default)]
+struct CallCollector {
+    calls: Vec<CallSite>,
+}
+
+impl<'ast> Visit<'ast> for CallCollector {
+    fn visit_expr_call(&mut self, node: &'ast ExprCall) {
+        if let Expr::Path(func) = &*node.func
+            && let Some(last) = func.path.segments.last()
+        {
+            let path = func.path.segments.iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>()
+                .join("::");
+            let start = last.ident.span().start();
+            self.calls.push(CallSite { target: path, is_method: false, line: start.line, column: start.column });
+        }
+        visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast ExprMethodCall) {
+        let start = node.method.span().start();
+        self.calls.push(CallSite {
+            target: node.method.to_string(),
+            is_method: true,
+            line: start.line,
+            column: start.column,
+        });
+        visit::visit_expr_method_call(self, node);
+    }
+}
+
+pub struct ReachabilityPass;
+
+impl ReachabilityPass {
+    pub fn calls(func: &CollectedFunction) -> Vec<CallSite> {
+        let mut collector = CallCollector::default();
+        collector.visit_block(&func.item.block);
+        collector.calls
+    }
+
+    // 按名称尽力解析调用目标：完整路径或路径后缀相同即视为匹配
+    pub fn resolve<'a>(callee: &str, functions: &'a [CollectedFunction]) -> Vec<&'a CollectedFunction> {
+        let callee = callee.trim_start_matches("crate::").trim_start_matches("self::");
+        functions.iter()
+            .filter(|func| {
+                func.path == callee
+                    || func.path.strip_suffix(callee).is_some_and(|prefix| prefix.ends_with("::"))
+            })
+            .collect()
+    }
+
+    // 结合 SCIP 索引和 `use` 语句解析调用目标，依次尝试：
+    // 0. 索引中有该调用点时直接使用索引的结果（精确处理方法调用、trait 分发和重导出）
+    // 1. 用调用者作用域内的 use（包括 glob）展开后按完整路径匹配，指向其他 crate 的 use 视为外部调用
+    // 2. 从调用者路径由内向外拼接（嵌套函数、同模块函数）
+    // 3. 退回到路径后缀匹配
+    pub fn resolve_call<'a>(
+        call: &CallSite,
+        caller: &CollectedFunction,
+        functions: &'a [CollectedFunction],
+        imports: &ImportTable,
+        index: Option<&ScipIndex>,
+    ) -> Vec<&'a CollectedFunction> {
+        if let (Some(index), Some(file)) = (index, &caller.file)
+            && let Some(locations) = index.definitions_of(file, call)
+        {
+            return functions.iter().filter(|func| ScipIndex::is_defined_at(func, &locations)).collect();
+        }
+
+        let exact = |path: &str| -> Vec<&'a CollectedFunction> {
+            functions.iter().filter(|func| func.path == path).collect()
+        };
+
+        if !call.is_method {
+            if let Some(expanded) = imports.expand(&caller.path, &call.target) {
+                if expanded.starts_with("crate::") {
+                    return exact(&expanded);
+                }
+                // `use module::f` 可能是相对于当前模块的路径，否则视为其他 crate
+                return ImportTable::scopes(&caller.path)
+                    .map(|scope| exact(&format!("{}::{}", scope, expanded)))
+                    .find(|found| !found.is_empty())
+                    .unwrap_or_default();
+            }
+            if call.target.starts_with("crate::") {
+                return exact(&call.target);
+            }
+            for scope in ImportTable::scopes(&caller.path) {
+                let found = exact(&format!("{}::{}", scope, call.target));
+                if !found.is_empty() {
+                    return found;
+                }
+                for glob in imports.globs(scope) {
+                    let found = exact(&format!("{}::{}", glob, call.target));
+                    if !found.is_empty() {
+                        return found;
+                    }
+                }
+            }
+        }
+
+        Self::resolve(&call.target, functions)
+    }
+
+    // 从所有入口函数出发做广度优先搜索，得到每个可达函数的调用距离
+    pub fn entry_distances(
+        functions: &[CollectedFunction],
+        imports: &ImportTable,
+        index: Option<&ScipIndex>,
+    ) -> HashMap<String, usize> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for func in functions.iter().filter(|func| func.is_entry) {
+            distances.insert(func.path.clone(), 0);
+            queue.push_back(func);
+        }
+
+        while let Some(func) = queue.pop_front() {
+            let distance = distances[&func.path];
+            for call in Self::calls(func) {
+                for target in Self::resolve_call(&call, func, functions, imports, index) {
+                    if !distances.contains_key(&target.path) {
+                        distances.insert(target.path.clone(), distance + 1);
+                        queue.push_back(target);
+                    }
+                }
+            }
+        }
+
+        distances
+    }
+}
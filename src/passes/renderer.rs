@@ -1,13 +1,82 @@
+use crate::graph::SourceSpan;
+use crate::passes::CodeOwners;
 use crate::passes::styler::{StyledGraph, StyledNode};
-use std::collections::{HashSet, HashMap, BTreeMap};
+use crate::style::{DotEscape, LabelSanitizer, SyntaxHighlighter, Theme, MAX_LINE_WIDTH};
+use std::collections::{BTreeSet, HashSet, HashMap, BTreeMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use petgraph::graph::NodeIndex;
 use petgraph::visit::EdgeRef;
 
+// DOT 中的节点和子图名称。函数的前缀是命名空间和函数完整路径的哈希，节点按函数内的先后顺序编号，
+// 因此与同一图中的其他文件和函数无关，多个图合并或拼接在一起时也不会冲突
+struct DotIds {
+    functions: HashMap<usize, String>,
+    nodes: HashMap<NodeIndex, String>,
+}
+
+impl DotIds {
+    fn new(graph: &StyledGraph) -> Self {
+        let mut functions: Vec<usize> = graph.nodes.iter().filter_map(|node| node.function).collect();
+        functions.sort();
+        functions.dedup();
+
+        // 同一命名空间中的同名函数（如不同 cfg 下的两个版本）加上序号区分
+        let mut used = HashSet::new();
+        let functions: HashMap<usize, String> = functions.into_iter()
+            .map(|function| {
+                let namespace = graph.namespaces.get(&function).map_or("", String::as_str);
+                let name = graph.names.get(&function).map_or("", String::as_str);
+                let base = format!("{:08x}", DotRendererPass::hash(&(namespace, name)) as u32);
+                let mut prefix = base.clone();
+                for i in 2.. {
+                    if used.insert(prefix.clone()) {
+                        break;
+                    }
+                    prefix = format!("{}_{}", base, i);
+                }
+                (function, prefix)
+            })
+            .collect();
+
+        let mut ordered: Vec<_> = graph.nodes.iter().collect();
+        ordered.sort_by_key(|node| node.id);
+        let mut counters: HashMap<Option<usize>, usize> = HashMap::new();
+        let nodes = ordered.into_iter()
+            .map(|node| {
+                let counter = counters.entry(node.function).or_insert(0);
+                let id = match node.function.and_then(|function| functions.get(&function)) {
+                    Some(prefix) => format!("node_{}_{}", prefix, counter),
+                    None => format!("node_{}", node.id.index()),
+                };
+                *counter += 1;
+                (node.id, id)
+            })
+            .collect();
+        Self { functions, nodes }
+    }
+}
+
 pub struct DotRendererPass;
 
 impl DotRendererPass {
     pub fn render(graph: &StyledGraph) -> String {
+        Self::render_grouped(graph, None)
+    }
+
+    // 每个节点在 render 输出的 DOT 中的名称
+    pub fn node_ids(graph: &StyledGraph) -> HashMap<NodeIndex, String> {
+        DotIds::new(graph).nodes
+    }
+
+    // 合并后的图每个命名空间（模块）一个分组框，按负责团队为分组框着色并附加团队图例；
+    // teams 为命名空间 -> 负责团队
+    pub fn render_with_owners(graph: &StyledGraph, teams: &HashMap<String, Option<String>>) -> String {
+        Self::render_grouped(graph, Some(teams))
+    }
+
+    fn render_grouped(graph: &StyledGraph, teams: Option<&HashMap<String, Option<String>>>) -> String {
         let mut dot = String::from("digraph G {\n");
+        let theme = &graph.theme;
         
         // 添加全局属性
         dot.push_str("    graph [\n");
@@ -21,12 +90,14 @@ impl DotRendererPass {
         dot.push_str("        layout=dot;\n");         // 使用dot布局引擎
         dot.push_str("        newrank=true;\n");       // 使用新的rank分配算法
         dot.push_str("        pad=0.3;\n");           // 图的内边距
+        dot.push_str(&format!("        bgcolor=\"{}\";\n", theme.background));
         dot.push_str("    ];\n\n");
 
         // 添加全局节点属性
         dot.push_str("    node [\n");
-        dot.push_str("        fontname=\"Arial\";\n");
-        dot.push_str("        fontsize=10;\n");
+        dot.push_str(&format!("        fontname=\"{}\";\n", DotEscape::quoted(&theme.font.name)));
+        dot.push_str(&format!("        fontsize={};\n", theme.font.size));
+        dot.push_str(&format!("        fontcolor=\"{}\";\n", theme.font.color));
         dot.push_str("        margin=0.2;\n");         // 节点内边距
         dot.push_str("        height=0.4;\n");         // 最小高度
         dot.push_str("        width=0.4;\n");          // 最小宽度
@@ -36,8 +107,9 @@ impl DotRendererPass {
 
         // 添加全局边属性
         dot.push_str("    edge [\n");
-        dot.push_str("        fontname=\"Arial\";\n");
-        dot.push_str("        fontsize=9;\n");
+        dot.push_str(&format!("        fontname=\"{}\";\n", DotEscape::quoted(&theme.font.name)));
+        dot.push_str(&format!("        fontsize={};\n", theme.font.edge_size));
+        dot.push_str(&format!("        fontcolor=\"{}\";\n", theme.font.color));
         dot.push_str("        dir=forward;\n");
         dot.push_str("        arrowsize=0.7;\n");      // 箭头大小
         dot.push_str("        penwidth=1.0;\n");       // 线宽
@@ -47,132 +119,257 @@ impl DotRendererPass {
         dot.push_str("        tailclip=true;\n");      // 箭头在节点边界结束
         dot.push_str("    ];\n\n");
         
-        // 收集所有有效的节点ID
-        let valid_nodes: HashSet<NodeIndex> = graph.nodes.iter()
-            .map(|node| node.id)
+        // 节点、函数子图和边都按源码位置输出，同样的输入每次得到完全相同的 DOT
+        let ordered = Self::source_order(graph);
+        let positions: HashMap<NodeIndex, usize> = ordered.iter()
+            .enumerate()
+            .map(|(position, node)| (node.id, position))
             .collect();
 
-        // 按函数分组节点
-        let mut function_nodes: BTreeMap<String, Vec<&StyledNode>> = BTreeMap::new();
-        for node in &graph.nodes {
-            let func_name = Self::get_function_name(&node.label);
-            function_nodes.entry(func_name).or_default().push(node);
-        }
+        let ids = DotIds::new(graph);
 
-        // 添加节点并设置rank约束
-        for (func_name, nodes) in &function_nodes {
-            // 创建子图以保持函数内的节点在一起
-            dot.push_str(&format!("    subgraph cluster_{} {{\n", func_name.replace(" ", "_")));
-            dot.push_str("        style=invis;\n");  // 使子图边框不可见
-
-            // 添加函数内的所有节点
-            for node in nodes {
-                let escaped_label = Self::process_label(&node.label);
-                dot.push_str(&format!(
-                    "        node_{} [label=\"{}\", shape=\"{}\", style=\"{}\", fillcolor=\"{}\", color=\"black\"];\n",
-                    node.id.index(),
-                    escaped_label,
-                    node.shape,
-                    node.style,
-                    node.fillcolor
-                ));
+        // 按函数编号分组节点，同名函数不会被合并；函数按开始节点的位置排列
+        let mut functions: Vec<Option<usize>> = Vec::new();
+        let mut function_nodes: BTreeMap<Option<usize>, Vec<&StyledNode>> = BTreeMap::new();
+        for node in ordered {
+            let nodes = function_nodes.entry(node.function).or_default();
+            if nodes.is_empty() {
+                functions.push(node.function);
             }
+            nodes.push(node);
+        }
 
-            // 对Start和End节点进行特殊处理
-            let mut start_nodes = Vec::new();
-            let mut end_nodes = Vec::new();
-            for node in nodes {
-                if node.label.starts_with("Start") {
-                    start_nodes.push(node);
-                } else if node.label.starts_with("End") {
-                    end_nodes.push(node);
-                }
-            }
+        // 闭包和嵌套函数的子图嵌套在所在函数的子图中
+        let mut children: BTreeMap<Option<usize>, Vec<usize>> = BTreeMap::new();
+        for function in functions.iter().flatten() {
+            let parent = graph.parents.get(function).copied().filter(|parent| function_nodes.contains_key(&Some(*parent)));
+            children.entry(parent).or_default().push(*function);
+        }
 
-            // 设置Start节点的rank
-            if !start_nodes.is_empty() {
-                dot.push_str("        { rank=source; ");
-                for node in &start_nodes {
-                    dot.push_str(&format!("node_{} ", node.id.index()));
+        // 合并后的图中，顶层函数按所在的命名空间（模块）分组，保持各命名空间第一次出现的顺序
+        let mut namespaces: Vec<(Option<&str>, Vec<Option<usize>>)> = Vec::new();
+        for function in &functions {
+            if function.is_none_or(|id| children.get(&None).is_some_and(|roots| roots.contains(&id))) {
+                let namespace = function.and_then(|id| graph.namespaces.get(&id)).map(String::as_str);
+                match namespaces.iter_mut().find(|(existing, _)| *existing == namespace) {
+                    Some((_, functions)) => functions.push(*function),
+                    None => namespaces.push((namespace, vec![*function])),
                 }
-                dot.push_str("}\n");
             }
+        }
 
-            // 设置End节点的rank
-            if !end_nodes.is_empty() {
-                dot.push_str("        { rank=sink; ");
-                for node in &end_nodes {
-                    dot.push_str(&format!("node_{} ", node.id.index()));
-                }
-                dot.push_str("}\n");
+        // 添加节点，每个函数一个子图
+        for (namespace, functions) in &namespaces {
+            let mut clusters = String::new();
+            for function in functions {
+                Self::render_cluster(&mut clusters, graph, *function, &function_nodes, &children, &ids, 1);
+            }
+            let Some(namespace) = namespace else {
+                dot.push_str(&clusters);
+                continue;
+            };
+            dot.push_str(&format!("    subgraph cluster_ns_{:08x} {{\n", Self::hash(namespace) as u32));
+            dot.push_str(&Self::namespace_attributes(namespace, teams, theme));
+            for line in clusters.lines() {
+                dot.push_str("    ");
+                dot.push_str(line);
+                dot.push('\n');
             }
+            dot.push_str("    }\n\n");
+        }
 
-            dot.push_str("    }\n");
+        // 添加边，按两端节点的位置排列
+        let mut edges: Vec<_> = graph.edges.iter()
+            .filter_map(|edge| Some(((*positions.get(&edge.from)?, *positions.get(&edge.to)?), edge)))
+            .collect();
+        edges.sort_by_key(|(endpoints, _)| *endpoints);
+        for (_, edge) in edges {
+            let escaped_label = Self::process_label(&edge.label);
+            dot.push_str(&format!(
+                "    {} -> {} [label=\"{}\", color=\"{}\", style=\"{}\", weight=1, constraint=true];\n",
+                ids.nodes[&edge.from],
+                ids.nodes[&edge.to],
+                escaped_label,
+                edge.color,
+                edge.style
+            ));
         }
 
-        // 添加边，确保边不会重叠
-        for edge in &graph.edges {
-            if valid_nodes.contains(&edge.from) && valid_nodes.contains(&edge.to) {
-                let escaped_label = Self::process_label(&edge.label);
-                dot.push_str(&format!(
-                    "    node_{} -> node_{} [label=\"{}\", color=\"{}\", style=\"{}\", weight=1, constraint=true];\n",
-                    edge.from.index(),
-                    edge.to.index(),
-                    escaped_label,
-                    edge.color,
-                    edge.style
-                ));
-            }
+        if let Some(teams) = teams {
+            dot.push_str(&CodeOwners::legend_dot(&teams.values().flatten().map(String::as_str).collect::<BTreeSet<_>>()));
         }
         
         dot.push_str("}\n");
         dot
     }
 
-    fn get_function_name(label: &str) -> String {
-        if label.starts_with("Start: ") {
-            label["Start: ".len()..].to_string()
-        } else if label.starts_with("End: ") {
-            label["End: ".len()..].to_string()
-        } else {
-            // 对于其他节点，尝试从标签中提取函数名
-            // 这里可能需要根据实际标签格式进行调整
-            label.split('\n').next().unwrap_or("default").to_string()
+    // 命名空间分组框的标签和边框；有负责团队信息时按团队着色，没有负责人的用红色虚线标出
+    fn namespace_attributes(namespace: &str, teams: Option<&HashMap<String, Option<String>>>, theme: &Theme) -> String {
+        let label = namespace.replace('\\', "/");
+        let mut attributes = match teams {
+            Some(teams) => CodeOwners::cluster_attributes(teams.get(namespace).and_then(|team| team.as_deref()), &label),
+            None => format!(
+                "        label=\"{}\";\n        style=rounded;\n        color=\"{}\";\n",
+                DotEscape::quoted(&label),
+                theme.cluster_color
+            ),
+        };
+        attributes.push_str("        fontsize=12;\n        margin=16;\n");
+        attributes
+    }
+
+    fn render_cluster(
+        dot: &mut String,
+        graph: &StyledGraph,
+        function: Option<usize>,
+        function_nodes: &BTreeMap<Option<usize>, Vec<&StyledNode>>,
+        children: &BTreeMap<Option<usize>, Vec<usize>>,
+        ids: &DotIds,
+        depth: usize,
+    ) {
+        let indent = "    ".repeat(depth);
+        // 每个函数一个子图，分组框以函数签名为标签，嵌套的闭包和函数用虚线框标出；
+        // 不属于任何函数的节点放在不可见的子图中
+        match function {
+            Some(id) => {
+                dot.push_str(&format!("{}subgraph cluster_fn_{} {{\n", indent, ids.functions[&id]));
+                let label = graph.signatures.get(&id).or_else(|| graph.names.get(&id)).map_or("", String::as_str);
+                let style = if depth == 1 { "rounded" } else { "rounded,dashed" };
+                dot.push_str(&format!("{}    label=\"{}\";\n", indent, Self::process_label(label)));
+                dot.push_str(&format!("{}    labeljust=l;\n", indent));
+                dot.push_str(&format!("{}    fontname=\"{}\";\n", indent, DotEscape::quoted(&graph.theme.font.name)));
+                dot.push_str(&format!("{}    fontsize={};\n", indent, graph.theme.font.edge_size));
+                dot.push_str(&format!("{}    fontcolor=\"{}\";\n", indent, graph.theme.font.color));
+                dot.push_str(&format!("{}    style=\"{}\";\n{}    color=\"{}\";\n", indent, style, indent, graph.theme.cluster_color));
+            }
+            None => {
+                dot.push_str(&format!("{}subgraph cluster_detached {{\n", indent));
+                dot.push_str(&format!("{}    style=invis;\n", indent));
+            }
         }
+
+        // 添加函数内的所有节点
+        let nodes = &function_nodes[&function];
+        for node in nodes {
+            let label = if graph.html_labels {
+                format!("<{}>", SyntaxHighlighter::html_label(&node.label))
+            } else {
+                format!("\"{}\"", Self::process_label(&node.label))
+            };
+            let tooltip = node.tooltip.as_ref()
+                .map(|tooltip| format!(", tooltip=\"{}\"", DotEscape::quoted(tooltip)))
+                .unwrap_or_default();
+            let url = node.url.as_ref()
+                .map(|url| format!(", URL=\"{}\"", DotEscape::quoted(url)))
+                .unwrap_or_default();
+            // id 使 Graphviz 输出的 SVG 元素也以节点名称为 id，HTML 查看器据此找到节点的源码
+            dot.push_str(&format!(
+                "{}    {} [id=\"{}\", label={}, shape=\"{}\", style=\"{}\", fillcolor=\"{}\", color=\"{}\"{}{}];\n",
+                indent,
+                ids.nodes[&node.id],
+                ids.nodes[&node.id],
+                label,
+                node.shape,
+                node.style,
+                node.fillcolor,
+                node.color,
+                tooltip,
+                url
+            ));
+        }
+
+        for child in function.and_then(|id| children.get(&Some(id))).into_iter().flatten() {
+            Self::render_cluster(dot, graph, Some(*child), function_nodes, children, ids, depth + 1);
+        }
+
+        dot.push_str(&format!("{}}}\n", indent));
     }
 
-    fn process_label(label: &str) -> String {
-        // 处理标签中的特殊字符
-        let escaped = label
-            .replace('\\', "\\\\")
-            .replace('\"', "\\\"")
-            .replace('{', "\\{")
-            .replace('}', "\\}")
-            .replace('<', "\\<")
-            .replace('>', "\\>")
-            .replace('|', "\\|")
-            .replace('\n', "\\n");
-
-        // 如果标签太长，添加换行
-        if escaped.len() > 20 {
-            let words: Vec<&str> = escaped.split_whitespace().collect();
-            let mut result = String::new();
-            let mut line_length = 0;
-            
-            for word in words {
-                if line_length + word.len() > 20 {
-                    result.push_str("\\n");
-                    line_length = 0;
-                } else if !result.is_empty() {
-                    result.push(' ');
-                    line_length += 1;
+    // 节点按 (所在文件, 所属函数开始节点的位置, 源码位置, 节点编号) 排序，与文件的分析和合并顺序无关，
+    // 每个函数的节点连续排列，函数子图按开始节点的位置排列。没有源码位置的辅助节点（分支合并点等）
+    // 沿用同一函数中编号在它之前、最近一个有位置的节点的位置，排在该节点之后；之前没有这样的节点时
+    // 使用函数开始节点的位置
+    fn source_order(graph: &StyledGraph) -> Vec<&StyledNode> {
+        let mut nodes: Vec<&StyledNode> = graph.nodes.iter().collect();
+        nodes.sort_by_key(|node| node.id);
+        let starts: HashMap<Option<usize>, SourceSpan> = nodes.iter()
+            .filter(|node| node.kind == "start")
+            .filter_map(|node| Some((node.function, node.span?)))
+            .collect();
+        let mut last_spans: HashMap<Option<usize>, SourceSpan> = HashMap::new();
+        let mut keyed: Vec<_> = nodes.into_iter()
+            .map(|node| {
+                if let Some(span) = node.span {
+                    last_spans.insert(node.function, span);
                 }
-                result.push_str(word);
-                line_length += word.len();
-            }
-            result
-        } else {
-            escaped
+                let file = node.function.and_then(|function| graph.files.get(&function)).map(String::as_str);
+                let start = starts.get(&node.function).copied();
+                let span = last_spans.get(&node.function).copied().or(start);
+                ((file, start, span, node.id), node)
+            })
+            .collect();
+        keyed.sort_by_key(|(key, _)| *key);
+        keyed.into_iter().map(|(_, node)| node).collect()
+    }
+
+    fn hash(value: &impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn process_label(label: &str) -> String {
+        // 按显示宽度折行后逐行转义
+        DotEscape::lines(&LabelSanitizer::wrap(label, MAX_LINE_WIDTH))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: usize) -> Option<SourceSpan> {
+        Some(SourceSpan { line, column: 1, end_line: line, end_column: 2 })
+    }
+
+    fn node(id: usize, function: usize, kind: &str, span: Option<SourceSpan>) -> StyledNode {
+        StyledNode {
+            id: NodeIndex::new(id),
+            function: Some(function),
+            kind: kind.to_string(),
+            shape: "box".to_string(),
+            style: "filled".to_string(),
+            fillcolor: "white".to_string(),
+            color: "black".to_string(),
+            label: id.to_string(),
+            tooltip: None,
+            url: None,
+            span,
         }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn helpers_before_start_follow_their_function() {
+        // 函数 0 位于第 10 行，但它的辅助节点编号在开始节点之前；函数 1 位于第 1 行
+        let graph = StyledGraph {
+            nodes: vec![
+                node(0, 0, "basic_block", None),
+                node(1, 0, "start", span(10)),
+                node(2, 0, "basic_block", span(11)),
+                node(3, 1, "start", span(1)),
+                node(4, 1, "basic_block", None),
+                node(5, 1, "basic_block", span(2)),
+            ],
+            edges: Vec::new(),
+            html_labels: false,
+            parents: HashMap::new(),
+            names: HashMap::new(),
+            namespaces: HashMap::new(),
+            signatures: HashMap::new(),
+            files: HashMap::from([(0, "src/lib.rs".to_string()), (1, "src/lib.rs".to_string())]),
+            theme: Theme::default(),
+        };
+        let order: Vec<usize> = DotRendererPass::source_order(&graph).iter().map(|node| node.id.index()).collect();
+        assert_eq!(order, vec![3, 4, 5, 0, 1, 2]);
+    }
+}
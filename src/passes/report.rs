@@ -0,0 +1,121 @@
+use crate::graph::FlowGraph;
+use crate::passes::{DotRendererPass, MermaidRendererPass, StatsPass, StylerPass};
+use crate::style::DotEscape;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// 报告中函数控制流图的嵌入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportDiagrams {
+    // 内嵌 ```mermaid 代码块，GitHub/GitLab 直接渲染
+    Mermaid,
+    // 每个函数单独渲染为 SVG，文档中只放图片链接
+    Svg,
+}
+
+impl std::str::FromStr for ReportDiagrams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mermaid" => Ok(Self::Mermaid),
+            "svg" => Ok(Self::Svg),
+            _ => Err(format!("未知的图表格式 `{}`，可选值：mermaid、svg", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleReport {
+    pub markdown: String,
+    // (图片相对于文档的路径, 函数名, DOT)，只在 ReportDiagrams::Svg 时生成
+    pub images: Vec<(String, String, String)>,
+}
+
+pub struct ReportPass;
+
+impl ReportPass {
+    // 一个模块（源文件）的 Markdown 文档：函数汇总表，之后每个函数一节，
+    // 列出圈复杂度、节点数和边数，并嵌入该函数的控制流图。
+    // image_dir 为 SVG 图片所在目录相对于文档的路径
+    pub fn render_module(
+        graph: &FlowGraph,
+        module: &str,
+        file: &str,
+        diagrams: ReportDiagrams,
+        image_dir: &str,
+    ) -> ModuleReport {
+        let styled = StylerPass::apply_style(graph);
+        let indices: HashMap<&str, usize> = styled.names.iter()
+            .map(|(function, name)| (name.as_str(), *function))
+            .collect();
+
+        let mut sections = Vec::new();
+        for stats in StatsPass::collect(graph) {
+            let Some(&function) = indices.get(stats.name.as_str()) else {
+                continue;
+            };
+            // 闭包和嵌套函数在各自的小节中单独显示
+            let subgraph = styled.function_subgraph(function);
+            let info = graph.function_info(function);
+            sections.push((stats, subgraph, info.and_then(|info| info.line)));
+        }
+
+        let mut report = ModuleReport::default();
+        let out = &mut report.markdown;
+        let _ = writeln!(out, "# `{}`\n", module);
+        let _ = writeln!(out, "源文件：`{}`\n", file);
+        if sections.is_empty() {
+            out.push_str("没有函数。\n");
+            return report;
+        }
+
+        out.push_str("| 函数 | 圈复杂度 | 节点 | 边 |\n|---|---:|---:|---:|\n");
+        for (stats, subgraph, _) in &sections {
+            let _ = writeln!(
+                out,
+                "| `{}` | {} | {} | {} |",
+                Self::short_name(&stats.name, module),
+                stats.complexity,
+                subgraph.nodes.len(),
+                subgraph.edges.len()
+            );
+        }
+
+        for (stats, subgraph, line) in &sections {
+            let _ = writeln!(out, "\n## `{}`\n", Self::short_name(&stats.name, module));
+            match line {
+                Some(line) => {
+                    let _ = writeln!(out, "- 位置：`{}:{}`", file, line);
+                }
+                None => {
+                    let _ = writeln!(out, "- 位置：`{}`", file);
+                }
+            }
+            let _ = writeln!(out, "- 圈复杂度：{}", stats.complexity);
+            let _ = writeln!(out, "- 节点数：{}", subgraph.nodes.len());
+            let _ = writeln!(out, "- 边数：{}", subgraph.edges.len());
+            let _ = writeln!(out, "- 分支：{}，循环：{}\n", stats.branches, stats.loops);
+
+            match diagrams {
+                ReportDiagrams::Mermaid => {
+                    let _ = writeln!(out, "```mermaid\n{}```", MermaidRendererPass::render(subgraph));
+                }
+                ReportDiagrams::Svg => {
+                    let image = format!("{}/{}.svg", image_dir, DotEscape::identifier(&stats.name));
+                    let _ = writeln!(out, "![{}]({})", stats.name, image);
+                    report.images.push((image, stats.name.clone(), DotRendererPass::render(subgraph)));
+                }
+            }
+        }
+
+        report
+    }
+
+    // 标题中省略模块路径前缀
+    pub fn short_name<'a>(name: &'a str, module: &str) -> &'a str {
+        name.strip_prefix(module)
+            .and_then(|rest| rest.strip_prefix("::"))
+            .unwrap_or(name)
+    }
+}
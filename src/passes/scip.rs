@@ -0,0 +1,212 @@
+use crate::passes::{CallSite, CollectedFunction};
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::path::Path;
+
+// 源码位置：(相对路径, 行号从 1 开始, 列号从 0 开始)
+type Location = (String, usize, usize);
+
+// rust-analyzer 生成的 SCIP 索引（`rust-analyzer scip .`）中解析调用所需的部分
+#[derive(Debug, Default)]
+pub struct ScipIndex {
+    // 引用位置 -> 符号
+    references: HashMap<Location, String>,
+    // 符号 -> 定义位置
+    definitions: HashMap<String, Vec<Location>>,
+    // trait 方法符号 -> 实现该方法的符号
+    implementations: HashMap<String, Vec<String>>,
+}
+
+// SCIP 中 Occurrence.symbol_roles 的 Definition 位
+const ROLE_DEFINITION: u64 = 1;
+
+impl ScipIndex {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    // Index { documents = 2 }
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut index = Self::default();
+        for (field, value) in Reader::new(bytes).fields()? {
+            if let (2, Value::Bytes(document)) = (field, value) {
+                index.add_document(document)?;
+            }
+        }
+        Ok(index)
+    }
+
+    // Document { relative_path = 1, occurrences = 2, symbols = 3 }
+    fn add_document(&mut self, bytes: &[u8]) -> Result<()> {
+        let fields = Reader::new(bytes).fields()?;
+        let path = fields.iter()
+            .find_map(|(field, value)| match (field, value) {
+                (1, Value::Bytes(path)) => Some(String::from_utf8_lossy(path).into_owned()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        for (field, value) in &fields {
+            match (field, value) {
+                (2, Value::Bytes(occurrence)) => self.add_occurrence(&path, occurrence)?,
+                (3, Value::Bytes(symbol)) => self.add_symbol(symbol)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Occurrence { range = 1, symbol = 2, symbol_roles = 3 }
+    fn add_occurrence(&mut self, path: &str, bytes: &[u8]) -> Result<()> {
+        let mut range = Vec::new();
+        let mut symbol = String::new();
+        let mut roles = 0;
+        for (field, value) in Reader::new(bytes).fields()? {
+            match (field, value) {
+                (1, Value::Varint(n)) => range.push(n as usize),
+                (1, Value::Bytes(packed)) => {
+                    let mut reader = Reader::new(packed);
+                    while !reader.is_empty() {
+                        range.push(reader.varint()? as usize);
+                    }
+                }
+                (2, Value::Bytes(name)) => symbol = String::from_utf8_lossy(name).into_owned(),
+                (3, Value::Varint(n)) => roles = n,
+                _ => {}
+            }
+        }
+        // 局部变量等符号不参与调用解析
+        if range.len() < 3 || symbol.is_empty() || symbol.starts_with("local ") {
+            return Ok(());
+        }
+
+        let location = (path.to_string(), range[0] + 1, range[1]);
+        if roles & ROLE_DEFINITION != 0 {
+            self.definitions.entry(symbol).or_default().push(location);
+        } else {
+            self.references.insert(location, symbol);
+        }
+        Ok(())
+    }
+
+    // SymbolInformation { symbol = 1, relationships = 4 }
+    // Relationship { symbol = 1, is_implementation = 3 }
+    fn add_symbol(&mut self, bytes: &[u8]) -> Result<()> {
+        let fields = Reader::new(bytes).fields()?;
+        let Some(symbol) = fields.iter().find_map(|(field, value)| match (field, value) {
+            (1, Value::Bytes(symbol)) => Some(String::from_utf8_lossy(symbol).into_owned()),
+            _ => None,
+        }) else {
+            return Ok(());
+        };
+
+        for (field, value) in &fields {
+            if let (4, Value::Bytes(relationship)) = (field, value) {
+                let mut target = None;
+                let mut is_implementation = false;
+                for (field, value) in Reader::new(relationship).fields()? {
+                    match (field, value) {
+                        (1, Value::Bytes(name)) => target = Some(String::from_utf8_lossy(name).into_owned()),
+                        (3, Value::Varint(flag)) => is_implementation = flag != 0,
+                        _ => {}
+                    }
+                }
+                if let (Some(target), true) = (target, is_implementation) {
+                    self.implementations.entry(target).or_default().push(symbol.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 调用点引用的符号的所有定义位置；trait 方法同时包括各个实现。
+    // 索引中没有这个调用点时返回 None
+    pub fn definitions_of(&self, file: &str, call: &CallSite) -> Option<Vec<&Location>> {
+        let symbol = self.references.get(&(file.to_string(), call.line, call.column))?;
+        let implementations = self.implementations.get(symbol).into_iter().flatten();
+        Some(std::iter::once(symbol)
+            .chain(implementations)
+            .filter_map(|symbol| self.definitions.get(symbol))
+            .flatten()
+            .collect())
+    }
+
+    // 函数名标识符的位置与定义位置一致
+    pub fn is_defined_at(func: &CollectedFunction, locations: &[&Location]) -> bool {
+        let Some(file) = &func.file else {
+            return false;
+        };
+        let start = func.item.sig.ident.span().start();
+        locations.iter().any(|(path, line, column)| path == file && *line == start.line && *column == start.column)
+    }
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+// 最小的 protobuf 解码器，只处理 SCIP 用到的 wire type
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let Some((&byte, rest)) = self.bytes.split_first() else {
+                bail!("Truncated varint in SCIP index");
+            };
+            self.bytes = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("Invalid varint in SCIP index")
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            bail!("Truncated field in SCIP index");
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn fields(mut self) -> Result<Vec<(u64, Value<'a>)>> {
+        let mut fields = Vec::new();
+        while !self.is_empty() {
+            let key = self.varint()?;
+            let value = match key & 0x7 {
+                0 => Value::Varint(self.varint()?),
+                1 => {
+                    self.take(8)?;
+                    Value::Fixed
+                }
+                2 => {
+                    let len = self.varint()? as usize;
+                    Value::Bytes(self.take(len)?)
+                }
+                5 => {
+                    self.take(4)?;
+                    Value::Fixed
+                }
+                wire_type => bail!("Unsupported protobuf wire type {} in SCIP index", wire_type),
+            };
+            fields.push((key >> 3, value));
+        }
+        Ok(fields)
+    }
+}
@@ -0,0 +1,212 @@
+use crate::graph::{FlowGraph, NodeType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionStats {
+    pub name: String,
+    pub nodes: usize,
+    pub branches: usize,
+    pub loops: usize,
+    // 圈复杂度 E - N + 2，只计算从函数开始可达的节点和边
+    pub complexity: usize,
+    // if/match 分支和循环体的最大嵌套层数；旧版本的历史记录中没有该字段
+    #[serde(default)]
+    pub max_nesting: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Distribution {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub median: usize,
+    pub p90: usize,
+    // 取值 -> 函数个数
+    pub histogram: BTreeMap<usize, usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsReport {
+    pub functions: Vec<FunctionStats>,
+    pub branches: Distribution,
+    pub loops: Distribution,
+    pub nodes: Distribution,
+}
+
+pub struct StatsPass;
+
+impl StatsPass {
+    pub fn collect(graph: &FlowGraph) -> Vec<FunctionStats> {
+        let mut stats = Vec::new();
+
+        for start in graph.function_starts() {
+            let (name, is_test) = match &graph.graph[start] {
+                NodeType::Start(name, is_test) => (name.clone(), *is_test),
+                _ => continue,
+            };
+            if is_test && !graph.config().include_tests {
+                continue;
+            }
+
+            let complexity = graph.function_complexity(start);
+            let max_nesting = graph.function_of(start)
+                .and_then(|function| graph.function_info(function))
+                .map_or(0, |info| info.max_nesting);
+            let mut function = FunctionStats { name, nodes: 0, branches: 0, loops: 0, complexity, max_nesting };
+            for node_id in graph.get_function_nodes(start) {
+                function.nodes += 1;
+                match &graph.graph[node_id] {
+                    NodeType::Condition(_) => function.branches += 1,
+                    NodeType::Loop(_) => function.loops += 1,
+                    _ => {}
+                }
+            }
+            stats.push(function);
+        }
+
+        stats
+    }
+
+    pub fn summarize(functions: Vec<FunctionStats>) -> StatsReport {
+        let branches = Self::distribution(functions.iter().map(|f| f.branches).collect());
+        let loops = Self::distribution(functions.iter().map(|f| f.loops).collect());
+        let nodes = Self::distribution(functions.iter().map(|f| f.nodes).collect());
+
+        StatsReport { functions, branches, loops, nodes }
+    }
+
+    fn distribution(mut values: Vec<usize>) -> Distribution {
+        if values.is_empty() {
+            return Distribution::default();
+        }
+        values.sort_unstable();
+
+        let mut histogram = BTreeMap::new();
+        for &value in &values {
+            *histogram.entry(value).or_insert(0) += 1;
+        }
+
+        let percentile = |p: usize| values[((values.len() - 1) * p) / 100];
+        Distribution {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<usize>() as f64 / values.len() as f64,
+            median: percentile(50),
+            p90: percentile(90),
+            histogram,
+        }
+    }
+
+    pub fn render_text(report: &StatsReport) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Functions: {}", report.functions.len());
+
+        for (title, dist) in Self::sections(report) {
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "{}: min={} max={} mean={:.2} median={} p90={}",
+                title, dist.min, dist.max, dist.mean, dist.median, dist.p90
+            );
+            let peak = dist.histogram.values().copied().max().unwrap_or(0);
+            for (value, count) in &dist.histogram {
+                // 柱状条最长 40 个字符
+                let width = (count * 40).div_ceil(peak.max(1));
+                let _ = writeln!(out, "  {:>4} | {:<40} {}", value, "#".repeat(width), count);
+            }
+        }
+
+        out
+    }
+
+    // 按圈复杂度从高到低排列的函数表格，超过阈值的行以 `!` 标出
+    pub fn render_complexity(functions: &[FunctionStats], threshold: Option<usize>) -> String {
+        let mut sorted: Vec<&FunctionStats> = functions.iter().collect();
+        sorted.sort_by(|a, b| b.complexity.cmp(&a.complexity).then_with(|| a.name.cmp(&b.name)));
+
+        let mut out = String::new();
+        let _ = writeln!(out, "  {:>10} {:>6} {:>7}  function", "complexity", "nodes", "nesting");
+        for function in sorted {
+            let marker = if threshold.is_some_and(|t| function.complexity > t) { '!' } else { ' ' };
+            let _ = writeln!(
+                out,
+                "{} {:>10} {:>6} {:>7}  {}",
+                marker, function.complexity, function.nodes, function.max_nesting, function.name
+            );
+        }
+        out
+    }
+
+    pub fn render_json(report: &StatsReport) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(report)?)
+    }
+
+    pub fn render_svg(report: &StatsReport) -> String {
+        const CHART_WIDTH: usize = 360;
+        const CHART_HEIGHT: usize = 200;
+        const MARGIN: usize = 40;
+
+        let sections = Self::sections(report);
+        let total_width = sections.len() * (CHART_WIDTH + MARGIN) + MARGIN;
+        let total_height = CHART_HEIGHT + MARGIN * 3;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"Arial\" font-size=\"10\">",
+            total_width, total_height
+        );
+        let _ = writeln!(svg, "  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>");
+
+        for (i, (title, dist)) in sections.iter().enumerate() {
+            let x0 = MARGIN + i * (CHART_WIDTH + MARGIN);
+            let y0 = MARGIN;
+            let _ = writeln!(svg, "  <g transform=\"translate({},{})\">", x0, y0);
+            let _ = writeln!(svg, "    <text x=\"0\" y=\"-12\" font-size=\"12\">{}</text>", title);
+            let _ = writeln!(
+                svg,
+                "    <line x1=\"0\" y1=\"{h}\" x2=\"{w}\" y2=\"{h}\" stroke=\"black\"/>",
+                h = CHART_HEIGHT,
+                w = CHART_WIDTH
+            );
+
+            let peak = dist.histogram.values().copied().max().unwrap_or(0).max(1);
+            let buckets = dist.histogram.len().max(1);
+            let bar_width = CHART_WIDTH / buckets;
+            for (j, (value, count)) in dist.histogram.iter().enumerate() {
+                let bar_height = count * (CHART_HEIGHT - 20) / peak;
+                let x = j * bar_width;
+                let y = CHART_HEIGHT - bar_height;
+                let _ = writeln!(
+                    svg,
+                    "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"lightblue\" stroke=\"black\"/>",
+                    x + 2, y, bar_width.saturating_sub(4).max(1), bar_height
+                );
+                let _ = writeln!(
+                    svg,
+                    "    <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                    x + bar_width / 2, y.saturating_sub(4), count
+                );
+                let _ = writeln!(
+                    svg,
+                    "    <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                    x + bar_width / 2, CHART_HEIGHT + 14, value
+                );
+            }
+            let _ = writeln!(svg, "  </g>");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn sections(report: &StatsReport) -> [(&'static str, &Distribution); 3] {
+        [
+            ("Branches per function", &report.branches),
+            ("Loops per function", &report.loops),
+            ("Nodes per function", &report.nodes),
+        ]
+    }
+}
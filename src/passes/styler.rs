@@ -1,24 +1,57 @@
-use crate::graph::FlowGraph;
-use crate::style::{NodeStyle, EdgeStyle};
+use crate::graph::{BlockGranularity, EdgeKind, FlowGraph, NodeType, SourceSpan};
+use crate::passes::{StatsPass, UnreachableCodePass};
+use crate::style::{EdgeStyle, EdgeTheme, NodeStyle, NodeTheme, Theme};
+use std::collections::{HashMap, HashSet};
+
+// 同时达到这两个阈值的函数视为高风险
+const RISKY_COMPLEXITY: usize = 10;
+const RISKY_CHURN: usize = 4;
 
 pub struct StyledGraph {
     pub nodes: Vec<StyledNode>,
     pub edges: Vec<StyledEdge>,
+    pub html_labels: bool,
+    // 闭包和嵌套函数的函数编号 -> 所在函数的编号
+    pub parents: HashMap<usize, usize>,
+    // 函数编号 -> 函数完整路径
+    pub names: HashMap<usize, String>,
+    // 函数编号 -> 合并前所在的命名空间（通常是模块名），只有合并后的图中才有
+    pub namespaces: HashMap<usize, String>,
+    // 函数编号 -> 函数签名，作为函数分组框的标签；没有签名的函数使用完整路径
+    pub signatures: HashMap<usize, String>,
+    // 函数编号 -> 所在源文件，渲染时按源码位置排列节点
+    pub files: HashMap<usize, String>,
+    // 字体、背景等全局样式，节点和边的样式已经按主题展开
+    pub theme: Theme,
 }
 
+#[derive(Clone)]
 pub struct StyledNode {
     pub id: petgraph::graph::NodeIndex,
+    // 所属函数编号，用于按函数分组
+    pub function: Option<usize>,
+    // 节点类型名称（见 NodeType::kind），供按类型定义样式的输出格式使用
+    pub kind: String,
     pub shape: String,
     pub style: String,
     pub fillcolor: String,
+    // 边框颜色
+    pub color: String,
     pub label: String,
+    pub tooltip: Option<String>,
+    // 点击节点时打开的源码位置，见 GraphConfig::link_template
+    pub url: Option<String>,
+    // 对应的源码范围，分支合并点等辅助节点没有
+    pub span: Option<SourceSpan>,
 }
 
+#[derive(Clone)]
 pub struct StyledEdge {
     pub from: petgraph::graph::NodeIndex,
     pub to: petgraph::graph::NodeIndex,
     pub color: String,
     pub style: String,
+    pub kind: EdgeKind,
     pub label: String,
 }
 
@@ -33,41 +66,162 @@ impl StyledGraph {
         Self {
             nodes: Vec::new(),
             edges: Vec::new(),
+            html_labels: false,
+            parents: HashMap::new(),
+            names: HashMap::new(),
+            namespaces: HashMap::new(),
+            signatures: HashMap::new(),
+            files: HashMap::new(),
+            theme: Theme::default(),
         }
     }
+
+    // 只保留一个函数的节点和边，不包括其中的闭包和嵌套函数
+    pub fn function_subgraph(&self, function: usize) -> StyledGraph {
+        let mut subgraph = StyledGraph::new();
+        subgraph.html_labels = self.html_labels;
+        subgraph.theme = self.theme.clone();
+        subgraph.nodes = self.nodes.iter()
+            .filter(|node| node.function == Some(function))
+            .cloned()
+            .collect();
+        let members: HashSet<_> = subgraph.nodes.iter().map(|node| node.id).collect();
+        subgraph.edges = self.edges.iter()
+            .filter(|edge| members.contains(&edge.from) && members.contains(&edge.to))
+            .cloned()
+            .collect();
+        if let Some(name) = self.names.get(&function) {
+            subgraph.names.insert(function, name.clone());
+        }
+        if let Some(namespace) = self.namespaces.get(&function) {
+            subgraph.namespaces.insert(function, namespace.clone());
+        }
+        if let Some(signature) = self.signatures.get(&function) {
+            subgraph.signatures.insert(function, signature.clone());
+        }
+        if let Some(file) = self.files.get(&function) {
+            subgraph.files.insert(function, file.clone());
+        }
+        subgraph
+    }
 }
 
 pub struct StylerPass;
 
 impl StylerPass {
     pub fn apply_style(graph: &FlowGraph) -> StyledGraph {
+        // merged 粒度下先合并连续的基本块，其他粒度每条语句保留为单独的节点
+        let merged;
+        let graph = if graph.config().block_granularity == BlockGranularity::Merged {
+            merged = graph.merged_blocks();
+            &merged
+        } else {
+            graph
+        };
         let mut styled = StyledGraph::new();
+        styled.html_labels = graph.config().html_labels;
+        let theme = &graph.config().theme;
+        styled.theme = theme.clone();
+        styled.parents = graph.function_parents();
+        let function_groups = graph.function_groups();
+        styled.names = function_groups.values()
+            .filter_map(|&function| graph.function_info(function).map(|info| (function, info.name.clone())))
+            .collect();
+        styled.namespaces = function_groups.values()
+            .filter_map(|&function| Some((function, graph.function_info(function)?.namespace.clone()?)))
+            .collect();
+        styled.signatures = function_groups.values()
+            .filter_map(|&function| Some((function, graph.function_info(function)?.signature.clone()?)))
+            .collect();
+        styled.files = function_groups.values()
+            .filter_map(|&function| Some((function, graph.function_info(function)?.file.clone()?)))
+            .collect();
+        let complexity: HashMap<String, usize> = if graph.config().churn {
+            StatsPass::collect(graph).into_iter().map(|stats| (stats.name, stats.complexity)).collect()
+        } else {
+            HashMap::new()
+        };
+        let back_edges: HashSet<_> = if graph.config().highlight_loops {
+            graph.back_edges()
+        } else {
+            HashSet::new()
+        };
+        let loop_headers: HashSet<_> = back_edges.iter().map(|&(_, header)| header).collect();
+        let unreachable = UnreachableCodePass::find(graph);
         
         // 处理节点
         for (id, node) in graph.nodes() {
-            let shape = NodeStyle::get_shape(node);
-            let style = NodeStyle::get_style(node);
-            let fillcolor = NodeStyle::get_fillcolor(node);
-            let label = NodeStyle::get_label(node);
+            let function = function_groups.get(&id).copied();
+            let NodeTheme { shape, mut style, mut fillcolor, mut color } = NodeStyle::get(node, theme);
+            let label = NodeStyle::get_label(node, &graph.config().labels);
+            let tooltip = NodeStyle::get_tooltip(node).filter(|tooltip| *tooltip != label);
+            let url = graph.node_file(id)
+                .zip(graph.node_span(id))
+                .and_then(|(file, span)| graph.config().node_link(file, span));
+
+            // 入口函数以及可达性着色只作用于函数的开始/结束节点
+            if let NodeType::Start(name, _) | NodeType::End(name, _) = node {
+                let info = function.and_then(|f| graph.function_info(f));
+                if info.is_some_and(|info| info.is_entry) {
+                    style = format!("{},bold", style);
+                    fillcolor = NodeStyle::get_entry_fillcolor(theme);
+                } else if graph.config().reachability {
+                    let distance = graph.config().entry_distances.get(name).copied();
+                    fillcolor = NodeStyle::get_distance_fillcolor(distance, theme);
+                }
+            }
+
+            if let NodeType::Start(name, _) = node
+                && graph.config().churn
+            {
+                let churn = graph.config().function_churn.get(name).copied().unwrap_or(0);
+                fillcolor = NodeStyle::get_churn_fillcolor(churn, theme);
+                if churn >= RISKY_CHURN && complexity.get(name).is_some_and(|c| *c >= RISKY_COMPLEXITY) {
+                    style = format!("{},bold", style);
+                    color = NodeStyle::get_risk_color(theme);
+                }
+            }
+
+            // 分析时已标记的不可达语句自带样式，其余不可达节点（如永不退出的循环之后的代码）保留形状并置灰
+            if unreachable.contains(&id) && !matches!(node, NodeType::Unreachable(_)) {
+                style = format!("{},dashed", style);
+                fillcolor = theme.node("unreachable").fillcolor;
+            }
+
+            if loop_headers.contains(&id) {
+                style = format!("{},bold", style);
+                color = theme.loop_header_color.clone();
+            }
             
             styled.nodes.push(StyledNode {
                 id,
+                function,
+                kind: node.kind().to_string(),
                 shape,
                 style,
                 fillcolor,
+                color,
                 label,
+                tooltip,
+                url,
+                span: graph.node_span(id),
             });
         }
         
         // 处理边
-        for (from, to, weight) in graph.edges() {
-            let (color, style) = EdgeStyle::get_color_and_style(weight);
+        for (from, to, kind) in graph.edges() {
+            let EdgeTheme { color, style } = if back_edges.contains(&(from, to)) {
+                theme.edge(Some("back_edge"))
+            } else {
+                EdgeStyle::get(kind, theme)
+            };
             styled.edges.push(StyledEdge {
                 from,
                 to,
                 color,
                 style,
-                label: weight.clone(),
+                kind: kind.clone(),
+                label: kind.label(&graph.config().labels),
             });
         }
         
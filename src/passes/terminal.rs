@@ -0,0 +1,340 @@
+use crate::passes::{StyledEdge, StyledGraph, StyledNode};
+use crate::style::{LabelSanitizer, MAX_LINE_WIDTH};
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use unicode_width::UnicodeWidthChar;
+
+// 边标签在终端中的最大显示宽度
+const EDGE_LABEL_WIDTH: usize = 20;
+// 连接相邻节点的竖线所在列
+const CONNECTOR_COLUMN: usize = 2;
+
+// 用 Unicode 制表符在终端中画出函数的控制流图，便于通过 SSH 快速查看。
+// 节点按拓扑序从上到下排成一列：指向下一个节点的边画成竖直箭头，跳过若干节点的边
+// 沿右侧的通道绕行，回边（循环）不画线，在源节点下方用文字注明目标
+pub struct TerminalRendererPass;
+
+// 画布上的格子：线段用上下左右四个方向的位掩码表示，重叠时合并成交叉或分叉
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Line(u8),
+    Text(char),
+    // 宽字符占用的第二列
+    Continuation,
+}
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+struct Canvas {
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Canvas {
+    fn new(height: usize) -> Self {
+        Self { rows: vec![Vec::new(); height] }
+    }
+
+    fn cell(&mut self, x: usize, y: usize) -> &mut Cell {
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, Cell::Empty);
+        }
+        &mut row[x]
+    }
+
+    fn line(&mut self, x: usize, y: usize, mask: u8) {
+        let cell = self.cell(x, y);
+        *cell = match *cell {
+            Cell::Line(existing) => Cell::Line(existing | mask),
+            _ => Cell::Line(mask),
+        };
+    }
+
+    fn horizontal(&mut self, from: usize, to: usize, y: usize) {
+        for x in from..=to {
+            self.line(x, y, LEFT | RIGHT);
+        }
+    }
+
+    fn vertical(&mut self, x: usize, from: usize, to: usize) {
+        for y in from..=to {
+            self.line(x, y, UP | DOWN);
+        }
+    }
+
+    // 写入文本，宽字符占两列
+    fn text(&mut self, x: usize, y: usize, text: &str) {
+        let mut column = x;
+        for c in text.chars() {
+            let width = c.width().unwrap_or(0);
+            if width == 0 {
+                continue;
+            }
+            *self.cell(column, y) = Cell::Text(c);
+            if width == 2 {
+                *self.cell(column + 1, y) = Cell::Continuation;
+            }
+            column += width;
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for row in &self.rows {
+            let mut line = String::new();
+            for cell in row {
+                match cell {
+                    Cell::Empty => line.push(' '),
+                    Cell::Line(mask) => line.push(Self::box_char(*mask)),
+                    Cell::Text(c) => line.push(*c),
+                    Cell::Continuation => {}
+                }
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    fn box_char(mask: u8) -> char {
+        match mask {
+            m if m == UP | DOWN | LEFT | RIGHT => '┼',
+            m if m == UP | DOWN | RIGHT => '├',
+            m if m == UP | DOWN | LEFT => '┤',
+            m if m == DOWN | LEFT | RIGHT => '┬',
+            m if m == UP | LEFT | RIGHT => '┴',
+            m if m == DOWN | RIGHT => '┌',
+            m if m == DOWN | LEFT => '┐',
+            m if m == UP | RIGHT => '└',
+            m if m == UP | LEFT => '┘',
+            m if m & (UP | DOWN) != 0 && m & (LEFT | RIGHT) == 0 => '│',
+            _ => '─',
+        }
+    }
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+impl TerminalRendererPass {
+    pub fn render(graph: &StyledGraph) -> String {
+        let mut function_nodes: BTreeMap<Option<usize>, Vec<&StyledNode>> = BTreeMap::new();
+        for node in &graph.nodes {
+            function_nodes.entry(node.function).or_default().push(node);
+        }
+
+        let mut sections = Vec::new();
+        for (function, nodes) in &function_nodes {
+            let mut section = String::new();
+            if let Some(function) = function {
+                let name = graph.names.get(function).cloned().unwrap_or_else(|| format!("fn {}", function));
+                section.push_str(&format!("═══ {} ═══\n\n", LabelSanitizer::sanitize(&name)));
+            }
+            let members: HashSet<_> = nodes.iter().map(|node| node.id).collect();
+            let edges: Vec<&StyledEdge> = graph.edges.iter()
+                .filter(|edge| members.contains(&edge.from) && members.contains(&edge.to))
+                .collect();
+            section.push_str(&Self::render_function(nodes, &edges));
+            sections.push(section);
+        }
+        sections.join("\n")
+    }
+
+    fn render_function(nodes: &[&StyledNode], edges: &[&StyledEdge]) -> String {
+        let order = Self::topological_order(nodes, edges);
+        let position: HashMap<NodeIndex, usize> = order.iter().enumerate().map(|(i, node)| (node.id, i)).collect();
+
+        // 边分为三类：指向下一个节点的直连边、向下跳过节点的边和回边
+        let mut straight: HashMap<usize, &StyledEdge> = HashMap::new();
+        let mut skips = Vec::new();
+        let mut back: HashMap<usize, Vec<&StyledEdge>> = HashMap::new();
+        for edge in edges {
+            let (from, to) = (position[&edge.from], position[&edge.to]);
+            if to <= from {
+                back.entry(from).or_default().push(*edge);
+            } else if to == from + 1 && !straight.contains_key(&from) {
+                straight.insert(from, *edge);
+            } else {
+                skips.push((from, to, *edge));
+            }
+        }
+
+        // 每条跳跃边在源节点和目标节点的方框内各占一行
+        let mut out_slots = vec![0; order.len()];
+        let mut in_slots = vec![0; order.len()];
+        for (from, to, _) in &skips {
+            out_slots[*from] += 1;
+            in_slots[*to] += 1;
+        }
+
+        let labels: Vec<Vec<String>> = order.iter()
+            .map(|node| LabelSanitizer::wrap(&node.label, MAX_LINE_WIDTH))
+            .collect();
+        let tags: Vec<String> = order.iter().map(|node| format!(" n{} ", node.id.index())).collect();
+        let inner_width = labels.iter().flatten().map(|line| text_width(line))
+            .chain(tags.iter().map(|tag| text_width(tag) + 1))
+            .max()
+            .unwrap_or(0)
+            .max(CONNECTOR_COLUMN + 2);
+        let box_width = inner_width + 4;
+
+        // 纵向布局：方框高度取标签行数和跳跃边数量的较大值，方框之间是回边说明和直连箭头
+        let annotations: Vec<Vec<String>> = (0..order.len())
+            .map(|i| back.get(&i).into_iter().flatten().map(|edge| {
+                let label = LabelSanitizer::truncate(&edge.label, EDGE_LABEL_WIDTH);
+                if label.is_empty() {
+                    format!("↺ 回到 n{}", edge.to.index())
+                } else {
+                    format!("↺ 回到 n{}：{}", edge.to.index(), label)
+                }
+            }).collect())
+            .collect();
+        let mut tops = Vec::with_capacity(order.len());
+        let mut height = 0;
+        for i in 0..order.len() {
+            tops.push(height);
+            height += 2 + labels[i].len().max(out_slots[i] + in_slots[i]).max(1);
+            height += annotations[i].len();
+            if straight.contains_key(&i) {
+                height += 2;
+            } else if i + 1 < order.len() {
+                height += 1;
+            }
+        }
+
+        let mut canvas = Canvas::new(height);
+        for i in 0..order.len() {
+            let top = tops[i];
+            let bottom = top + 1 + labels[i].len().max(out_slots[i] + in_slots[i]).max(1);
+            canvas.horizontal(0, box_width - 1, top);
+            canvas.horizontal(0, box_width - 1, bottom);
+            canvas.vertical(0, top, bottom);
+            canvas.vertical(box_width - 1, top, bottom);
+            *canvas.cell(0, top) = Cell::Line(DOWN | RIGHT);
+            *canvas.cell(box_width - 1, top) = Cell::Line(DOWN | LEFT);
+            *canvas.cell(0, bottom) = Cell::Line(UP | RIGHT);
+            *canvas.cell(box_width - 1, bottom) = Cell::Line(UP | LEFT);
+            canvas.text(2, top, &tags[i]);
+            for (line_index, line) in labels[i].iter().enumerate() {
+                canvas.text(2, top + 1 + line_index, line);
+            }
+
+            let mut row = bottom + 1;
+            let has_straight = straight.contains_key(&i);
+            for annotation in &annotations[i] {
+                if has_straight {
+                    canvas.line(CONNECTOR_COLUMN, row, UP | DOWN);
+                }
+                canvas.text(CONNECTOR_COLUMN + 2, row, annotation);
+                row += 1;
+            }
+            if let Some(edge) = straight.get(&i) {
+                canvas.line(CONNECTOR_COLUMN, bottom, DOWN);
+                canvas.line(CONNECTOR_COLUMN, row, UP | DOWN);
+                canvas.text(CONNECTOR_COLUMN + 2, row, &LabelSanitizer::truncate(&edge.label, EDGE_LABEL_WIDTH));
+                *canvas.cell(CONNECTOR_COLUMN, row + 1) = Cell::Text('▼');
+            }
+        }
+
+        Self::route_skips(&mut canvas, &skips, &tops, &out_slots, box_width);
+        canvas.render()
+    }
+
+    // 跳跃边从源方框右侧引出，沿各自的竖直通道向下，再以箭头进入目标方框右侧。
+    // 行区间不重叠的边共用一条通道，跨度短的边离方框更近
+    fn route_skips(
+        canvas: &mut Canvas,
+        skips: &[(usize, usize, &StyledEdge)],
+        tops: &[usize],
+        out_slots: &[usize],
+        box_width: usize,
+    ) {
+        let mut next_out = vec![0; tops.len()];
+        let mut next_in = vec![0; tops.len()];
+        let routes: Vec<(usize, usize, String)> = skips.iter().map(|(from, to, edge)| {
+            let source = tops[*from] + 1 + next_out[*from];
+            next_out[*from] += 1;
+            let target = tops[*to] + 1 + out_slots[*to] + next_in[*to];
+            next_in[*to] += 1;
+            (source, target, LabelSanitizer::truncate(&edge.label, EDGE_LABEL_WIDTH))
+        }).collect();
+
+        let mut indices: Vec<usize> = (0..routes.len()).collect();
+        indices.sort_by_key(|&i| (routes[i].1 - routes[i].0, i));
+        let mut lanes: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut lane_of = vec![0; routes.len()];
+        for i in indices {
+            let (source, target, _) = &routes[i];
+            let free = lanes.iter().position(|occupied| {
+                occupied.iter().all(|(start, end)| target < start || source > end)
+            });
+            let lane = free.unwrap_or_else(|| {
+                lanes.push(Vec::new());
+                lanes.len() - 1
+            });
+            lanes[lane].push((*source, *target));
+            lane_of[i] = lane;
+        }
+
+        let label_width = routes.iter().map(|(_, _, label)| text_width(label)).max().unwrap_or(0);
+        let first_lane = box_width + label_width + 3;
+        for (i, (source, target, label)) in routes.iter().enumerate() {
+            let x = first_lane + lane_of[i] * 2;
+            canvas.line(box_width - 1, *source, RIGHT);
+            canvas.horizontal(box_width, x - 1, *source);
+            canvas.line(x, *source, LEFT | DOWN);
+            if *target > *source + 1 {
+                canvas.vertical(x, source + 1, target - 1);
+            }
+            canvas.line(x, *target, UP | LEFT);
+            canvas.horizontal(box_width + 1, x - 1, *target);
+            *canvas.cell(box_width, *target) = Cell::Text('◄');
+            if !label.is_empty() {
+                canvas.text(box_width + 1, *source, &format!(" {} ", label));
+            }
+        }
+    }
+
+    // 从开始节点做深度优先搜索，按逆后序排列；开始节点不可达的节点按编号接在后面
+    fn topological_order<'a>(nodes: &[&'a StyledNode], edges: &[&StyledEdge]) -> Vec<&'a StyledNode> {
+        let mut successors: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+        // 后继按边的逆序访问，逆后序中第一条边（如条件的“是”分支、循环体）的目标排在前面
+        for edge in edges.iter().rev() {
+            successors.entry(edge.from).or_default().push(edge.to);
+        }
+        let by_id: HashMap<NodeIndex, &StyledNode> = nodes.iter().map(|node| (node.id, *node)).collect();
+        let mut roots: Vec<&StyledNode> = nodes.iter().copied().filter(|node| node.kind == "start").collect();
+        let mut rest: Vec<&StyledNode> = nodes.iter().copied().filter(|node| node.kind != "start").collect();
+        rest.sort_by_key(|node| node.id.index());
+        roots.extend(rest);
+
+        let mut visited = HashSet::new();
+        let mut order = Vec::with_capacity(nodes.len());
+        for root in roots {
+            if !visited.insert(root.id) {
+                continue;
+            }
+            let mut postorder = Vec::new();
+            let mut stack = vec![(root.id, 0)];
+            while let Some((node, child)) = stack.pop() {
+                let next = successors.get(&node).and_then(|children| children.get(child)).copied();
+                match next {
+                    Some(next) => {
+                        stack.push((node, child + 1));
+                        if visited.insert(next) {
+                            stack.push((next, 0));
+                        }
+                    }
+                    None => postorder.push(node),
+                }
+            }
+            order.extend(postorder.into_iter().rev().map(|id| by_id[&id]));
+        }
+        order
+    }
+}
@@ -0,0 +1,219 @@
+use crate::graph::{EdgeKind, FlowGraph, NodeType, TypeKind};
+use crate::passes::FunctionCollectorPass;
+use petgraph::graph::NodeIndex;
+use std::collections::{BTreeSet, HashMap};
+use syn::visit::{self, Visit};
+use syn::{Fields, File, Generics, ItemEnum, ItemImpl, ItemMod, ItemStruct, ItemTrait, TypeParamBound, TypePath};
+
+// 一个结构体、枚举或 trait 的定义
+struct TypeDefinition {
+    module: String,
+    name: String,
+    kind: TypeKind,
+    // 字段（枚举为 `::变体`）-> 字段类型中出现的类型名称
+    fields: Vec<(String, BTreeSet<String>)>,
+    // 泛型参数和 where 子句中的 trait 约束，trait 还包括父 trait
+    bounds: BTreeSet<String>,
+}
+
+// `impl Trait for Type`
+struct TraitImpl {
+    module: String,
+    self_ty: String,
+    trait_name: String,
+    bounds: BTreeSet<String>,
+}
+
+// 类型关系图的输入，各个文件收集的结果可以合并
+#[derive(Default)]
+pub struct TypeItems {
+    definitions: Vec<TypeDefinition>,
+    impls: Vec<TraitImpl>,
+}
+
+impl TypeItems {
+    pub fn extend(&mut self, other: TypeItems) {
+        self.definitions.extend(other.definitions);
+        self.impls.extend(other.impls);
+    }
+}
+
+// 结构体/枚举/trait 关系图：字段类型为组合边（标注字段名），trait 实现为 implements 边，
+// 泛型约束为虚线的 bound 边。类型按最后一段名称解析，只显示 crate 内定义的类型
+pub struct TypeGraphPass {
+    items: TypeItems,
+    scope: Vec<String>,
+}
+
+impl TypeGraphPass {
+    pub fn collect(file: &File, module_path: &str) -> TypeItems {
+        let mut pass = Self {
+            items: TypeItems::default(),
+            scope: module_path.split("::").map(str::to_string).collect(),
+        };
+        pass.visit_file(file);
+        pass.items
+    }
+
+    pub fn build(items: &TypeItems) -> FlowGraph {
+        let mut graph = FlowGraph::new();
+        let mut by_name: HashMap<&str, Vec<(&str, NodeIndex)>> = HashMap::new();
+        let mut nodes = Vec::new();
+        for definition in &items.definitions {
+            let path = format!("{}::{}", definition.module, definition.name);
+            let node = graph.add_node(NodeType::Type(definition.kind.clone(), path));
+            by_name.entry(&definition.name).or_default().push((&definition.module, node));
+            nodes.push(node);
+        }
+
+        // 同名类型优先取同一模块中的，否则只有唯一一个同名类型时才连接
+        let resolve = |module: &str, name: &str| -> Option<NodeIndex> {
+            let candidates = by_name.get(name)?;
+            candidates.iter()
+                .find(|(candidate, _)| *candidate == module)
+                .or_else(|| (candidates.len() == 1).then(|| &candidates[0]))
+                .map(|(_, node)| *node)
+        };
+
+        let mut edges: BTreeSet<(NodeIndex, NodeIndex, EdgeKind)> = BTreeSet::new();
+        for (definition, &node) in items.definitions.iter().zip(&nodes) {
+            for (field, types) in &definition.fields {
+                for target in types.iter().filter_map(|name| resolve(&definition.module, name)) {
+                    edges.insert((node, target, EdgeKind::Custom(field.clone())));
+                }
+            }
+            for target in definition.bounds.iter().filter_map(|name| resolve(&definition.module, name)) {
+                edges.insert((node, target, EdgeKind::Bound));
+            }
+        }
+        for item in &items.impls {
+            let (Some(self_ty), Some(trait_node)) = (resolve(&item.module, &item.self_ty), resolve(&item.module, &item.trait_name)) else {
+                continue;
+            };
+            edges.insert((self_ty, trait_node, EdgeKind::Implements));
+            for target in item.bounds.iter().filter_map(|name| resolve(&item.module, name)) {
+                edges.insert((self_ty, target, EdgeKind::Bound));
+            }
+        }
+
+        // 递归类型（如 `Box<Self>` 字段）保留为自环
+        for (from, to, kind) in edges {
+            graph.add_edge(from, to, kind);
+        }
+        graph
+    }
+
+    fn module(&self) -> String {
+        self.scope.join("::")
+    }
+
+    // 泛型参数和 where 子句中的 trait 约束
+    fn generic_bounds(generics: &Generics) -> BTreeSet<String> {
+        let mut names = TypeNames::default();
+        for param in generics.type_params() {
+            param.bounds.iter().for_each(|bound| names.visit_type_param_bound(bound));
+        }
+        if let Some(where_clause) = &generics.where_clause {
+            names.visit_where_clause(where_clause);
+        }
+        names.0
+    }
+
+    fn fields(prefix: &str, fields: &Fields) -> Vec<(String, BTreeSet<String>)> {
+        fields.iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let name = field.ident.as_ref().map(ToString::to_string).unwrap_or_else(|| i.to_string());
+                let mut names = TypeNames::default();
+                names.visit_type(&field.ty);
+                (format!("{}.{}", prefix, name), names.0)
+            })
+            .collect()
+    }
+}
+
+impl<'ast> Visit<'ast> for TypeGraphPass {
+    fn visit_item_struct(&mut self, node: &'ast ItemStruct) {
+        self.items.definitions.push(TypeDefinition {
+            module: self.module(),
+            name: node.ident.to_string(),
+            kind: TypeKind::Struct,
+            fields: Self::fields("", &node.fields),
+            bounds: Self::generic_bounds(&node.generics),
+        });
+    }
+
+    fn visit_item_enum(&mut self, node: &'ast ItemEnum) {
+        self.items.definitions.push(TypeDefinition {
+            module: self.module(),
+            name: node.ident.to_string(),
+            kind: TypeKind::Enum,
+            fields: node.variants.iter()
+                .flat_map(|variant| Self::fields(&format!("::{}", variant.ident), &variant.fields))
+                .collect(),
+            bounds: Self::generic_bounds(&node.generics),
+        });
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast ItemTrait) {
+        let mut bounds = Self::generic_bounds(&node.generics);
+        let mut supertraits = TypeNames::default();
+        node.supertraits.iter().for_each(|bound| supertraits.visit_type_param_bound(bound));
+        bounds.extend(supertraits.0);
+        self.items.definitions.push(TypeDefinition {
+            module: self.module(),
+            name: node.ident.to_string(),
+            kind: TypeKind::Trait,
+            fields: Vec::new(),
+            bounds,
+        });
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast ItemImpl) {
+        let Some((_, trait_path, _)) = &node.trait_ else {
+            return;
+        };
+        let syn::Type::Path(self_ty) = &*node.self_ty else {
+            return;
+        };
+        if let (Some(self_ty), Some(trait_name)) = (self_ty.path.segments.last(), trait_path.segments.last()) {
+            self.items.impls.push(TraitImpl {
+                module: self.module(),
+                self_ty: self_ty.ident.to_string(),
+                trait_name: trait_name.ident.to_string(),
+                bounds: Self::generic_bounds(&node.generics),
+            });
+        }
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        if FunctionCollectorPass::is_test_module(node) {
+            return;
+        }
+        self.scope.push(node.ident.to_string());
+        visit::visit_item_mod(self, node);
+        self.scope.pop();
+    }
+}
+
+// 类型或约束中出现的所有路径的最后一段名称（包括泛型参数中的，如 `Vec<Node>` 中的 `Vec` 和 `Node`）
+#[derive(Default)]
+struct TypeNames(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for TypeNames {
+    fn visit_type_path(&mut self, node: &'ast TypePath) {
+        if let Some(segment) = node.path.segments.last() {
+            self.0.insert(segment.ident.to_string());
+        }
+        visit::visit_type_path(self, node);
+    }
+
+    fn visit_type_param_bound(&mut self, node: &'ast TypeParamBound) {
+        if let TypeParamBound::Trait(bound) = node
+            && let Some(segment) = bound.path.segments.last()
+        {
+            self.0.insert(segment.ident.to_string());
+        }
+        visit::visit_type_param_bound(self, node);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::graph::{FlowGraph, NodeType, SourceSpan};
+use petgraph::Direction;
+use petgraph::graph::NodeIndex;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+// 一段不可达代码的第一个节点
+#[derive(Debug, Clone)]
+pub struct UnreachableCode {
+    pub function: String,
+    pub file: Option<String>,
+    pub span: SourceSpan,
+    pub code: String,
+}
+
+pub struct UnreachableCodePass;
+
+impl UnreachableCodePass {
+    // 属于某个函数、但从任何函数开始节点都不可达的节点，如 return 之后的语句、
+    // 永不退出的 loop 之后的代码。发散函数的结束节点本来就不可达，不算在内
+    pub fn find(graph: &FlowGraph) -> HashSet<NodeIndex> {
+        let reachable: HashSet<NodeIndex> = graph.function_starts().into_iter()
+            .flat_map(|start| graph.get_function_nodes(start))
+            .collect();
+        graph.graph.node_indices()
+            .filter(|id| !reachable.contains(id) && graph.function_of(*id).is_some())
+            .filter(|&id| !matches!(graph.graph[id], NodeType::End(_, _)))
+            .collect()
+    }
+
+    // 每段不可达代码只报告第一个有源码位置的节点，分支合并点等辅助节点没有源码位置
+    pub fn lint(graph: &FlowGraph) -> Vec<UnreachableCode> {
+        let dead = Self::find(graph);
+        let reported = |id: NodeIndex| dead.contains(&id) && graph.node_span(id).is_some();
+
+        let mut findings: Vec<UnreachableCode> = graph.nodes()
+            .filter(|&(id, _)| reported(id))
+            .filter(|&(id, _)| !graph.graph.neighbors_directed(id, Direction::Incoming).any(reported))
+            .filter_map(|(id, node)| {
+                let info = graph.function_info(graph.function_of(id)?)?;
+                Some(UnreachableCode {
+                    function: info.name.clone(),
+                    file: info.file.clone(),
+                    span: graph.node_span(id)?,
+                    code: node.tooltip().unwrap_or_else(|| node.label()),
+                })
+            })
+            .collect();
+        findings.sort_by(|a, b| (&a.file, a.span.line, a.span.column).cmp(&(&b.file, b.span.line, b.span.column)));
+        findings
+    }
+
+    // 与编译器诊断相同的 `文件:行:列` 格式，便于编辑器跳转
+    pub fn render_text(findings: &[UnreachableCode]) -> String {
+        let mut out = String::new();
+        for finding in findings {
+            let code = finding.code.lines().next().unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{}:{}:{}: unreachable code in {}: {}",
+                finding.file.as_deref().unwrap_or("<unknown>"),
+                finding.span.line,
+                finding.span.column,
+                finding.function,
+                code
+            );
+        }
+        out
+    }
+}
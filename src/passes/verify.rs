@@ -0,0 +1,154 @@
+use crate::graph::Labels;
+use crate::passes::FunctionSnapshot;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+// 提交到仓库中的基准控制流图，按函数名排序，便于在代码评审中查看差异
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub functions: Vec<FunctionSnapshot>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FunctionDiff {
+    pub name: String,
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub added_edges: Vec<String>,
+    pub removed_edges: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub added_functions: Vec<String>,
+    pub removed_functions: Vec<String>,
+    pub changed_functions: Vec<FunctionDiff>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.added_functions.is_empty() && self.removed_functions.is_empty() && self.changed_functions.is_empty()
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        for name in &self.added_functions {
+            let _ = writeln!(out, "+ function {}", name);
+        }
+        for name in &self.removed_functions {
+            let _ = writeln!(out, "- function {}", name);
+        }
+        for diff in &self.changed_functions {
+            let _ = writeln!(out, "~ function {}", diff.name);
+            for node in &diff.removed_nodes {
+                let _ = writeln!(out, "    - node {}", node);
+            }
+            for node in &diff.added_nodes {
+                let _ = writeln!(out, "    + node {}", node);
+            }
+            for edge in &diff.removed_edges {
+                let _ = writeln!(out, "    - edge {}", edge);
+            }
+            for edge in &diff.added_edges {
+                let _ = writeln!(out, "    + edge {}", edge);
+            }
+        }
+        out
+    }
+}
+
+pub struct VerifyPass;
+
+impl VerifyPass {
+    pub fn baseline(mut functions: Vec<FunctionSnapshot>) -> Baseline {
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+        Baseline { functions }
+    }
+
+    pub fn load(path: &Path) -> Result<Baseline> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline: {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid baseline: {}", path.display()))
+    }
+
+    pub fn save(path: &Path, baseline: &Baseline) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(baseline)? + "\n")?;
+        Ok(())
+    }
+
+    // 按结构比较：节点按标签、边按 (起点标签, 终点标签, 边标签) 作为多重集合比较，
+    // 与节点编号无关；标签中的空白被规范化，只改格式不会被视为控制流变化
+    pub fn compare(baseline: &Baseline, current: &[FunctionSnapshot]) -> VerifyReport {
+        let before: BTreeMap<&str, &FunctionSnapshot> = baseline.functions.iter().map(|f| (f.name.as_str(), f)).collect();
+        let after: BTreeMap<&str, &FunctionSnapshot> = current.iter().map(|f| (f.name.as_str(), f)).collect();
+        let mut report = VerifyReport::default();
+
+        for (name, old) in &before {
+            match after.get(name) {
+                None => report.removed_functions.push(name.to_string()),
+                Some(new) => {
+                    let (removed_nodes, added_nodes) = Self::diff(Self::nodes(old), Self::nodes(new));
+                    let (removed_edges, added_edges) = Self::diff(Self::edges(old), Self::edges(new));
+                    if !(removed_nodes.is_empty() && added_nodes.is_empty() && removed_edges.is_empty() && added_edges.is_empty()) {
+                        report.changed_functions.push(FunctionDiff {
+                            name: name.to_string(),
+                            added_nodes,
+                            removed_nodes,
+                            added_edges,
+                            removed_edges,
+                        });
+                    }
+                }
+            }
+        }
+        report.added_functions = after.keys()
+            .filter(|name| !before.contains_key(*name))
+            .map(|name| name.to_string())
+            .collect();
+
+        report
+    }
+
+    fn label(func: &FunctionSnapshot, node: usize) -> String {
+        func.nodes.get(node)
+            .map(|node| node.label().split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default()
+    }
+
+    fn nodes(func: &FunctionSnapshot) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for node in 0..func.nodes.len() {
+            *counts.entry(Self::label(func, node)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn edges(func: &FunctionSnapshot) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        let labels = Labels::default();
+        for (from, to, kind) in &func.edges {
+            let edge = format!("{} -> {} [{}]", Self::label(func, *from), Self::label(func, *to), kind.label(&labels));
+            *counts.entry(edge).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    // 多重集合的差：(只在 before 中的, 只在 after 中的)
+    fn diff(before: BTreeMap<String, usize>, after: BTreeMap<String, usize>) -> (Vec<String>, Vec<String>) {
+        let missing = |from: &BTreeMap<String, usize>, to: &BTreeMap<String, usize>| -> Vec<String> {
+            from.iter()
+                .flat_map(|(item, count)| {
+                    let remaining = count.saturating_sub(to.get(item).copied().unwrap_or(0));
+                    std::iter::repeat_n(item.clone(), remaining)
+                })
+                .collect()
+        };
+        (missing(&before, &after), missing(&after, &before))
+    }
+}
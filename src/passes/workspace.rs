@@ -0,0 +1,107 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// `cargo metadata --no-deps --format-version 1` 输出中用到的部分
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    id: String,
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    // lib、bin、test、example、bench、custom-build 等
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+// workspace 中的一个成员 crate
+#[derive(Debug, Clone)]
+pub struct WorkspacePackage {
+    pub name: String,
+    // Cargo.toml 所在目录
+    pub root: PathBuf,
+    // 各个 target 源文件所在的目录（src、src/bin、tests、examples 等），已去掉嵌套在其他目录中的；
+    // 构建脚本不在其中
+    pub source_dirs: Vec<PathBuf>,
+    // 只包含 lib、bin 等 target 的目录，不含测试、示例和 benchmark
+    pub library_dirs: Vec<PathBuf>,
+}
+
+pub struct WorkspacePass;
+
+impl WorkspacePass {
+    // 从 dir 向上找到的 workspace 的根目录和所有成员，成员按 cargo metadata 给出的顺序
+    pub fn load(dir: &Path) -> Result<(PathBuf, Vec<WorkspacePackage>)> {
+        let metadata = Self::metadata(dir)?;
+        let packages = metadata.workspace_members.iter()
+            .filter_map(|id| metadata.packages.iter().find(|package| &package.id == id))
+            .map(Self::package)
+            .collect();
+        Ok((metadata.workspace_root, packages))
+    }
+
+    // cargo 实际使用的构建输出目录，考虑了 CARGO_TARGET_DIR 和 .cargo/config.toml 中的 build.target-dir
+    pub fn target_dir(dir: &Path) -> Result<PathBuf> {
+        Ok(Self::metadata(dir)?.target_directory)
+    }
+
+    fn metadata(dir: &Path) -> Result<Metadata> {
+        let output = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+            .current_dir(dir)
+            .args(["metadata", "--no-deps", "--format-version", "1"])
+            .output()
+            .context("Failed to run cargo metadata")?;
+        if !output.status.success() {
+            bail!("cargo metadata failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        serde_json::from_slice(&output.stdout).context("Failed to parse cargo metadata output")
+    }
+
+    // 按名称选择成员，名称不存在时报错并列出所有成员
+    pub fn select(packages: Vec<WorkspacePackage>, names: &[String]) -> Result<Vec<WorkspacePackage>> {
+        if let Some(missing) = names.iter().find(|name| !packages.iter().any(|package| &package.name == *name)) {
+            let available: Vec<&str> = packages.iter().map(|package| package.name.as_str()).collect();
+            bail!("Package `{}` is not a workspace member (members: {})", missing, available.join(", "));
+        }
+        Ok(packages.into_iter().filter(|package| names.contains(&package.name)).collect())
+    }
+
+    fn package(package: &Package) -> WorkspacePackage {
+        let root = package.manifest_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let excluded = |library: bool| -> &[&str] {
+            if library { &["custom-build", "test", "example", "bench"] } else { &["custom-build"] }
+        };
+        let dirs = |library: bool| -> Vec<PathBuf> {
+            let dirs: Vec<PathBuf> = package.targets.iter()
+                .filter(|target| !target.kind.iter().any(|kind| excluded(library).contains(&kind.as_str())))
+                .filter_map(|target| target.src_path.parent().map(Path::to_path_buf))
+                .collect();
+            let mut outermost: Vec<PathBuf> = dirs.iter()
+                .filter(|dir| !dirs.iter().any(|other| other != *dir && dir.starts_with(other)))
+                .cloned()
+                .collect();
+            outermost.sort();
+            outermost.dedup();
+            outermost
+        };
+        WorkspacePackage {
+            name: package.name.clone(),
+            source_dirs: dirs(false),
+            library_dirs: dirs(true),
+            root,
+        }
+    }
+}
@@ -1,13 +1,34 @@
+use crate::graph::EdgeKind;
+use crate::style::{EdgeTheme, Theme};
+
 pub struct EdgeStyle;
 
 impl EdgeStyle {
-    pub fn get_color_and_style(label: &str) -> (String, String) {
-        match label {
-            "是" => ("green".to_string(), "solid".to_string()),
-            "否" => ("red".to_string(), "solid".to_string()),
-            "继续循环" => ("blue".to_string(), "dashed".to_string()),
-            "跳出循环" => ("red".to_string(), "dashed".to_string()),
-            _ => ("black".to_string(), "solid".to_string()),
+    // 边在主题 `edges` 表中的键，普通的边没有单独的样式
+    pub fn kind(kind: &EdgeKind) -> Option<&'static str> {
+        match kind {
+            EdgeKind::True => Some("true"),
+            EdgeKind::False => Some("false"),
+            EdgeKind::LoopBack | EdgeKind::Continue(_) => Some("continue"),
+            EdgeKind::Break(_) => Some("break"),
+            EdgeKind::Default => Some("default"),
+            EdgeKind::Recursion => Some("recursion"),
+            EdgeKind::Mod => Some("mod"),
+            EdgeKind::Cycle => Some("cycle"),
+            EdgeKind::Implements => Some("implements"),
+            EdgeKind::Bound => Some("bound"),
+            EdgeKind::Dependency { optional: true, .. } => Some("optional"),
+            EdgeKind::Dependency { kinds, .. } => match kinds.as_slice() {
+                [kind] if kind == "dev" => Some("dev"),
+                [kind] if kind == "build" => Some("build"),
+                _ => None,
+            },
+            EdgeKind::ExternalCall => Some("external_call"),
+            _ => None,
         }
     }
-} 
\ No newline at end of file
+
+    pub fn get(kind: &EdgeKind, theme: &Theme) -> EdgeTheme {
+        theme.edge(Self::kind(kind))
+    }
+}
@@ -0,0 +1,196 @@
+use crate::style::LabelSanitizer;
+
+// 所有 DOT 输出共用的转义规则
+pub struct DotEscape;
+
+impl DotEscape {
+    // 双引号字符串（ID、普通标签）：只有 `\` 和 `"` 需要转义，换行写作 `\n`。
+    // 反斜杠成对转义后，`\N`、`\G` 等 Graphviz 替换序列也不会被误触发
+    pub fn quoted(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in LabelSanitizer::sanitize(text).chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '"' => escaped.push_str("\\\""),
+                '\n' => escaped.push_str("\\n"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    // 多行标签：逐行转义，行之间用 `\n` 连接（居中对齐）
+    pub fn lines<S: AsRef<str>>(lines: &[S]) -> String {
+        lines.iter()
+            .map(|line| Self::quoted(line.as_ref()))
+            .collect::<Vec<_>>()
+            .join("\\n")
+    }
+
+    // shape=record 的标签中 `{`、`}`、`|`、`<`、`>` 和空格有特殊含义
+    pub fn record(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in Self::quoted(text).chars() {
+            if matches!(c, '{' | '}' | '|' | '<' | '>') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    // HTML-like 标签（`label=<...>`）中的文本按 XML 实体转义，换行需要用 <BR/> 表示
+    pub fn html(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in LabelSanitizer::sanitize(text).chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                '\n' => escaped.push_str("<BR ALIGN=\"LEFT\"/>"),
+                // 连续空格会被 Graphviz 合并，使用不换行空格保留缩进
+                ' ' => escaped.push_str("&#160;"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    // 用作 subgraph 名称等裸标识符，非字母数字字符替换为下划线
+    pub fn identifier(text: &str) -> String {
+        let identifier: String = text.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("_{}", identifier)
+        } else {
+            identifier
+        }
+    }
+}
+
+// Mermaid 标签中的转义规则：特殊字符使用 Mermaid 的 `#name;` 实体，换行写作 `<br/>`
+pub struct MermaidEscape;
+
+impl MermaidEscape {
+    pub fn text(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in LabelSanitizer::sanitize(text).chars() {
+            match c {
+                '"' => escaped.push_str("#quot;"),
+                '#' => escaped.push_str("#35;"),
+                '&' => escaped.push_str("#amp;"),
+                '<' => escaped.push_str("#lt;"),
+                '>' => escaped.push_str("#gt;"),
+                '\n' => escaped.push_str("<br/>"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    pub fn lines<S: AsRef<str>>(lines: &[S]) -> String {
+        lines.iter()
+            .map(|line| Self::text(line.as_ref()))
+            .collect::<Vec<_>>()
+            .join("<br/>")
+    }
+}
+
+// D2 双引号字符串的转义规则：`\` 和 `"` 需要转义，换行写作 `\n`
+pub struct D2Escape;
+
+impl D2Escape {
+    pub fn text(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in LabelSanitizer::sanitize(text).chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '"' => escaped.push_str("\\\""),
+                '\n' => escaped.push_str("\\n"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
+
+// PlantUML 标签中的转义规则：creole 标记（`**`、`//`、`--` 等成对字符和 `<`）前加 `~`，
+// 换行写作 `\n`，使每个标签只占一行
+pub struct PlantUmlEscape;
+
+impl PlantUmlEscape {
+    pub fn text(text: &str) -> String {
+        let chars: Vec<char> = LabelSanitizer::sanitize(text).chars().collect();
+        let mut escaped = String::with_capacity(text.len());
+        for (i, &c) in chars.iter().enumerate() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '<' => escaped.push_str("~<"),
+                '*' | '/' | '"' | '-' | '_' | '~' | '=' if chars.get(i + 1) == Some(&c) => {
+                    escaped.push('~');
+                    escaped.push(c);
+                }
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    pub fn lines<S: AsRef<str>>(lines: &[S]) -> String {
+        lines.iter()
+            .map(|line| Self::text(line.as_ref()))
+            .collect::<Vec<_>>()
+            .join("\\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_escapes_quotes_backslashes_and_newlines() {
+        assert_eq!(DotEscape::quoted(r#"say "hi""#), r#"say \"hi\""#);
+        assert_eq!(DotEscape::quoted(r"C:\dir\N"), r"C:\\dir\\N");
+        assert_eq!(DotEscape::quoted(r#"\""#), r#"\\\""#);
+        assert_eq!(DotEscape::quoted("a\nb\r\nc"), r"a\nb\nc");
+    }
+
+    #[test]
+    fn quoted_keeps_record_and_html_characters() {
+        assert_eq!(DotEscape::quoted("format!(\"{}\", v)"), r#"format!(\"{}\", v)"#);
+        assert_eq!(DotEscape::quoted("Vec<T> | Option<&T>"), "Vec<T> | Option<&T>");
+    }
+
+    #[test]
+    fn lines_joins_escaped_lines() {
+        assert_eq!(DotEscape::lines(&["if \"a\"", "{ b }"]), r#"if \"a\"\n{ b }"#);
+        assert_eq!(DotEscape::lines::<&str>(&[]), "");
+    }
+
+    #[test]
+    fn record_escapes_field_separators() {
+        assert_eq!(DotEscape::record("{a|b}"), r"\{a\|b\}");
+        assert_eq!(DotEscape::record("Vec<T>"), r"Vec\<T\>");
+        assert_eq!(DotEscape::record("\"x\"\n"), r#"\"x\"\n"#);
+    }
+
+    #[test]
+    fn html_uses_entities_and_line_breaks() {
+        assert_eq!(DotEscape::html("Vec<T> & \"s\" 'c'"), "Vec&lt;T&gt;&#160;&amp;&#160;&quot;s&quot;&#160;&#39;c&#39;");
+        assert_eq!(DotEscape::html("a\nb"), "a<BR ALIGN=\"LEFT\"/>b");
+        // HTML 标签中反斜杠、花括号和竖线没有特殊含义
+        assert_eq!(DotEscape::html(r"{\|}"), r"{\|}");
+    }
+
+    #[test]
+    fn identifier_replaces_non_alphanumerics() {
+        assert_eq!(DotEscape::identifier("crate::a-b"), "crate__a_b");
+        assert_eq!(DotEscape::identifier("1st"), "_1st");
+        assert_eq!(DotEscape::identifier("模块"), "__");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::style::{DotEscape, LabelSanitizer, MAX_LINE_WIDTH};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+const KEYWORD_COLOR: &str = "#0000CC";
+const STRING_COLOR: &str = "#A31515";
+const NUMBER_COLOR: &str = "#098658";
+const COMMENT_COLOR: &str = "#008000";
+const MACRO_COLOR: &str = "#795E26";
+
+// 跨行的字符串和块注释需要在行之间保持状态
+#[derive(Clone, Copy, PartialEq)]
+enum LexState {
+    Normal,
+    Str,
+    BlockComment,
+}
+
+// 为 Graphviz 的 HTML-like 标签生成带简单语法高亮的代码
+pub struct SyntaxHighlighter;
+
+impl SyntaxHighlighter {
+    // 返回 `label=<...>` 中尖括号内的内容，每行左对齐
+    pub fn html_label(text: &str) -> String {
+        let mut state = LexState::Normal;
+        let mut html = String::new();
+        for line in LabelSanitizer::wrap(text, MAX_LINE_WIDTH) {
+            html.push_str(&Self::highlight_line(&line, &mut state));
+            html.push_str("<BR ALIGN=\"LEFT\"/>");
+        }
+        html
+    }
+
+    // 逐行高亮源码，不折行；FONT、B、I 标签在 HTML 中同样有效，供 HTML 查看器的源码面板使用
+    pub fn html_lines(text: &str) -> Vec<String> {
+        let mut state = LexState::Normal;
+        text.lines().map(|line| Self::highlight_line(line, &mut state)).collect()
+    }
+
+    fn highlight_line(line: &str, state: &mut LexState) -> String {
+        let chars: Vec<char> = line.chars().collect();
+        let mut html = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let start = i;
+            match *state {
+                LexState::Str => {
+                    i = Self::string_end(&chars, i, state);
+                    html.push_str(&Self::colored(&chars[start..i], STRING_COLOR));
+                    continue;
+                }
+                LexState::BlockComment => {
+                    while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                        i += 1;
+                    }
+                    if i < chars.len() {
+                        i += 2;
+                        *state = LexState::Normal;
+                    }
+                    html.push_str(&Self::comment(&chars[start..i]));
+                    continue;
+                }
+                LexState::Normal => {}
+            }
+
+            let c = chars[i];
+            if c == '/' && chars.get(i + 1) == Some(&'/') {
+                html.push_str(&Self::comment(&chars[i..]));
+                break;
+            } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+                *state = LexState::BlockComment;
+                html.push_str(&Self::comment(&chars[i..i + 2]));
+                i += 2;
+            } else if c == '"' {
+                i = Self::string_end(&chars, i + 1, state);
+                html.push_str(&Self::colored(&chars[start..i], STRING_COLOR));
+            } else if c == 'r' && matches!(chars.get(i + 1), Some('"' | '#')) {
+                // 原始字符串 r"..." / r#"..."#，只在同一行内识别
+                let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                if chars.get(i + 1 + hashes) == Some(&'"') {
+                    let body = i + 2 + hashes;
+                    let close: Vec<char> = std::iter::once('"').chain(std::iter::repeat_n('#', hashes)).collect();
+                    let end = (body..chars.len())
+                        .find(|&j| chars[j..].starts_with(&close))
+                        .map(|j| j + close.len())
+                        .unwrap_or(chars.len());
+                    html.push_str(&Self::colored(&chars[i..end], STRING_COLOR));
+                    i = end;
+                } else {
+                    i = Self::push_word(&chars, i, &mut html);
+                }
+            } else if c == '\'' {
+                // 字符字面量 'a'、'\n'；否则是生命周期
+                let end = if chars.get(i + 1) == Some(&'\\') {
+                    chars[i + 2..].iter().position(|c| *c == '\'').map(|p| i + 3 + p)
+                } else if chars.get(i + 2) == Some(&'\'') {
+                    Some(i + 3)
+                } else {
+                    None
+                };
+                match end {
+                    Some(end) => {
+                        html.push_str(&Self::colored(&chars[i..end], STRING_COLOR));
+                        i = end;
+                    }
+                    None => {
+                        html.push_str(&DotEscape::html("'"));
+                        i += 1;
+                    }
+                }
+            } else if c.is_ascii_digit() {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                    // `0..n` 中的 `..` 不属于数字
+                    if chars[i] == '.' && chars.get(i + 1) == Some(&'.') {
+                        break;
+                    }
+                    i += 1;
+                }
+                html.push_str(&Self::colored(&chars[start..i], NUMBER_COLOR));
+            } else if c.is_alphabetic() || c == '_' {
+                i = Self::push_word(&chars, i, &mut html);
+            } else {
+                html.push_str(&DotEscape::html(&c.to_string()));
+                i += 1;
+            }
+        }
+
+        html
+    }
+
+    // 从字符串内容的 from 处找到结束引号之后的位置；没有结束时字符串延续到下一行
+    fn string_end(chars: &[char], from: usize, state: &mut LexState) -> usize {
+        let mut i = from;
+        while i < chars.len() && chars[i] != '"' {
+            i += if chars[i] == '\\' { 2 } else { 1 };
+        }
+        if i < chars.len() {
+            *state = LexState::Normal;
+            i + 1
+        } else {
+            *state = LexState::Str;
+            chars.len()
+        }
+    }
+
+    // 标识符、关键字或宏名称（后面紧跟 `!`）
+    fn push_word(chars: &[char], start: usize, html: &mut String) -> usize {
+        let mut end = start;
+        while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+            end += 1;
+        }
+        let word: String = chars[start..end].iter().collect();
+        if KEYWORDS.contains(&word.as_str()) {
+            html.push_str(&format!("<FONT COLOR=\"{}\"><B>{}</B></FONT>", KEYWORD_COLOR, DotEscape::html(&word)));
+        } else if chars.get(end) == Some(&'!') && chars.get(end + 1) != Some(&'=') {
+            html.push_str(&Self::colored(&chars[start..end + 1], MACRO_COLOR));
+            end += 1;
+        } else {
+            html.push_str(&DotEscape::html(&word));
+        }
+        end
+    }
+
+    fn colored(chars: &[char], color: &str) -> String {
+        let text: String = chars.iter().collect();
+        if text.is_empty() {
+            return text;
+        }
+        format!("<FONT COLOR=\"{}\">{}</FONT>", color, DotEscape::html(&text))
+    }
+
+    fn comment(chars: &[char]) -> String {
+        let text: String = chars.iter().collect();
+        format!("<FONT COLOR=\"{}\"><I>{}</I></FONT>", COMMENT_COLOR, DotEscape::html(&text))
+    }
+}
@@ -0,0 +1,187 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// 标签每行的最大显示宽度（中日韩字符按两列计算）
+pub const MAX_LINE_WIDTH: usize = 40;
+
+// 所有渲染器共用的标签清理：按字素簇处理，不会在多字节字符中间截断
+pub struct LabelSanitizer;
+
+impl LabelSanitizer {
+    // 统一换行符，制表符展开为空格，其余控制字符和双向文本控制符替换为 U+FFFD，
+    // 避免源码中的 RLO 等字符改变标签其余部分的显示顺序
+    pub fn sanitize(text: &str) -> String {
+        text.replace("\r\n", "\n")
+            .chars()
+            .map(|c| match c {
+                '\n' => "\n".to_string(),
+                '\t' => "    ".to_string(),
+                c if c.is_control() || Self::is_bidi_control(c) => '\u{FFFD}'.to_string(),
+                c => c.to_string(),
+            })
+            .collect()
+    }
+
+    fn is_bidi_control(c: char) -> bool {
+        matches!(c, '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+    }
+
+    // 压缩成一行，超出显示宽度时截断并以 `…` 结尾，用于边标签等短文本
+    pub fn truncate(text: &str, max_width: usize) -> String {
+        let line = Self::sanitize(text).split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.width() <= max_width {
+            return line;
+        }
+        let mut result = String::new();
+        for grapheme in line.graphemes(true) {
+            if result.width() + grapheme.width() + 1 > max_width {
+                break;
+            }
+            result.push_str(grapheme);
+        }
+        result.push('…');
+        result
+    }
+
+    // 清理后按显示宽度折行：保留原有的换行和缩进，过长的行在空白处断开，
+    // 没有空白可断的长单词按字素簇强制断开
+    pub fn wrap(text: &str, max_width: usize) -> Vec<String> {
+        let max_width = max_width.max(1);
+        let mut lines = Vec::new();
+
+        for line in Self::sanitize(text).split('\n') {
+            let line = line.trim_end();
+            if line.width() <= max_width {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let indent: String = line.chars().take_while(|c| *c == ' ').collect();
+            let indent = if indent.width() * 2 > max_width { String::new() } else { indent };
+            let mut current = indent.clone();
+
+            for word in line.split_whitespace() {
+                let has_content = !current.trim().is_empty();
+                if has_content && current.width() + 1 + word.width() > max_width {
+                    lines.push(std::mem::replace(&mut current, indent.clone()));
+                } else if has_content {
+                    current.push(' ');
+                }
+
+                for grapheme in word.graphemes(true) {
+                    if !current.trim().is_empty() && current.width() + grapheme.width() > max_width {
+                        lines.push(std::mem::replace(&mut current, indent.clone()));
+                    }
+                    current.push_str(grapheme);
+                }
+            }
+            if !current.trim().is_empty() {
+                lines.push(current);
+            }
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::DotEscape;
+
+    // 标签中所有的双引号都已转义（前面有奇数个反斜杠）
+    fn has_unescaped_quote(text: &str) -> bool {
+        let mut backslashes = 0;
+        for c in text.chars() {
+            if c == '\\' {
+                backslashes += 1;
+                continue;
+            }
+            if c == '"' && backslashes % 2 == 0 {
+                return true;
+            }
+            backslashes = 0;
+        }
+        false
+    }
+
+    #[test]
+    fn sanitize_replaces_control_characters() {
+        assert_eq!(LabelSanitizer::sanitize("a\u{7}b\u{0}c\u{1b}[0m"), "a\u{FFFD}b\u{FFFD}c\u{FFFD}[0m");
+        assert_eq!(LabelSanitizer::sanitize("a\r\nb\rc"), "a\nb\u{FFFD}c");
+        assert_eq!(LabelSanitizer::sanitize("\tx"), "    x");
+    }
+
+    #[test]
+    fn sanitize_replaces_bidi_overrides() {
+        assert_eq!(LabelSanitizer::sanitize("is_admin\u{202E} \u{2066}// check\u{2069}"), "is_admin\u{FFFD} \u{FFFD}// check\u{FFFD}");
+        assert_eq!(LabelSanitizer::sanitize("a\u{200F}b\u{061C}c"), "a\u{FFFD}b\u{FFFD}c");
+    }
+
+    #[test]
+    fn truncate_keeps_combining_sequences_whole() {
+        let text = "e\u{301}".repeat(4);
+        assert_eq!(LabelSanitizer::truncate(&text, 3), "e\u{301}e\u{301}…");
+        assert_eq!(LabelSanitizer::truncate(&text, 4), text);
+    }
+
+    #[test]
+    fn truncate_counts_wide_characters_as_two_columns() {
+        assert_eq!(LabelSanitizer::truncate("控制流图分析", 7), "控制流…");
+        assert_eq!(LabelSanitizer::truncate("控制流图分析", 12), "控制流图分析");
+        assert_eq!(LabelSanitizer::truncate("a\n  b", 10), "a b");
+    }
+
+    #[test]
+    fn truncate_stops_at_grapheme_boundaries() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let text = format!("{family}{family}{family}");
+        let truncated = LabelSanitizer::truncate(&text, 5);
+        let kept = truncated.strip_suffix('…').expect("text is truncated");
+        assert!(!kept.is_empty());
+        assert_eq!(kept.graphemes(true).filter(|grapheme| *grapheme != family).count(), 0);
+    }
+
+    #[test]
+    fn wrap_breaks_wide_words_between_graphemes() {
+        assert_eq!(LabelSanitizer::wrap("控制流图分析工具", 6), vec!["控制流", "图分析", "工具"]);
+        assert_eq!(LabelSanitizer::wrap("let x = 1;\n    y()", 40), vec!["let x = 1;", "    y()"]);
+    }
+
+    // 用固定种子的 xorshift 生成包含换行、引号、控制字符、双向控制符、组合字符和宽字符的随机标签
+    #[test]
+    fn random_labels_never_leak_newlines_or_quotes() {
+        const ALPHABET: &[&str] = &[
+            "a", "Z", "0", " ", "  ", "\n", "\r\n", "\r", "\t", "\"", "\\", "\\\"", "\\n", "{", "}", "|", "<", ">",
+            "\u{0}", "\u{7}", "\u{1b}", "\u{85}", "\u{2028}", "\u{202E}", "\u{2066}", "\u{301}", "e\u{301}",
+            "中", "控制流", "\u{1F468}\u{200D}\u{1F469}", "…", "Vec<T>", "format!(\"{}\")",
+        ];
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..2000 {
+            let len = next() % 40;
+            let text: String = (0..len).map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize]).collect();
+            let width = 4 + (next() % 40) as usize;
+
+            let truncated = LabelSanitizer::truncate(&text, width);
+            assert!(!truncated.contains(['\n', '\r']), "{text:?}");
+            assert!(truncated.width() <= width, "{text:?} -> {truncated:?}");
+
+            let lines = LabelSanitizer::wrap(&text, width);
+            for line in &lines {
+                assert!(!line.contains(['\n', '\r']), "{text:?}");
+                assert!(line.width() <= width, "{text:?} -> {line:?}");
+            }
+
+            let label = DotEscape::lines(&lines);
+            assert!(!label.contains(['\n', '\r']), "{text:?} -> {label:?}");
+            assert!(!has_unescaped_quote(&label), "{text:?} -> {label:?}");
+        }
+    }
+}
@@ -1,5 +1,13 @@
 mod node_style;
 mod edge_style;
+mod label;
+mod escape;
+mod highlight;
+mod theme;
 
 pub use node_style::NodeStyle;
-pub use edge_style::EdgeStyle; 
\ No newline at end of file
+pub use edge_style::EdgeStyle;
+pub use label::{LabelSanitizer, MAX_LINE_WIDTH};
+pub use escape::{D2Escape, DotEscape, MermaidEscape, PlantUmlEscape};
+pub use highlight::SyntaxHighlighter;
+pub use theme::{EdgeTheme, FontTheme, NodeTheme, Theme, BUILTIN_THEMES}; 
\ No newline at end of file
@@ -1,50 +1,83 @@
-use crate::graph::NodeType;
+use crate::graph::{Labels, NodeType, TypeKind};
+use crate::style::{NodeTheme, Theme};
 
+// 节点的形状、样式和颜色取自主题，见 Theme
 pub struct NodeStyle;
 
 impl NodeStyle {
-    pub fn get_shape(node: &NodeType) -> String {
+    // 节点在主题 `nodes` 表中的键，比 NodeType::kind 更细：区分测试函数、成员包和类型种类
+    pub fn theme_key(node: &NodeType) -> &'static str {
         match node {
-            NodeType::Start(_, _) => "oval".to_string(),
-            NodeType::End(_, _) => "oval".to_string(),
-            NodeType::BasicBlock(_) => "box".to_string(),
-            NodeType::Condition(_) => "diamond".to_string(),
-            NodeType::Loop(_) => "hexagon".to_string(),
+            NodeType::Start(_, true) => "test_start",
+            NodeType::End(_, true) => "test_end",
+            NodeType::Package(_, true) => "member_package",
+            NodeType::Type(TypeKind::Struct, _) => "struct",
+            NodeType::Type(TypeKind::Enum, _) => "enum",
+            NodeType::Type(TypeKind::Trait, _) => "trait",
+            node => node.kind(),
         }
     }
 
-    pub fn get_style(node: &NodeType) -> String {
-        match node {
-            NodeType::Start(_, _) | NodeType::End(_, _) => "filled".to_string(),
-            NodeType::Condition(_) => "filled".to_string(),
-            NodeType::Loop(_) => "filled".to_string(),
-            NodeType::BasicBlock(_) => "filled".to_string(),
+    pub fn get(node: &NodeType, theme: &Theme) -> NodeTheme {
+        theme.node(Self::theme_key(node))
+    }
+
+    pub fn get_entry_fillcolor(theme: &Theme) -> String {
+        theme.entry_fillcolor.clone()
+    }
+
+    // 按到入口函数的调用距离着色，越远越浅，不可达为灰色
+    pub fn get_distance_fillcolor(distance: Option<usize>, theme: &Theme) -> String {
+        match distance {
+            Some(0) => Self::get_entry_fillcolor(theme),
+            Some(distance) => Self::bucket(&theme.distance_fillcolors, distance - 1),
+            None => theme.unreachable_fillcolor.clone(),
+        }
+    }
+
+    // 修改越频繁颜色越深
+    pub fn get_churn_fillcolor(commits: usize, theme: &Theme) -> String {
+        let bucket = match commits {
+            0 => 0,
+            1 => 1,
+            2..=3 => 2,
+            4..=6 => 3,
+            _ => 4,
+        };
+        Self::bucket(&theme.churn_fillcolors, bucket)
+    }
+
+    // 复杂度和修改频率都高的函数用红色粗边框标出
+    pub fn get_risk_color(theme: &Theme) -> String {
+        theme.risk_color.clone()
+    }
+
+    // 色阶不够长时使用最后一个颜色
+    fn bucket(colors: &[String], index: usize) -> String {
+        colors.get(index).or(colors.last()).cloned().unwrap_or_else(|| "white".to_string())
+    }
+
+    // Graphviz 的 `grayNN` 在 CSS、PlantUML 等其他格式中不是合法的颜色名，换算为十六进制
+    pub fn css_color(color: &str) -> String {
+        let level = color.strip_prefix("gray").or_else(|| color.strip_prefix("grey"));
+        match level.and_then(|level| level.parse::<u32>().ok()) {
+            Some(level) if level <= 100 => {
+                let value = (level * 255 + 50) / 100;
+                format!("#{:02x}{:02x}{:02x}", value, value, value)
+            }
+            _ => color.to_string(),
         }
     }
 
-    pub fn get_fillcolor(node: &NodeType) -> String {
+    pub fn get_label(node: &NodeType, labels: &Labels) -> String {
         match node {
-            NodeType::Start(_, is_test) => {
-                if *is_test {
-                    "palegreen".to_string()
-                } else {
-                    "lightgreen".to_string()
-                }
-            },
-            NodeType::End(_, is_test) => {
-                if *is_test {
-                    "mistyrose".to_string()
-                } else {
-                    "lightpink".to_string()
-                }
-            },
-            NodeType::BasicBlock(_) => "lightblue".to_string(),
-            NodeType::Condition(_) => "lightyellow".to_string(),
-            NodeType::Loop(_) => "lightgray".to_string(),
+            NodeType::Start(name, _) => format!("{}: {}", labels.start, name),
+            NodeType::End(name, _) => format!("{}: {}", labels.end, name),
+            node => node.label(),
         }
     }
 
-    pub fn get_label(node: &NodeType) -> String {
-        node.label()
+    pub fn get_tooltip(node: &NodeType) -> Option<String> {
+        node.tooltip()
     }
 } 
\ No newline at end of file
@@ -0,0 +1,373 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// 节点的形状、样式和颜色，键见 NodeStyle::theme_key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeTheme {
+    pub shape: String,
+    pub style: String,
+    pub fillcolor: String,
+    // 边框颜色
+    pub color: String,
+}
+
+impl Default for NodeTheme {
+    fn default() -> Self {
+        Self::new("box", "filled", "white", "black")
+    }
+}
+
+impl NodeTheme {
+    fn new(shape: &str, style: &str, fillcolor: &str, color: &str) -> Self {
+        Self {
+            shape: shape.to_string(),
+            style: style.to_string(),
+            fillcolor: fillcolor.to_string(),
+            color: color.to_string(),
+        }
+    }
+}
+
+// 边的颜色和线型，键见 EdgeStyle::kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeTheme {
+    pub color: String,
+    pub style: String,
+}
+
+impl Default for EdgeTheme {
+    fn default() -> Self {
+        Self::new("black", "solid")
+    }
+}
+
+impl EdgeTheme {
+    fn new(color: &str, style: &str) -> Self {
+        Self { color: color.to_string(), style: style.to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FontTheme {
+    pub name: String,
+    pub size: u32,
+    pub edge_size: u32,
+    pub color: String,
+}
+
+impl Default for FontTheme {
+    fn default() -> Self {
+        Self { name: "Arial".to_string(), size: 10, edge_size: 9, color: "black".to_string() }
+    }
+}
+
+// 图的配色方案。内置 light（默认）、dark 和 high-contrast 三套，
+// 也可以从 TOML 文件加载：文件中的值覆盖 `extends` 指定的内置主题（默认 light）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub background: String,
+    pub font: FontTheme,
+    // 闭包和嵌套函数分组框的颜色
+    pub cluster_color: String,
+    pub nodes: BTreeMap<String, NodeTheme>,
+    // 没有单独配置的边使用 `edge`
+    pub edge: EdgeTheme,
+    pub edges: BTreeMap<String, EdgeTheme>,
+    // 入口函数开始/结束节点的填充色
+    pub entry_fillcolor: String,
+    // 复杂度和修改频率都高的函数的边框颜色
+    pub risk_color: String,
+    // --highlight-loops 时循环头的边框颜色，回边使用 `back_edge` 边样式
+    pub loop_header_color: String,
+    // cargo graph diff 中新增、删除和发生变化的节点填充色及边的颜色
+    pub added_color: String,
+    pub removed_color: String,
+    pub changed_color: String,
+    // 到入口函数的调用距离为 1、2、3、更远时的填充色
+    pub distance_fillcolors: Vec<String>,
+    // 从入口函数不可达的函数
+    pub unreachable_fillcolor: String,
+    // 修改次数为 0、1、2-3、4-6、更多时的填充色
+    pub churn_fillcolors: Vec<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+pub const BUILTIN_THEMES: [&str; 3] = ["light", "dark", "high-contrast"];
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::light()),
+            "dark" => Some(Self::dark()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    // 参数是内置主题名称或 TOML 主题文件的路径
+    pub fn load(spec: &str) -> Result<Self> {
+        if let Some(theme) = Self::builtin(spec) {
+            return Ok(theme);
+        }
+        let path = Path::new(spec);
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read theme {} (built-in themes: {})", spec, BUILTIN_THEMES.join(", ")))?;
+        let mut overrides: toml::Table = source.parse()
+            .with_context(|| format!("Invalid theme file: {}", path.display()))?;
+
+        let base = match overrides.remove("extends") {
+            Some(toml::Value::String(name)) => Self::builtin(&name)
+                .ok_or_else(|| anyhow!("Unknown built-in theme `{}` in {}", name, path.display()))?,
+            Some(_) => bail!("`extends` in {} must be the name of a built-in theme", path.display()),
+            None => Self::light(),
+        };
+        let toml::Value::Table(mut merged) = toml::Value::try_from(&base)? else {
+            bail!("Failed to serialize the base theme");
+        };
+        Self::merge(&mut merged, overrides);
+        toml::Value::Table(merged).try_into()
+            .with_context(|| format!("Invalid theme file: {}", path.display()))
+    }
+
+    // 逐层合并表，只替换文件中出现的值
+    fn merge(base: &mut toml::Table, overrides: toml::Table) {
+        for (key, value) in overrides {
+            match (base.get_mut(&key), value) {
+                (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => Self::merge(base, overrides),
+                (_, value) => {
+                    base.insert(key, value);
+                }
+            }
+        }
+    }
+
+    pub fn node(&self, key: &str) -> NodeTheme {
+        self.nodes.get(key).cloned().unwrap_or_default()
+    }
+
+    pub fn edge(&self, kind: Option<&str>) -> EdgeTheme {
+        kind.and_then(|kind| self.edges.get(kind)).unwrap_or(&self.edge).clone()
+    }
+
+    pub fn light() -> Self {
+        let nodes = [
+            ("start", NodeTheme::new("oval", "filled", "lightgreen", "black")),
+            ("test_start", NodeTheme::new("oval", "filled", "palegreen", "black")),
+            ("end", NodeTheme::new("oval", "filled", "lightpink", "black")),
+            ("test_end", NodeTheme::new("oval", "filled", "mistyrose", "black")),
+            ("basic_block", NodeTheme::new("box", "filled", "lightblue", "black")),
+            ("condition", NodeTheme::new("diamond", "filled", "lightyellow", "black")),
+            ("loop", NodeTheme::new("hexagon", "filled", "lightgray", "black")),
+            ("unreachable", NodeTheme::new("box", "filled,dashed", "gainsboro", "black")),
+            ("declarations", NodeTheme::new("note", "filled", "lightcyan", "black")),
+            ("await", NodeTheme::new("parallelogram", "filled", "plum", "black")),
+            ("abort", NodeTheme::new("octagon", "filled,bold", "salmon", "black")),
+            ("unsafe", NodeTheme::new("box", "filled,bold", "lightblue", "red")),
+            ("module", NodeTheme::new("folder", "filled", "wheat", "black")),
+            ("package", NodeTheme::new("component", "filled", "lightblue", "black")),
+            ("member_package", NodeTheme::new("component", "filled,bold", "gold", "black")),
+            ("duplicate_package", NodeTheme::new("component", "filled", "orange", "black")),
+            ("denied_package", NodeTheme::new("component", "filled,bold", "salmon", "red")),
+            ("struct", NodeTheme::new("box", "filled", "lightblue", "black")),
+            ("enum", NodeTheme::new("box", "filled", "khaki", "black")),
+            ("trait", NodeTheme::new("box", "rounded,filled", "palegreen", "black")),
+            ("function", NodeTheme::new("box", "rounded,filled", "lightblue", "black")),
+            ("root_function", NodeTheme::new("box", "rounded,filled,bold", "gold", "black")),
+            ("external", NodeTheme::new("box", "dashed", "white", "gray50")),
+        ];
+        let edges = [
+            ("true", EdgeTheme::new("green", "solid")),
+            ("false", EdgeTheme::new("red", "solid")),
+            ("continue", EdgeTheme::new("blue", "dashed")),
+            ("break", EdgeTheme::new("red", "dashed")),
+            ("default", EdgeTheme::new("darkorange", "dashed")),
+            ("dev", EdgeTheme::new("gray50", "dashed")),
+            ("build", EdgeTheme::new("darkorange", "dashed")),
+            ("optional", EdgeTheme::new("black", "dotted")),
+            ("implements", EdgeTheme::new("darkgreen", "solid")),
+            ("bound", EdgeTheme::new("gray50", "dashed")),
+            ("mod", EdgeTheme::new("gray", "dashed")),
+            ("cycle", EdgeTheme::new("red", "bold")),
+            ("recursion", EdgeTheme::new("purple", "dashed")),
+            ("external_call", EdgeTheme::new("gray60", "dashed")),
+            ("back_edge", EdgeTheme::new("blue", "bold")),
+        ];
+        Self {
+            background: "white".to_string(),
+            font: FontTheme::default(),
+            cluster_color: "gray".to_string(),
+            nodes: nodes.into_iter().map(|(key, node)| (key.to_string(), node)).collect(),
+            edge: EdgeTheme::default(),
+            edges: edges.into_iter().map(|(key, edge)| (key.to_string(), edge)).collect(),
+            entry_fillcolor: "gold".to_string(),
+            risk_color: "red".to_string(),
+            loop_header_color: "blue".to_string(),
+            added_color: "#74c476".to_string(),
+            removed_color: "#fb6a4a".to_string(),
+            changed_color: "#fdd835".to_string(),
+            distance_fillcolors: ["#74c476", "#a1d99b", "#c7e9c0", "#e5f5e0"].map(String::from).to_vec(),
+            unreachable_fillcolor: "gray".to_string(),
+            churn_fillcolors: ["#fff5f0", "#fee0d2", "#fc9272", "#ef3b2c", "#a50f15"].map(String::from).to_vec(),
+        }
+    }
+
+    // 深色背景，填充色取较暗的色调，文字和边框为浅色
+    pub fn dark() -> Self {
+        let mut theme = Self::light();
+        theme.background = "#1e1e1e".to_string();
+        theme.font.color = "#e0e0e0".to_string();
+        theme.cluster_color = "#888888".to_string();
+        let fills = [
+            ("start", "#2e6b34"),
+            ("test_start", "#3b5e3e"),
+            ("end", "#7a2a48"),
+            ("test_end", "#5e3a45"),
+            ("basic_block", "#1f4460"),
+            ("condition", "#6b5d1c"),
+            ("loop", "#4a4a4a"),
+            ("unreachable", "#333333"),
+            ("declarations", "#1d5454"),
+            ("await", "#563a66"),
+            ("abort", "#803628"),
+            ("unsafe", "#1f4460"),
+            ("module", "#5e4f33"),
+            ("package", "#1f4460"),
+            ("member_package", "#7a6000"),
+            ("duplicate_package", "#8a4b00"),
+            ("denied_package", "#803628"),
+            ("struct", "#1f4460"),
+            ("enum", "#5e5a24"),
+            ("trait", "#2e5e2e"),
+            ("function", "#1f4460"),
+            ("root_function", "#7a6000"),
+            ("external", "#1e1e1e"),
+        ];
+        for (key, fill) in fills {
+            if let Some(node) = theme.nodes.get_mut(key) {
+                node.fillcolor = fill.to_string();
+                node.color = match key {
+                    "unsafe" | "denied_package" => "#ff5555",
+                    "external" => "#888888",
+                    _ => "#c0c0c0",
+                }.to_string();
+            }
+        }
+        theme.edge = EdgeTheme::new("#c0c0c0", "solid");
+        let colors = [
+            ("true", "#66bb6a"),
+            ("false", "#ef5350"),
+            ("continue", "#64b5f6"),
+            ("break", "#ef5350"),
+            ("default", "#ffa726"),
+            ("dev", "#9e9e9e"),
+            ("build", "#ffa726"),
+            ("optional", "#c0c0c0"),
+            ("implements", "#81c784"),
+            ("bound", "#9e9e9e"),
+            ("mod", "#9e9e9e"),
+            ("cycle", "#ef5350"),
+            ("recursion", "#ce93d8"),
+            ("external_call", "#777777"),
+            ("back_edge", "#64b5f6"),
+        ];
+        for (kind, color) in colors {
+            if let Some(edge) = theme.edges.get_mut(kind) {
+                edge.color = color.to_string();
+            }
+        }
+        theme.entry_fillcolor = "#8a6d00".to_string();
+        theme.risk_color = "#ff5555".to_string();
+        theme.loop_header_color = "#64b5f6".to_string();
+        theme.added_color = "#2e7d32".to_string();
+        theme.removed_color = "#b71c1c".to_string();
+        theme.changed_color = "#8d7b00".to_string();
+        theme.distance_fillcolors = ["#2e7d32", "#276128", "#1f4a20", "#183a19"].map(String::from).to_vec();
+        theme.unreachable_fillcolor = "#555555".to_string();
+        theme.churn_fillcolors = ["#2a2222", "#4a1c1c", "#7a1f1f", "#b02a2a", "#e53935"].map(String::from).to_vec();
+        theme
+    }
+
+    // 白底黑字、加粗边框、更大的字号，颜色只用高饱和度的纯色，便于投影和打印
+    pub fn high_contrast() -> Self {
+        let mut theme = Self::light();
+        theme.font = FontTheme { name: "Arial".to_string(), size: 12, edge_size: 11, color: "black".to_string() };
+        theme.cluster_color = "black".to_string();
+        let fills = [
+            ("start", "#00ff00"),
+            ("test_start", "#00ff00"),
+            ("end", "#ff66cc"),
+            ("test_end", "#ff66cc"),
+            ("basic_block", "white"),
+            ("condition", "yellow"),
+            ("loop", "cyan"),
+            ("unreachable", "#cccccc"),
+            ("declarations", "white"),
+            ("await", "#cc99ff"),
+            ("abort", "#ff3333"),
+            ("unsafe", "white"),
+            ("module", "#ffcc00"),
+            ("package", "white"),
+            ("member_package", "#ffcc00"),
+            ("duplicate_package", "#ff9900"),
+            ("denied_package", "#ff3333"),
+            ("struct", "white"),
+            ("enum", "yellow"),
+            ("trait", "#00ff00"),
+            ("function", "white"),
+            ("root_function", "#ffcc00"),
+            ("external", "white"),
+        ];
+        for (key, fill) in fills {
+            if let Some(node) = theme.nodes.get_mut(key) {
+                node.fillcolor = fill.to_string();
+                if !node.style.contains("bold") {
+                    node.style.push_str(",bold");
+                }
+                if node.color != "red" {
+                    node.color = "black".to_string();
+                }
+            }
+        }
+        let colors = [
+            ("true", "#006400"),
+            ("false", "#cc0000"),
+            ("continue", "#0000cc"),
+            ("break", "#cc0000"),
+            ("default", "#b35900"),
+            ("dev", "black"),
+            ("build", "#b35900"),
+            ("implements", "#006400"),
+            ("bound", "black"),
+            ("mod", "black"),
+            ("cycle", "#cc0000"),
+            ("recursion", "#6600cc"),
+            ("external_call", "black"),
+            ("back_edge", "#0000cc"),
+        ];
+        for (kind, color) in colors {
+            if let Some(edge) = theme.edges.get_mut(kind) {
+                edge.color = color.to_string();
+            }
+        }
+        theme.entry_fillcolor = "#ffcc00".to_string();
+        theme.loop_header_color = "#0000cc".to_string();
+        theme.added_color = "#00cc00".to_string();
+        theme.removed_color = "#ff0000".to_string();
+        theme.changed_color = "#ffff00".to_string();
+        theme.distance_fillcolors = ["#00cc00", "#66ff66", "#ccffcc", "white"].map(String::from).to_vec();
+        theme.unreachable_fillcolor = "#999999".to_string();
+        theme.churn_fillcolors = ["white", "#ffcccc", "#ff6666", "#ff0000", "#990000"].map(String::from).to_vec();
+        theme
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+// 流水线中各个 pass 的先后顺序，用于汇总输出
+const PASS_ORDER: [&str; 9] = [
+    "walk", "cache", "parse", "collect", "analyze", "merge", "style", "render", "graphviz",
+];
+
+#[derive(Debug, Clone)]
+pub struct PassTiming {
+    pub pass: &'static str,
+    pub elapsed: Duration,
+    pub count: usize,
+}
+
+// 各个 pass 的耗时与处理数量，同名 pass 会累加
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    passes: Vec<PassTiming>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, pass: &'static str, elapsed: Duration, count: usize) {
+        if let Some(entry) = self.passes.iter_mut().find(|t| t.pass == pass) {
+            entry.elapsed += elapsed;
+            entry.count += count;
+        } else {
+            self.passes.push(PassTiming { pass, elapsed, count });
+        }
+    }
+
+    pub fn record_since(&mut self, pass: &'static str, start: Instant, count: usize) {
+        self.record(pass, start.elapsed(), count);
+    }
+
+    pub fn merge(&mut self, other: &Timings) {
+        for timing in &other.passes {
+            self.record(timing.pass, timing.elapsed, timing.count);
+        }
+    }
+
+    pub fn passes(&self) -> &[PassTiming] {
+        &self.passes
+    }
+
+    pub fn total(&self) -> Duration {
+        self.passes.iter().map(|t| t.elapsed).sum()
+    }
+}
+
+// 按文件记录的耗时，外加全局 pass（walk/merge/graphviz）
+#[derive(Debug, Clone, Default)]
+pub struct TimingReport {
+    pub files: Vec<(String, Timings)>,
+    pub global: Timings,
+}
+
+impl TimingReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn aggregate(&self) -> Timings {
+        let mut total = self.global.clone();
+        for (_, timings) in &self.files {
+            total.merge(timings);
+        }
+        total.passes.sort_by_key(|t| {
+            PASS_ORDER.iter().position(|&p| p == t.pass).unwrap_or(PASS_ORDER.len())
+        });
+        total
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        for (file, timings) in &self.files {
+            let _ = writeln!(out, "{} ({:.2?})", file, timings.total());
+            Self::render_passes(&mut out, timings);
+        }
+
+        let aggregate = self.aggregate();
+        let _ = writeln!(out, "Total ({:.2?})", aggregate.total());
+        Self::render_passes(&mut out, &aggregate);
+        out
+    }
+
+    fn render_passes(out: &mut String, timings: &Timings) {
+        for timing in timings.passes() {
+            let _ = writeln!(
+                out,
+                "    {:<10} {:>12.2?} {:>8}",
+                timing.pass, timing.elapsed, timing.count
+            );
+        }
+    }
+}